@@ -0,0 +1,9 @@
+-- Add down migration script here
+ALTER TABLE matches
+DROP COLUMN startgg_set_id;
+
+ALTER TABLE scoreboard
+DROP COLUMN startgg_set_id;
+
+ALTER TABLE stream_overlay
+DROP COLUMN startgg_sync;
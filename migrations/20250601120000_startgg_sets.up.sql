@@ -0,0 +1,11 @@
+-- Add up migration script here
+-- The same set can be synced into more than one overlay
+ALTER TABLE matches
+ADD startgg_set_id VARCHAR,
+ADD CONSTRAINT matches_overlay_startgg_set_key UNIQUE (overlay_id, startgg_set_id);
+
+ALTER TABLE scoreboard
+ADD startgg_set_id VARCHAR;
+
+ALTER TABLE stream_overlay
+ADD startgg_sync BOOLEAN NOT NULL DEFAULT FALSE;
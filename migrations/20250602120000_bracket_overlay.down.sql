@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE stream_overlay
+DROP COLUMN bracket_phase_group_id;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE stream_overlay
+ADD bracket_phase_group_id VARCHAR;
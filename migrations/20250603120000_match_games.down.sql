@@ -0,0 +1,8 @@
+-- Add down migration script here
+DROP TABLE match_games;
+
+ALTER TABLE matches
+DROP COLUMN best_of;
+
+ALTER TABLE scoreboard
+DROP COLUMN best_of;
@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE "match_games" (
+  "match_id" uuid NOT NULL REFERENCES matches (id) ON DELETE CASCADE,
+  "game_number" INTEGER NOT NULL,
+  "winner" VARCHAR NOT NULL,
+  "team_a_goals" INTEGER NOT NULL DEFAULT 0,
+  "team_b_goals" INTEGER NOT NULL DEFAULT 0,
+  "arena" VARCHAR,
+  "duration_seconds" INTEGER,
+  "created_at" TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  PRIMARY KEY (match_id, game_number)
+);
+
+ALTER TABLE matches
+ADD best_of INTEGER NOT NULL DEFAULT 3;
+
+ALTER TABLE scoreboard
+ADD best_of INTEGER NOT NULL DEFAULT 3;
@@ -0,0 +1,17 @@
+-- Add up migration script here
+CREATE TABLE "team_member" (
+  "team_id" VARCHAR NOT NULL REFERENCES team (id) ON DELETE CASCADE,
+  "gamer_tag" VARCHAR NOT NULL,
+  "prefix" VARCHAR,
+  "captain" BOOLEAN NOT NULL DEFAULT false,
+  "alternate" BOOLEAN NOT NULL DEFAULT false,
+  "discord_id" VARCHAR NOT NULL DEFAULT '',
+  PRIMARY KEY (team_id, gamer_tag)
+);
+
+CREATE TABLE "lineup" (
+  "overlay_id" uuid NOT NULL REFERENCES stream_overlay (id) ON DELETE CASCADE,
+  "team_id" VARCHAR NOT NULL REFERENCES team (id) ON DELETE CASCADE,
+  "gamer_tags" VARCHAR[] NOT NULL DEFAULT '{}',
+  PRIMARY KEY (overlay_id, team_id)
+);
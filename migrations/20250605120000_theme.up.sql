@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE "theme" (
+  "tournament_slug" VARCHAR PRIMARY KEY,
+  "primary_color" VARCHAR NOT NULL DEFAULT '#4180B0',
+  "secondary_color" VARCHAR NOT NULL DEFAULT '#878787',
+  "background_color" VARCHAR NOT NULL DEFAULT '#1D1D1D',
+  "panel_color" VARCHAR NOT NULL DEFAULT '#121212',
+  "surface_color" VARCHAR NOT NULL DEFAULT '#323232',
+  "text_color" VARCHAR NOT NULL DEFAULT '#FFFFFF',
+  "team_a_color" VARCHAR NOT NULL DEFAULT '#6592FD',
+  "team_b_color" VARCHAR NOT NULL DEFAULT '#B93D55',
+  "font_family" VARCHAR NOT NULL DEFAULT 'Montserrat',
+  "logo" VARCHAR NOT NULL DEFAULT '/assets/SASL-with-text.png',
+  "background_image" VARCHAR NOT NULL DEFAULT '/assets/background-logo.png',
+  "swap_scoreboard_sides" BOOLEAN NOT NULL DEFAULT false
+);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE casters
+DROP COLUMN image;
+
+DROP TABLE asset;
@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE "asset" (
+  "id" uuid DEFAULT gen_random_uuid() PRIMARY KEY,
+  "tournament_slug" VARCHAR NOT NULL,
+  "filename" VARCHAR NOT NULL,
+  "content_type" VARCHAR NOT NULL,
+  "size" INTEGER NOT NULL,
+  -- Empty when the asset is stored in the configured local directory
+  "data" BYTEA,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX asset_tournament_slug_idx ON asset (tournament_slug);
+
+ALTER TABLE casters
+ADD image VARCHAR;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE "obs_config" (
+  "overlay_id" uuid PRIMARY KEY REFERENCES stream_overlay (id) ON DELETE CASCADE,
+  "enabled" BOOLEAN NOT NULL DEFAULT false,
+  "url" VARCHAR NOT NULL DEFAULT 'ws://127.0.0.1:4455',
+  "password" VARCHAR,
+  "starting_scene" VARCHAR,
+  "break_scene" VARCHAR,
+  "ending_scene" VARCHAR,
+  "live_scene" VARCHAR,
+  "scoreboard_source" VARCHAR
+);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE "api_token" (
+  "id" uuid DEFAULT gen_random_uuid() PRIMARY KEY,
+  "tournament_slug" VARCHAR NOT NULL,
+  "name" VARCHAR NOT NULL,
+  -- SHA-256 of the token, which is only shown once when created
+  "token_hash" VARCHAR NOT NULL UNIQUE,
+  "scopes" TEXT[] NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  "last_used_at" TIMESTAMPTZ
+);
+
+CREATE INDEX api_token_tournament_slug_idx ON api_token (tournament_slug);
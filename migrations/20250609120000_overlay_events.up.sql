@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE "overlay_events" (
+  "id" BIGSERIAL PRIMARY KEY,
+  "overlay_id" uuid NOT NULL REFERENCES stream_overlay (id) ON DELETE CASCADE,
+  -- start.gg user, API token or sync that made the change
+  "actor" VARCHAR NOT NULL,
+  "kind" VARCHAR NOT NULL,
+  "before" JSONB NOT NULL,
+  "after" JSONB NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  "undone_at" TIMESTAMPTZ,
+  "undone_by" VARCHAR
+);
+
+CREATE INDEX overlay_events_overlay_id_idx ON overlay_events (overlay_id, id);
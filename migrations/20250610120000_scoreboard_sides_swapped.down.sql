@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE scoreboard
+DROP COLUMN sides_swapped;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE scoreboard
+ADD COLUMN sides_swapped BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP TABLE organization_tournament;
+DROP TABLE organization_invite;
+DROP TABLE organization_member;
+DROP TABLE organization;
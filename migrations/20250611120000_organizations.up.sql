@@ -0,0 +1,34 @@
+-- Add up migration script here
+CREATE TABLE "organization" (
+  "id" uuid DEFAULT gen_random_uuid() PRIMARY KEY,
+  "name" VARCHAR NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Members are start.gg users, identified by their user slug
+CREATE TABLE "organization_member" (
+  "organization_id" uuid NOT NULL REFERENCES organization (id) ON DELETE CASCADE,
+  "user_slug" VARCHAR NOT NULL,
+  "name" VARCHAR NOT NULL,
+  "role" VARCHAR NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY ("organization_id", "user_slug")
+);
+
+CREATE INDEX organization_member_user_slug_idx ON organization_member (user_slug);
+
+CREATE TABLE "organization_invite" (
+  "id" uuid DEFAULT gen_random_uuid() PRIMARY KEY,
+  "organization_id" uuid NOT NULL REFERENCES organization (id) ON DELETE CASCADE,
+  "code" VARCHAR NOT NULL UNIQUE,
+  "role" VARCHAR NOT NULL,
+  "created_by" VARCHAR NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  "expires_at" TIMESTAMPTZ NOT NULL
+);
+
+-- A tournament is managed by a single organization
+CREATE TABLE "organization_tournament" (
+  "tournament_slug" VARCHAR PRIMARY KEY,
+  "organization_id" uuid NOT NULL REFERENCES organization (id) ON DELETE CASCADE
+);
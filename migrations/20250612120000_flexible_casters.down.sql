@@ -0,0 +1,18 @@
+-- Add down migration script here
+DELETE FROM casters WHERE position > 1;
+
+ALTER TABLE casters
+DROP CONSTRAINT casters_pkey;
+
+UPDATE casters SET role = CASE WHEN position = 0 THEN 'narrator' ELSE 'commenter' END;
+
+ALTER TABLE casters
+DROP COLUMN position,
+DROP COLUMN handle,
+DROP COLUMN pronouns;
+
+ALTER TABLE casters
+RENAME COLUMN role TO kind;
+
+ALTER TABLE casters
+ADD PRIMARY KEY (overlay_id, kind);
@@ -0,0 +1,18 @@
+-- Add up migration script here
+ALTER TABLE casters
+DROP CONSTRAINT casters_pkey;
+
+-- Casters are an ordered list, with any role
+ALTER TABLE casters
+RENAME COLUMN kind TO role;
+
+ALTER TABLE casters
+ADD position INTEGER NOT NULL DEFAULT 0,
+ADD handle VARCHAR,
+ADD pronouns VARCHAR;
+
+UPDATE casters SET position = 1, role = 'Comentarista' WHERE role = 'commenter';
+UPDATE casters SET role = 'Narrador' WHERE role = 'narrator';
+
+ALTER TABLE casters
+ADD PRIMARY KEY (overlay_id, position);
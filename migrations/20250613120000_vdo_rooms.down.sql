@@ -0,0 +1,12 @@
+-- Add down migration script here
+UPDATE casters
+SET stream_video = 'https://vdo.ninja/?view=' || push_id || '&solo&room='
+  || substr(replace(overlay_id, '-', ''), 1, 12)
+  || substr(replace(overlay_id, '-', ''), 14, 3)
+  || substr(replace(overlay_id, '-', ''), 18)
+WHERE push_id IS NOT NULL AND stream_video = '';
+
+ALTER TABLE casters
+DROP COLUMN push_id;
+
+DROP TABLE vdo_room;
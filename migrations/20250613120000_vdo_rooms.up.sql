@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE "vdo_room" (
+  "overlay_id" uuid PRIMARY KEY REFERENCES stream_overlay (id) ON DELETE CASCADE,
+  "password" VARCHAR NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+ALTER TABLE casters
+ADD push_id VARCHAR;
+
+-- Casters already in the overlay's room keep their push ID, their view link is generated from now on
+UPDATE casters
+SET push_id = substring(stream_video from '[?&]view=([^&]+)'), stream_video = ''
+WHERE stream_video LIKE 'https://vdo.ninja/%view=%';
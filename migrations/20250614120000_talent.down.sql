@@ -0,0 +1,7 @@
+-- Add down migration script here
+DROP TABLE talent_appearance;
+
+ALTER TABLE casters
+DROP COLUMN talent_id;
+
+DROP TABLE talent;
@@ -0,0 +1,30 @@
+-- Add up migration script here
+-- People who work the broadcasts of the organization, reused in the casters of any overlay
+CREATE TABLE "talent" (
+  "id" uuid DEFAULT gen_random_uuid() PRIMARY KEY,
+  "organization_id" uuid NOT NULL REFERENCES organization (id) ON DELETE CASCADE,
+  "name" VARCHAR NOT NULL,
+  "handle" VARCHAR,
+  "pronouns" VARCHAR,
+  "image" VARCHAR,
+  "push_id" VARCHAR NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX talent_organization_id_idx ON talent (organization_id);
+
+ALTER TABLE casters
+ADD talent_id uuid REFERENCES talent (id) ON DELETE SET NULL;
+
+-- A broadcast the talent worked, one per overlay and day. Kept when the overlay is deleted.
+CREATE TABLE "talent_appearance" (
+  "talent_id" uuid NOT NULL REFERENCES talent (id) ON DELETE CASCADE,
+  "overlay_id" uuid NOT NULL,
+  "day" DATE NOT NULL DEFAULT CURRENT_DATE,
+  "tournament_slug" VARCHAR NOT NULL,
+  "overlay_name" VARCHAR,
+  "role" VARCHAR NOT NULL,
+  "first_seen_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  "last_seen_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY ("talent_id", "overlay_id", "day")
+);
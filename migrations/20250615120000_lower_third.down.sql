@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE lower_third;
+
+DROP TABLE lower_third_message;
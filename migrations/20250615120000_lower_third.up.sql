@@ -0,0 +1,22 @@
+-- Add up migration script here
+-- Messages of the lower third and ticker overlay, shown in the order of their position
+CREATE TABLE "lower_third_message" (
+  "id" uuid DEFAULT gen_random_uuid() PRIMARY KEY,
+  "overlay_id" uuid NOT NULL REFERENCES stream_overlay (id) ON DELETE CASCADE,
+  "kind" VARCHAR NOT NULL,
+  "title" VARCHAR NOT NULL,
+  "subtitle" VARCHAR,
+  "position" INTEGER NOT NULL DEFAULT 0,
+  "duration_seconds" INTEGER NOT NULL DEFAULT 10,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX lower_third_message_overlay_id_idx ON lower_third_message (overlay_id);
+
+CREATE TABLE "lower_third" (
+  "overlay_id" uuid PRIMARY KEY REFERENCES stream_overlay (id) ON DELETE CASCADE,
+  "lower_third_on_air" BOOLEAN NOT NULL DEFAULT false,
+  "ticker_on_air" BOOLEAN NOT NULL DEFAULT false,
+  "pinned_message_id" uuid REFERENCES lower_third_message (id) ON DELETE SET NULL,
+  "rotation_started_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
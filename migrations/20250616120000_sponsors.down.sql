@@ -0,0 +1,7 @@
+-- Add down migration script here
+ALTER TABLE stream_overlay
+DROP sponsor_token;
+
+DROP TABLE sponsor_impression;
+
+DROP TABLE sponsor;
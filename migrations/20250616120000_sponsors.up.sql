@@ -0,0 +1,31 @@
+-- Add up migration script here
+-- Sponsors of the tournament, rotated in the sponsor slot of the overlays
+CREATE TABLE "sponsor" (
+  "id" uuid DEFAULT gen_random_uuid() PRIMARY KEY,
+  "tournament_slug" VARCHAR NOT NULL,
+  "name" VARCHAR NOT NULL,
+  "logo" VARCHAR NOT NULL,
+  -- How many times the sponsor is shown in each turn of the rotation
+  "weight" INTEGER NOT NULL DEFAULT 1,
+  "starts_at" TIMESTAMPTZ,
+  "ends_at" TIMESTAMPTZ,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX sponsor_tournament_slug_idx ON sponsor (tournament_slug);
+
+-- Time the sponsor was on screen, added up per overlay, slot and day. Kept when the overlay is deleted.
+CREATE TABLE "sponsor_impression" (
+  "sponsor_id" uuid NOT NULL REFERENCES sponsor (id) ON DELETE CASCADE,
+  "overlay_id" uuid NOT NULL,
+  "slot" VARCHAR NOT NULL,
+  "day" DATE NOT NULL DEFAULT CURRENT_DATE,
+  "impressions" INTEGER NOT NULL DEFAULT 0,
+  "seconds" INTEGER NOT NULL DEFAULT 0,
+  "last_reported_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY ("sponsor_id", "overlay_id", "slot", "day")
+);
+
+-- Embedded in the overlay pages, which send it back with the impressions
+ALTER TABLE stream_overlay
+ADD sponsor_token uuid NOT NULL DEFAULT gen_random_uuid();
@@ -0,0 +1,15 @@
+-- Add down migration script here
+UPDATE team SET image = '/uploads/' || image_asset_id WHERE image_asset_id IS NOT NULL;
+UPDATE scoreboard SET logo = '/uploads/' || logo_asset_id WHERE logo_asset_id IS NOT NULL;
+UPDATE casters SET image = '/uploads/' || image_asset_id WHERE image_asset_id IS NOT NULL;
+UPDATE theme SET logo = '/uploads/' || logo_asset_id WHERE logo_asset_id IS NOT NULL;
+UPDATE theme SET background_image = '/uploads/' || background_image_asset_id WHERE background_image_asset_id IS NOT NULL;
+UPDATE sponsor SET logo = '/uploads/' || logo_asset_id WHERE logo_asset_id IS NOT NULL;
+UPDATE talent SET image = '/uploads/' || image_asset_id WHERE image_asset_id IS NOT NULL;
+
+ALTER TABLE team DROP COLUMN image_asset_id;
+ALTER TABLE scoreboard DROP COLUMN logo_asset_id;
+ALTER TABLE casters DROP COLUMN image_asset_id;
+ALTER TABLE theme DROP COLUMN logo_asset_id, DROP COLUMN background_image_asset_id;
+ALTER TABLE sponsor DROP COLUMN logo_asset_id;
+ALTER TABLE talent DROP COLUMN image_asset_id;
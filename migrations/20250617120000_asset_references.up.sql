@@ -0,0 +1,41 @@
+-- Add up migration script here
+-- Uploaded images are referenced by id, the URL columns only keep external images
+ALTER TABLE team
+ADD image_asset_id uuid REFERENCES asset (id);
+
+ALTER TABLE scoreboard
+ADD logo_asset_id uuid REFERENCES asset (id);
+
+ALTER TABLE casters
+ADD image_asset_id uuid REFERENCES asset (id);
+
+ALTER TABLE theme
+ADD logo_asset_id uuid REFERENCES asset (id),
+ADD background_image_asset_id uuid REFERENCES asset (id);
+
+ALTER TABLE sponsor
+ADD logo_asset_id uuid REFERENCES asset (id);
+
+ALTER TABLE talent
+ADD image_asset_id uuid REFERENCES asset (id);
+
+UPDATE team SET image_asset_id = asset.id, image = NULL
+FROM asset WHERE team.image = '/uploads/' || asset.id;
+
+UPDATE scoreboard SET logo_asset_id = asset.id, logo = ''
+FROM asset WHERE scoreboard.logo = '/uploads/' || asset.id;
+
+UPDATE casters SET image_asset_id = asset.id, image = NULL
+FROM asset WHERE casters.image = '/uploads/' || asset.id;
+
+UPDATE theme SET logo_asset_id = asset.id, logo = ''
+FROM asset WHERE theme.logo = '/uploads/' || asset.id;
+
+UPDATE theme SET background_image_asset_id = asset.id, background_image = ''
+FROM asset WHERE theme.background_image = '/uploads/' || asset.id;
+
+UPDATE sponsor SET logo_asset_id = asset.id, logo = ''
+FROM asset WHERE sponsor.logo = '/uploads/' || asset.id;
+
+UPDATE talent SET image_asset_id = asset.id, image = NULL
+FROM asset WHERE talent.image = '/uploads/' || asset.id;
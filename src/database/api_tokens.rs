@@ -0,0 +1,161 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::DB;
+
+/// What an API token is allowed to change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    Scoreboard,
+    Timer,
+    Casters,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 3] = [ApiScope::Scoreboard, ApiScope::Timer, ApiScope::Casters];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "scoreboard" => Some(ApiScope::Scoreboard),
+            "timer" => Some(ApiScope::Timer),
+            "casters" => Some(ApiScope::Casters),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ApiScope::Scoreboard => "Placar",
+            ApiScope::Timer => "Timer",
+            ApiScope::Casters => "Casters",
+        }
+    }
+}
+
+impl Display for ApiScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiScope::Scoreboard => write!(f, "scoreboard"),
+            ApiScope::Timer => write!(f, "timer"),
+            ApiScope::Casters => write!(f, "casters"),
+        }
+    }
+}
+
+/// Token for controlling a tournament's overlays without a start.gg login.
+/// Only the hash of the token is stored.
+#[derive(Debug, Clone)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub tournament_slug: String,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+fn parse_scopes(scopes: Vec<String>) -> Vec<ApiScope> {
+    scopes.iter().filter_map(|s| ApiScope::parse(s)).collect()
+}
+
+impl DB {
+    pub async fn get_tournament_api_tokens(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Vec<ApiToken>, anyhow::Error> {
+        sqlx::query!(
+            r#"SELECT id, tournament_slug, name, scopes,
+            created_at AS "created_at: DateTime<Utc>", last_used_at AS "last_used_at: DateTime<Utc>"
+            FROM api_token WHERE tournament_slug = $1 ORDER BY created_at DESC"#,
+            tournament_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get api tokens: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| ApiToken {
+                    id: row.id,
+                    tournament_slug: row.tournament_slug,
+                    name: row.name,
+                    scopes: parse_scopes(row.scopes),
+                    created_at: row.created_at,
+                    last_used_at: row.last_used_at,
+                })
+                .collect::<Vec<_>>()
+        })
+    }
+
+    /// Finds the token by its hash, marking it as used
+    pub async fn use_api_token(&self, token_hash: &str) -> Result<Option<ApiToken>, anyhow::Error> {
+        Ok(sqlx::query!(
+            r#"UPDATE api_token SET last_used_at = now() WHERE token_hash = $1
+            RETURNING id, tournament_slug, name, scopes,
+            created_at AS "created_at: DateTime<Utc>", last_used_at AS "last_used_at: DateTime<Utc>""#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get api token: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(|row| ApiToken {
+            id: row.id,
+            tournament_slug: row.tournament_slug,
+            name: row.name,
+            scopes: parse_scopes(row.scopes),
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+        }))
+    }
+
+    pub async fn insert_api_token(
+        &self,
+        tournament_slug: &str,
+        name: &str,
+        token_hash: &str,
+        scopes: &[ApiScope],
+    ) -> Result<(), anyhow::Error> {
+        let scopes = scopes.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        sqlx::query!(
+            "INSERT INTO api_token (tournament_slug, name, token_hash, scopes) VALUES ($1, $2, $3, $4)",
+            tournament_slug,
+            name,
+            token_hash,
+            &scopes,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert api token: {}", e.to_string()))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    pub async fn delete_api_token(
+        &self,
+        tournament_slug: &str,
+        id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        let query = sqlx::query!(
+            "DELETE FROM api_token WHERE tournament_slug = $1 AND id = $2",
+            tournament_slug,
+            id
+        );
+        let response = query.execute(&self.pool).await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete api token"))
+        }
+    }
+}
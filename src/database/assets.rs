@@ -0,0 +1,192 @@
+use uuid::Uuid;
+
+use super::DB;
+
+/// Image uploaded to a tournament, served at [`Asset::url`]
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i32,
+}
+
+impl Asset {
+    /// Path filled in the image fields of the setup pages when the asset is picked
+    pub fn url(&self) -> String {
+        asset_url(self.id)
+    }
+}
+
+pub fn asset_url(id: Uuid) -> String {
+    format!("/uploads/{}", id)
+}
+
+/// The uploaded asset an image URL points to
+pub fn url_asset_id(url: &str) -> Option<Uuid> {
+    url.strip_prefix("/uploads/")?.parse().ok()
+}
+
+/// Splits an image field into the columns it is stored in: the uploaded asset it shows,
+/// or the URL of an external image
+pub fn image_reference(url: Option<&str>) -> (Option<Uuid>, Option<&str>) {
+    match url.and_then(url_asset_id) {
+        Some(id) => (Some(id), None),
+        None => (None, url),
+    }
+}
+
+/// URL of an image stored as an uploaded asset or as the URL of an external image
+pub fn image_url(asset_id: Option<Uuid>, url: Option<String>) -> Option<String> {
+    asset_id.map(asset_url).or(url)
+}
+
+impl DB {
+    pub async fn get_tournament_assets(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Vec<Asset>, anyhow::Error> {
+        sqlx::query!(
+            "SELECT id, filename, content_type, size from asset
+            WHERE tournament_slug = $1 ORDER BY created_at DESC",
+            tournament_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get assets: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| Asset {
+                    id: row.id,
+                    filename: row.filename,
+                    content_type: row.content_type,
+                    size: row.size,
+                })
+                .collect::<Vec<_>>()
+        })
+    }
+
+    /// Asset and its contents, which are `None` when stored in the local assets directory
+    pub async fn get_asset(&self, id: Uuid) -> Result<(Asset, Option<Vec<u8>>), anyhow::Error> {
+        sqlx::query!("SELECT * from asset WHERE id = $1", id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to get asset: {}", e))
+            .map(|row| {
+                (
+                    Asset {
+                        id: row.id,
+                        filename: row.filename,
+                        content_type: row.content_type,
+                        size: row.size,
+                    },
+                    row.data,
+                )
+            })
+    }
+
+    pub async fn insert_asset(
+        &self,
+        tournament_slug: &str,
+        filename: &str,
+        content_type: &str,
+        size: i32,
+        data: Option<&[u8]>,
+    ) -> Result<Asset, anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO asset (tournament_slug, filename, content_type, size, data) VALUES ($1, $2, $3, $4, $5)
+            RETURNING id",
+            tournament_slug,
+            filename,
+            content_type,
+            size,
+            data,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert asset: {}", e.to_string()))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(|row| Asset {
+            id: row.id,
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            size,
+        })
+    }
+
+    /// Whether the tournament can show the image: an external one or one of its assets
+    pub async fn is_tournament_image(
+        &self,
+        tournament_slug: &str,
+        url: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let Some(id) = url_asset_id(url) else {
+            return Ok(true);
+        };
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM asset WHERE id = $1 AND tournament_slug = $2) AS "exists!""#,
+            id,
+            tournament_slug
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to check asset: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    /// Whether the organization can show the image: an external one or an asset of its tournaments
+    pub async fn is_organization_image(
+        &self,
+        organization_id: Uuid,
+        url: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let Some(id) = url_asset_id(url) else {
+            return Ok(true);
+        };
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM asset
+                JOIN organization_tournament ON organization_tournament.tournament_slug = asset.tournament_slug
+                WHERE asset.id = $1 AND organization_tournament.organization_id = $2
+            ) AS "exists!""#,
+            id,
+            organization_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to check asset: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    /// What still shows the image, as described to the user
+    pub async fn get_asset_references(&self, id: Uuid) -> Result<Vec<String>, anyhow::Error> {
+        sqlx::query_scalar!(
+            r#"SELECT 'time ' || name AS "reference!" FROM team WHERE image_asset_id = $1
+            UNION ALL SELECT 'logo do placar' FROM scoreboard WHERE logo_asset_id = $1
+            UNION ALL SELECT 'caster ' || name FROM casters WHERE image_asset_id = $1
+            UNION ALL SELECT 'tema' FROM theme WHERE logo_asset_id = $1 OR background_image_asset_id = $1
+            UNION ALL SELECT 'patrocinador ' || name FROM sponsor WHERE logo_asset_id = $1
+            UNION ALL SELECT 'talento ' || name FROM talent WHERE image_asset_id = $1"#,
+            id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get asset references: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    pub async fn delete_asset(&self, tournament_slug: &str, id: Uuid) -> Result<(), anyhow::Error> {
+        let query = sqlx::query!(
+            "DELETE FROM asset WHERE tournament_slug = $1 AND id = $2",
+            tournament_slug,
+            id
+        );
+        let response = query.execute(&self.pool).await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete asset"))
+        }
+    }
+}
@@ -1,84 +1,194 @@
-use std::fmt::Display;
-use std::str::FromStr;
-
 use serde::{Deserialize, Serialize};
+use url::Url;
 use uuid::Uuid;
 
+use super::assets::{image_reference, image_url};
 use super::DB;
 
+/// Roles suggested in the setup, any other can be typed in
+pub const CASTER_ROLES: [&str; 5] = [
+    "Narrador",
+    "Comentarista",
+    "Analista",
+    "Entrevistador",
+    "Convidado",
+];
+
+/// A caster of the overlay, their position is the order in the overlay's list
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub enum CasterKind {
-    Narrator,
-    Commenter,
+pub struct Caster {
+    pub overlay_id: Uuid,
+    pub name: String,
+    pub role: String,
+    /// Video shown instead of the caster's feed from the overlay's VDO.ninja room, usually empty
+    pub stream_video: String,
+    pub image: Option<String>,
+    pub handle: Option<String>,
+    pub pronouns: Option<String>,
+    /// ID the caster pushes their video to the overlay's VDO.ninja room with
+    #[serde(default)]
+    pub push_id: Option<String>,
+    /// Who the caster is in the directory of the organization
+    #[serde(default)]
+    pub talent_id: Option<Uuid>,
 }
 
-impl Display for CasterKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            CasterKind::Narrator => write!(f, "narrator"),
-            CasterKind::Commenter => write!(f, "commenter"),
-        }
-    }
+/// Casters as the history kept them, when overlays had exactly a narrator and a commenter
+#[derive(Debug, Deserialize)]
+struct LegacyCaster {
+    overlay_id: Uuid,
+    name: String,
+    kind: String,
+    stream_video: String,
+    image: Option<String>,
 }
 
-impl FromStr for CasterKind {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "narrator" => Ok(CasterKind::Narrator),
-            "commenter" => Ok(CasterKind::Commenter),
-            _ => Err(anyhow::anyhow!("Unknown caster kind")),
+impl From<LegacyCaster> for Caster {
+    fn from(caster: LegacyCaster) -> Self {
+        // Their video was a view link of the overlay's room back then
+        let push_id = Url::parse(&caster.stream_video)
+            .ok()
+            .filter(|url| url.host_str() == Some("vdo.ninja"))
+            .and_then(|url| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "view")
+                    .map(|(_, value)| value.into_owned())
+            });
+        Caster {
+            overlay_id: caster.overlay_id,
+            name: caster.name,
+            role: match caster.kind.as_str() {
+                "Narrator" => "Narrador",
+                _ => "Comentarista",
+            }
+            .to_string(),
+            stream_video: match push_id {
+                Some(_) => String::new(),
+                None => caster.stream_video,
+            },
+            image: caster.image,
+            handle: None,
+            pronouns: None,
+            push_id,
+            talent_id: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Caster {
-    pub overlay_id: Uuid,
-    pub name: String,
-    pub kind: CasterKind,
-    pub stream_video: String,
+/// Reads casters from the history, including the narrator and commenter pairs of older entries
+pub fn casters_from_json(json: &str) -> serde_json::Result<Vec<Caster>> {
+    if let Ok(casters) = serde_json::from_str(json) {
+        return Ok(casters);
+    }
+    let pair: Option<(LegacyCaster, LegacyCaster)> = serde_json::from_str(json)?;
+    Ok(pair
+        .map(|(narrator, commenter)| vec![narrator.into(), commenter.into()])
+        .unwrap_or_default())
 }
 
 impl DB {
     pub async fn get_casters(&self, overlay_id: &Uuid) -> Result<Vec<Caster>, anyhow::Error> {
         sqlx::query!(
-            "SELECT * from casters WHERE overlay_id = $1",
+            "SELECT * from casters WHERE overlay_id = $1 ORDER BY position",
             overlay_id.to_string()
         )
         .fetch_all(&self.pool)
         .await
-        .map_err(|e| anyhow::anyhow!("failed to get team: {}", e))
+        .map_err(|e| anyhow::anyhow!("failed to get casters: {}", e))
         .inspect_err(|e| tracing::error!("{}", e))
         .map(|rows| {
             rows.into_iter()
-                .map(|row| {
-                    let error_msg = format!("got invalid kind for caster {}", &row.name);
-                    Caster {
-                        overlay_id: Uuid::parse_str(&row.overlay_id).unwrap(),
-                        name: row.name,
-                        kind: row.kind.parse().expect(&error_msg),
-                        stream_video: row.stream_video,
-                    }
+                .map(|row| Caster {
+                    overlay_id: *overlay_id,
+                    name: row.name,
+                    role: row.role,
+                    stream_video: row.stream_video,
+                    image: image_url(row.image_asset_id, row.image),
+                    handle: row.handle,
+                    pronouns: row.pronouns,
+                    push_id: row.push_id,
+                    talent_id: row.talent_id,
                 })
                 .collect::<Vec<_>>()
         })
     }
 
-    pub async fn upsert_caster(&self, caster: &Caster) -> Result<(), anyhow::Error> {
-        let query = sqlx::query!(
-            "INSERT INTO casters (overlay_id, kind, name, stream_video) VALUES ($1, $2, $3, $4)
-            ON CONFLICT (overlay_id, kind) DO UPDATE SET name = $3, stream_video = $4",
-            caster.overlay_id.to_string(),
-            caster.kind.to_string(),
-            caster.name,
-            caster.stream_video,
-        );
-        query
-            .execute(&self.pool)
+    /// Replaces all the casters of the overlay, keeping the order they are in
+    pub async fn replace_casters(
+        &self,
+        overlay_id: &Uuid,
+        casters: &[Caster],
+    ) -> Result<(), anyhow::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM casters WHERE overlay_id = $1",
+            overlay_id.to_string()
+        )
+        .execute(&mut *tx)
+        .await?;
+        for (position, caster) in casters.iter().enumerate() {
+            let (image_asset_id, image) = image_reference(caster.image.as_deref());
+            sqlx::query!(
+                "INSERT INTO casters (overlay_id, position, role, name, stream_video, image, handle, pronouns, push_id, talent_id, image_asset_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+                overlay_id.to_string(),
+                position as i32,
+                caster.role,
+                caster.name,
+                caster.stream_video,
+                image,
+                caster.handle,
+                caster.pronouns,
+                caster.push_id,
+                caster.talent_id,
+                image_asset_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit()
             .await
-            .map_err(|e| anyhow::anyhow!("failed to upsert team: {}", e.to_string()))
-            .inspect_err(|e| tracing::error!("{}", e))?;
-        Ok(())
+            .map_err(|e| anyhow::anyhow!("failed to update casters: {}", e))
+            .inspect_err(|e| tracing::error!("{}", e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_legacy_history() {
+        let overlay_id = Uuid::new_v4();
+        let legacy = serde_json::json!([
+            {
+                "overlay_id": overlay_id,
+                "name": "Ana",
+                "kind": "Narrator",
+                "stream_video": "https://vdo.ninja/?view=narrator&solo&room=abc",
+                "image": null,
+            },
+            {
+                "overlay_id": overlay_id,
+                "name": "Bia",
+                "kind": "Commenter",
+                "stream_video": "",
+                "image": "/uploads/bia.png",
+            },
+        ]);
+
+        let casters = casters_from_json(&legacy.to_string()).unwrap();
+        assert_eq!(casters.len(), 2);
+        assert_eq!(casters[0].role, "Narrador");
+        assert_eq!(casters[0].push_id.as_deref(), Some("narrator"));
+        assert!(casters[0].stream_video.is_empty());
+        assert_eq!(casters[1].role, "Comentarista");
+        assert_eq!(casters[1].push_id, None);
+        assert!(casters_from_json("null").unwrap().is_empty());
+
+        let json = serde_json::to_string(&casters).unwrap();
+        assert_eq!(casters_from_json(&json).unwrap().len(), 2);
     }
 }
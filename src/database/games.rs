@@ -0,0 +1,144 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use super::DB;
+
+/// A single game of a best-of-N match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchGame {
+    pub match_id: Uuid,
+    pub game_number: i32,
+    /// Id of the winning team, same as `StartGGTeam::id`
+    pub winner: String,
+    pub team_a_goals: i32,
+    pub team_b_goals: i32,
+    pub arena: Option<String>,
+    #[serde(
+        rename = "duration_seconds",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub duration: Option<chrono::Duration>,
+}
+
+fn serialize_duration<S: Serializer>(
+    duration: &Option<chrono::Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration.map(|d| d.num_seconds()).serialize(serializer)
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<chrono::Duration>, D::Error> {
+    Ok(Option::<i64>::deserialize(deserializer)?.map(chrono::Duration::seconds))
+}
+
+impl MatchGame {
+    /// Duration in the "m:ss" format used by the game's own clock
+    pub fn duration_text(&self) -> Option<String> {
+        self.duration.map(|d| {
+            let secs = d.num_seconds().max(0);
+            format!("{}:{:02}", secs / 60, secs % 60)
+        })
+    }
+}
+
+impl DB {
+    pub async fn get_match_games(&self, match_id: Uuid) -> Result<Vec<MatchGame>, anyhow::Error> {
+        sqlx::query!(
+            "SELECT * from match_games WHERE match_id = $1 ORDER BY game_number ASC",
+            match_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get match games: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| MatchGame {
+                    match_id: row.match_id,
+                    game_number: row.game_number,
+                    winner: row.winner,
+                    team_a_goals: row.team_a_goals,
+                    team_b_goals: row.team_b_goals,
+                    arena: row.arena,
+                    duration: row
+                        .duration_seconds
+                        .map(|secs| chrono::Duration::seconds(secs.into())),
+                })
+                .collect::<Vec<_>>()
+        })
+    }
+
+    pub async fn upsert_match_game(&self, game: &MatchGame) -> Result<(), anyhow::Error> {
+        let query = sqlx::query!(
+            r#"INSERT INTO match_games
+                (match_id, game_number, winner, team_a_goals, team_b_goals, arena, duration_seconds)
+                VALUES
+                ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (match_id, game_number) DO
+                UPDATE SET
+                    winner = $3,
+                    team_a_goals = $4,
+                    team_b_goals = $5,
+                    arena = $6,
+                    duration_seconds = $7
+            "#,
+            game.match_id,
+            game.game_number,
+            game.winner,
+            game.team_a_goals,
+            game.team_b_goals,
+            game.arena,
+            game.duration.map(|d| d.num_seconds() as i32),
+        );
+        query
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to upsert match game: {}", e.to_string()))
+            .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    /// Leaves the match with exactly these games
+    pub async fn set_match_games(
+        &self,
+        match_id: Uuid,
+        games: &[MatchGame],
+    ) -> Result<(), anyhow::Error> {
+        let game_numbers = games.iter().map(|g| g.game_number).collect::<Vec<_>>();
+        sqlx::query!(
+            "DELETE FROM match_games WHERE match_id = $1 AND game_number <> ALL($2)",
+            match_id,
+            &game_numbers
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to delete match games: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+
+        for game in games {
+            self.upsert_match_game(game).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete_match_game(
+        &self,
+        match_id: Uuid,
+        game_number: i32,
+    ) -> Result<(), anyhow::Error> {
+        let query = sqlx::query!(
+            "DELETE FROM match_games WHERE match_id = $1 AND game_number = $2",
+            match_id,
+            game_number
+        );
+        let response = query.execute(&self.pool).await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete match game"))
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use uuid::Uuid;
+
+use super::DB;
+
+impl DB {
+    /// Gamer tags of the players picked to start for the team on the overlay, if any were picked
+    pub async fn get_lineup(
+        &self,
+        overlay_id: Uuid,
+        team_id: &str,
+    ) -> Result<Option<Vec<String>>, anyhow::Error> {
+        Ok(sqlx::query!(
+            "SELECT gamer_tags from lineup WHERE overlay_id = $1 AND team_id = $2",
+            overlay_id,
+            team_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get lineup: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(|row| row.gamer_tags))
+    }
+
+    pub async fn upsert_lineup(
+        &self,
+        overlay_id: Uuid,
+        team_id: &str,
+        gamer_tags: &[String],
+    ) -> Result<(), anyhow::Error> {
+        let query = sqlx::query!(
+            "INSERT INTO lineup (overlay_id, team_id, gamer_tags) VALUES ($1, $2, $3)
+            ON CONFLICT (overlay_id, team_id) DO UPDATE SET gamer_tags = $3",
+            overlay_id,
+            team_id,
+            gamer_tags,
+        );
+        query
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to upsert lineup: {}", e.to_string()))
+            .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::DB;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    /// Shown one at a time in the lower third, rotating every few seconds
+    LowerThird,
+    /// Scrolling all together in the ticker
+    Ticker,
+}
+
+impl MessageKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "lower_third" => Some(MessageKind::LowerThird),
+            "ticker" => Some(MessageKind::Ticker),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            MessageKind::LowerThird => "lower_third",
+            MessageKind::Ticker => "ticker",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LowerThirdMessage {
+    pub id: Uuid,
+    pub overlay_id: Uuid,
+    pub kind: MessageKind,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub duration_seconds: i32,
+}
+
+/// What the lower third and ticker of the overlay are showing
+#[derive(Debug, Clone)]
+pub struct LowerThird {
+    pub overlay_id: Uuid,
+    pub lower_third_on_air: bool,
+    pub ticker_on_air: bool,
+    /// Message kept in the lower third instead of the rotation
+    pub pinned_message_id: Option<Uuid>,
+    /// Rotation start, so every open overlay shows the same message at the same time
+    pub rotation_started_at: DateTime<Utc>,
+}
+
+impl LowerThird {
+    pub fn new(overlay_id: Uuid) -> Self {
+        Self {
+            overlay_id,
+            lower_third_on_air: false,
+            ticker_on_air: false,
+            pinned_message_id: None,
+            rotation_started_at: Utc::now(),
+        }
+    }
+}
+
+impl DB {
+    pub async fn get_lower_third(&self, overlay_id: Uuid) -> Result<LowerThird, anyhow::Error> {
+        Ok(sqlx::query_as!(
+            LowerThird,
+            r#"SELECT overlay_id, lower_third_on_air, ticker_on_air, pinned_message_id,
+            rotation_started_at AS "rotation_started_at: DateTime<Utc>"
+            FROM lower_third WHERE overlay_id = $1"#,
+            overlay_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get lower third: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .unwrap_or_else(|| LowerThird::new(overlay_id)))
+    }
+
+    pub async fn upsert_lower_third(&self, lower_third: &LowerThird) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO lower_third
+            (overlay_id, lower_third_on_air, ticker_on_air, pinned_message_id, rotation_started_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (overlay_id) DO UPDATE SET lower_third_on_air = $2, ticker_on_air = $3,
+            pinned_message_id = $4, rotation_started_at = $5",
+            lower_third.overlay_id,
+            lower_third.lower_third_on_air,
+            lower_third.ticker_on_air,
+            lower_third.pinned_message_id,
+            lower_third.rotation_started_at as _,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to upsert lower third: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    /// Messages of the overlay in the order they are shown
+    pub async fn get_lower_third_messages(
+        &self,
+        overlay_id: Uuid,
+    ) -> Result<Vec<LowerThirdMessage>, anyhow::Error> {
+        sqlx::query!(
+            "SELECT id, overlay_id, kind, title, subtitle, duration_seconds FROM lower_third_message
+            WHERE overlay_id = $1 ORDER BY position, created_at",
+            overlay_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get lower third messages: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok(LowerThirdMessage {
+                id: row.id,
+                overlay_id: row.overlay_id,
+                kind: MessageKind::parse(&row.kind)
+                    .ok_or_else(|| anyhow::anyhow!("unknown message kind: {}", row.kind))?,
+                title: row.title,
+                subtitle: row.subtitle,
+                duration_seconds: row.duration_seconds,
+            })
+        })
+        .collect()
+    }
+
+    /// Adds the message at the end of the queue
+    pub async fn insert_lower_third_message(
+        &self,
+        message: &LowerThirdMessage,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO lower_third_message
+            (id, overlay_id, kind, title, subtitle, duration_seconds, position)
+            VALUES ($1, $2, $3, $4, $5, $6,
+                (SELECT COALESCE(MAX(position) + 1, 0) FROM lower_third_message WHERE overlay_id = $2))",
+            message.id,
+            message.overlay_id,
+            message.kind.value(),
+            message.title,
+            message.subtitle,
+            message.duration_seconds,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert lower third message: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    pub async fn update_lower_third_message(
+        &self,
+        message: &LowerThirdMessage,
+    ) -> Result<(), anyhow::Error> {
+        let response = sqlx::query!(
+            "UPDATE lower_third_message SET title = $3, subtitle = $4, duration_seconds = $5
+            WHERE overlay_id = $1 AND id = $2",
+            message.overlay_id,
+            message.id,
+            message.title,
+            message.subtitle,
+            message.duration_seconds,
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to update lower third message"))
+        }
+    }
+
+    pub async fn delete_lower_third_message(
+        &self,
+        overlay_id: Uuid,
+        id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        let response = sqlx::query!(
+            "DELETE FROM lower_third_message WHERE overlay_id = $1 AND id = $2",
+            overlay_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete lower third message"))
+        }
+    }
+
+    /// Puts the messages in the order of the IDs
+    pub async fn reorder_lower_third_messages(
+        &self,
+        overlay_id: Uuid,
+        ids: &[Uuid],
+    ) -> Result<(), anyhow::Error> {
+        let mut tx = self.pool.begin().await?;
+        for (position, id) in ids.iter().enumerate() {
+            sqlx::query!(
+                "UPDATE lower_third_message SET position = $3 WHERE overlay_id = $1 AND id = $2",
+                overlay_id,
+                id,
+                position as i32,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to reorder lower third messages: {}", e))
+            .inspect_err(|e| tracing::error!("{}", e))
+    }
+}
@@ -1,11 +1,13 @@
 use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::startgg::tournaments::StartGGTeam;
 
+use super::games::MatchGame;
 use super::DB;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Match {
     pub id: Uuid,
     pub overlay_id: Option<Uuid>,
@@ -17,6 +19,18 @@ pub struct Match {
     pub completed: bool,
     pub in_progress: bool,
     pub featured: bool,
+    pub startgg_set_id: Option<String>,
+    pub best_of: i32,
+    /// Games recorded for this match, ordered by game number
+    #[serde(default)]
+    pub games: Vec<MatchGame>,
+}
+
+impl Match {
+    /// Games a team has to win to take the series
+    pub fn wins_needed(&self) -> i32 {
+        self.best_of / 2 + 1
+    }
 }
 
 impl DB {
@@ -43,6 +57,9 @@ impl DB {
                 completed: row.completed,
                 in_progress: row.in_progress,
                 featured: row.featured,
+                startgg_set_id: row.startgg_set_id,
+                best_of: row.best_of,
+                games: self.get_match_games(row.id).await?,
             }) as anyhow::Result<_>
         })?
         .await
@@ -69,6 +86,47 @@ impl DB {
                         completed: row.completed,
                         in_progress: row.in_progress,
                         featured: row.featured,
+                        startgg_set_id: row.startgg_set_id,
+                        best_of: row.best_of,
+                        games: self.get_match_games(row.id).await?,
+                    }) as anyhow::Result<_>
+                })
+                .collect::<Vec<_>>()
+        })?;
+
+        join_all(matches_fut)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    pub async fn get_tournament_completed_matches(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Vec<Match>, anyhow::Error> {
+        let matches_fut = sqlx::query!(
+            "SELECT * from matches WHERE tournament_slug = $1 AND completed = true ORDER BY created_at ASC",
+            tournament_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| async move {
+                    Ok(Match {
+                        id: row.id,
+                        overlay_id: row.overlay_id,
+                        tournament_slug: row.tournament_slug,
+                        team_a: self.get_team(&row.team_a).await?,
+                        team_b: self.get_team(&row.team_b).await?,
+                        team_a_score: row.team_a_score,
+                        team_b_score: row.team_b_score,
+                        completed: row.completed,
+                        in_progress: row.in_progress,
+                        featured: row.featured,
+                        startgg_set_id: row.startgg_set_id,
+                        best_of: row.best_of,
+                        games: self.get_match_games(row.id).await?,
                     }) as anyhow::Result<_>
                 })
                 .collect::<Vec<_>>()
@@ -91,9 +149,9 @@ impl DB {
         };
         let query = sqlx::query!(
             r#"INSERT INTO matches
-                (id, overlay_id, tournament_slug, team_a, team_b, team_a_score, team_b_score, completed, in_progress, featured)
+                (id, overlay_id, tournament_slug, team_a, team_b, team_a_score, team_b_score, completed, in_progress, featured, startgg_set_id, best_of)
                 VALUES
-                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
                 ON CONFLICT (id) DO
                 UPDATE SET
                     overlay_id = $2,
@@ -105,6 +163,8 @@ impl DB {
                     completed = $8,
                     in_progress = $9,
                     featured = $10,
+                    startgg_set_id = $11,
+                    best_of = $12,
                     updated_at = now()
             "#,
             match_.id,
@@ -117,6 +177,8 @@ impl DB {
             match_.completed,
             match_.in_progress,
             match_.featured,
+            match_.startgg_set_id,
+            match_.best_of,
         );
         query
             .execute(&self.pool)
@@ -125,4 +187,40 @@ impl DB {
             .inspect_err(|e| tracing::error!("{}", e))?;
         Ok(match_)
     }
+
+    /// Adds the match of a start.gg set to the overlay, or updates the scores of the match
+    /// the overlay already has for the set
+    pub async fn upsert_set_match(&self, match_: Match) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"INSERT INTO matches
+                (id, overlay_id, tournament_slug, team_a, team_b, team_a_score, team_b_score, completed, in_progress, featured, startgg_set_id, best_of)
+                VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                ON CONFLICT (overlay_id, startgg_set_id) DO
+                UPDATE SET
+                    team_a_score = $6,
+                    team_b_score = $7,
+                    completed = $8,
+                    in_progress = $9,
+                    updated_at = now()
+            "#,
+            Uuid::new_v4(),
+            match_.overlay_id,
+            match_.tournament_slug,
+            match_.team_a.id,
+            match_.team_b.id,
+            match_.team_a_score,
+            match_.team_b_score,
+            match_.completed,
+            match_.in_progress,
+            match_.featured,
+            match_.startgg_set_id,
+            match_.best_of,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to upsert set match: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
 }
@@ -1,10 +1,23 @@
+pub mod api_tokens;
+pub mod assets;
 pub mod casters;
+pub mod games;
 pub mod group;
+pub mod lineup;
+pub mod lower_third;
 pub mod matches;
+pub mod obs;
+pub mod organizations;
 pub mod overlay;
+pub mod overlay_events;
 pub mod scoreboard;
+pub mod sponsors;
+pub mod standings;
+pub mod talent;
 pub mod team;
+pub mod theme;
 pub mod user;
+pub mod vdo;
 pub mod wait_timer;
 
 use sqlx::PgPool;
@@ -0,0 +1,104 @@
+use uuid::Uuid;
+
+use super::wait_timer::WaitType;
+use super::DB;
+
+/// Connection to the producer's OBS and the scenes switched for the overlay
+#[derive(Debug, Clone)]
+pub struct ObsConfig {
+    pub overlay_id: Uuid,
+    pub enabled: bool,
+    pub url: String,
+    pub password: Option<String>,
+    pub starting_scene: Option<String>,
+    pub break_scene: Option<String>,
+    pub ending_scene: Option<String>,
+    /// Scene shown when the match goes live, i.e. `WaitType::Nothing`
+    pub live_scene: Option<String>,
+    /// Source of the live scene shown only while live, usually the scoreboard browser source
+    pub scoreboard_source: Option<String>,
+}
+
+impl ObsConfig {
+    pub fn default_for(overlay_id: Uuid) -> Self {
+        Self {
+            overlay_id,
+            enabled: false,
+            url: "ws://127.0.0.1:4455".to_string(),
+            password: None,
+            starting_scene: None,
+            break_scene: None,
+            ending_scene: None,
+            live_scene: None,
+            scoreboard_source: None,
+        }
+    }
+
+    pub fn scene_for(&self, wait_type: &WaitType) -> Option<&str> {
+        match wait_type {
+            WaitType::Nothing => self.live_scene.as_deref(),
+            WaitType::Starting => self.starting_scene.as_deref(),
+            WaitType::Break => self.break_scene.as_deref(),
+            WaitType::Ending => self.ending_scene.as_deref(),
+        }
+    }
+}
+
+impl DB {
+    pub async fn get_obs_config(&self, overlay_id: Uuid) -> Result<ObsConfig, anyhow::Error> {
+        Ok(
+            sqlx::query!("SELECT * from obs_config WHERE overlay_id = $1", overlay_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| anyhow::anyhow!("failed to get obs config: {}", e))
+                .inspect_err(|e| tracing::error!("{}", e))?
+                .map(|row| ObsConfig {
+                    overlay_id: row.overlay_id,
+                    enabled: row.enabled,
+                    url: row.url,
+                    password: row.password,
+                    starting_scene: row.starting_scene,
+                    break_scene: row.break_scene,
+                    ending_scene: row.ending_scene,
+                    live_scene: row.live_scene,
+                    scoreboard_source: row.scoreboard_source,
+                })
+                .unwrap_or_else(|| ObsConfig::default_for(overlay_id)),
+        )
+    }
+
+    pub async fn upsert_obs_config(&self, config: &ObsConfig) -> Result<(), anyhow::Error> {
+        let query = sqlx::query!(
+            r#"INSERT INTO obs_config
+                (overlay_id, enabled, url, password, starting_scene, break_scene, ending_scene, live_scene, scoreboard_source)
+                VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (overlay_id) DO
+                UPDATE SET
+                    enabled = $2,
+                    url = $3,
+                    password = $4,
+                    starting_scene = $5,
+                    break_scene = $6,
+                    ending_scene = $7,
+                    live_scene = $8,
+                    scoreboard_source = $9
+            "#,
+            config.overlay_id,
+            config.enabled,
+            config.url,
+            config.password,
+            config.starting_scene,
+            config.break_scene,
+            config.ending_scene,
+            config.live_scene,
+            config.scoreboard_source,
+        );
+        query
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to upsert obs config: {}", e.to_string()))
+            .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,569 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::DB;
+
+/// Role of a member in an organization, from the most to the least privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationRole {
+    Owner,
+    Producer,
+    ScoreKeeper,
+    Caster,
+}
+
+/// Actions that depend on the role of the user in the organization of the tournament
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Change the roles of the members and remove them
+    ManageMembers,
+    InviteMembers,
+    /// Overlays, theme, OBS, start.gg sync, API tokens and linking tournaments
+    ManageTournament,
+    /// Scoreboard, lineups, today's matches and the timer
+    Scoreboard,
+    /// Casters of the overlays and the talent directory
+    Casters,
+}
+
+impl OrganizationRole {
+    pub const ALL: [OrganizationRole; 4] = [
+        OrganizationRole::Owner,
+        OrganizationRole::Producer,
+        OrganizationRole::ScoreKeeper,
+        OrganizationRole::Caster,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "owner" => Some(OrganizationRole::Owner),
+            "producer" => Some(OrganizationRole::Producer),
+            "score_keeper" => Some(OrganizationRole::ScoreKeeper),
+            "caster" => Some(OrganizationRole::Caster),
+            _ => None,
+        }
+    }
+
+    /// Name of the role in the database and in forms
+    pub fn value(&self) -> &'static str {
+        match self {
+            OrganizationRole::Owner => "owner",
+            OrganizationRole::Producer => "producer",
+            OrganizationRole::ScoreKeeper => "score_keeper",
+            OrganizationRole::Caster => "caster",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrganizationRole::Owner => "Dono",
+            OrganizationRole::Producer => "Produção",
+            OrganizationRole::ScoreKeeper => "Placar",
+            OrganizationRole::Caster => "Caster",
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::ManageMembers => *self == OrganizationRole::Owner,
+            Permission::InviteMembers | Permission::ManageTournament => {
+                matches!(self, OrganizationRole::Owner | OrganizationRole::Producer)
+            }
+            Permission::Scoreboard => matches!(
+                self,
+                OrganizationRole::Owner
+                    | OrganizationRole::Producer
+                    | OrganizationRole::ScoreKeeper
+            ),
+            Permission::Casters => matches!(
+                self,
+                OrganizationRole::Owner | OrganizationRole::Producer | OrganizationRole::Caster
+            ),
+        }
+    }
+
+    /// Only owners can make other owners
+    pub fn can_grant(&self, role: OrganizationRole) -> bool {
+        match self {
+            OrganizationRole::Owner => true,
+            OrganizationRole::Producer => role != OrganizationRole::Owner,
+            OrganizationRole::ScoreKeeper | OrganizationRole::Caster => false,
+        }
+    }
+}
+
+impl Display for OrganizationRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+fn parse_role(role: &str) -> Result<OrganizationRole, anyhow::Error> {
+    OrganizationRole::parse(role).ok_or_else(|| anyhow::anyhow!("unknown role: {}", role))
+}
+
+#[derive(Debug, Clone)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// An organization the user is a member of, with the user's role in it
+#[derive(Debug, Clone)]
+pub struct Membership {
+    pub organization: Organization,
+    pub role: OrganizationRole,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrganizationMember {
+    pub user_slug: String,
+    pub name: String,
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Single use link for joining the organization with the role
+#[derive(Debug, Clone)]
+pub struct OrganizationInvite {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub code: String,
+    pub role: OrganizationRole,
+    pub created_by: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DB {
+    /// Creates the organization with the user as its owner
+    pub async fn insert_organization(
+        &self,
+        name: &str,
+        owner_slug: &str,
+        owner_name: &str,
+    ) -> Result<Organization, anyhow::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let organization = sqlx::query_as!(
+            Organization,
+            "INSERT INTO organization (name) VALUES ($1) RETURNING id, name",
+            name
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert organization: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+
+        sqlx::query!(
+            "INSERT INTO organization_member (organization_id, user_slug, name, role)
+            VALUES ($1, $2, $3, $4)",
+            organization.id,
+            owner_slug,
+            owner_name,
+            OrganizationRole::Owner.to_string(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert organization owner: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+
+        tx.commit().await?;
+        Ok(organization)
+    }
+
+    pub async fn get_organization(&self, id: Uuid) -> Result<Organization, anyhow::Error> {
+        sqlx::query_as!(
+            Organization,
+            "SELECT id, name FROM organization WHERE id = $1",
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get organization: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    pub async fn get_user_memberships(
+        &self,
+        user_slug: &str,
+    ) -> Result<Vec<Membership>, anyhow::Error> {
+        sqlx::query!(
+            "SELECT o.id, o.name, m.role FROM organization o
+            JOIN organization_member m ON m.organization_id = o.id
+            WHERE m.user_slug = $1 ORDER BY o.name",
+            user_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get user organizations: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok(Membership {
+                organization: Organization {
+                    id: row.id,
+                    name: row.name,
+                },
+                role: parse_role(&row.role)?,
+            })
+        })
+        .collect()
+    }
+
+    pub async fn get_organization_role(
+        &self,
+        organization_id: Uuid,
+        user_slug: &str,
+    ) -> Result<Option<OrganizationRole>, anyhow::Error> {
+        sqlx::query_scalar!(
+            "SELECT role FROM organization_member WHERE organization_id = $1 AND user_slug = $2",
+            organization_id,
+            user_slug
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get organization role: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(|role| parse_role(&role))
+        .transpose()
+    }
+
+    /// Role of the user in the organization that manages the tournament
+    pub async fn get_tournament_role(
+        &self,
+        tournament_slug: &str,
+        user_slug: &str,
+    ) -> Result<Option<OrganizationRole>, anyhow::Error> {
+        sqlx::query_scalar!(
+            "SELECT m.role FROM organization_tournament t
+            JOIN organization_member m ON m.organization_id = t.organization_id
+            WHERE t.tournament_slug = $1 AND m.user_slug = $2",
+            tournament_slug,
+            user_slug
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get tournament role: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(|role| parse_role(&role))
+        .transpose()
+    }
+
+    /// Organization that manages the tournament, if any
+    pub async fn get_tournament_organization(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Option<Uuid>, anyhow::Error> {
+        sqlx::query_scalar!(
+            "SELECT organization_id FROM organization_tournament WHERE tournament_slug = $1",
+            tournament_slug
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get tournament organization: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    pub async fn get_organization_members(
+        &self,
+        organization_id: Uuid,
+    ) -> Result<Vec<OrganizationMember>, anyhow::Error> {
+        sqlx::query!(
+            r#"SELECT user_slug, name, role, created_at AS "created_at: DateTime<Utc>"
+            FROM organization_member WHERE organization_id = $1 ORDER BY created_at"#,
+            organization_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get organization members: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok(OrganizationMember {
+                user_slug: row.user_slug,
+                name: row.name,
+                role: parse_role(&row.role)?,
+                created_at: row.created_at,
+            })
+        })
+        .collect()
+    }
+
+    pub async fn update_member_role(
+        &self,
+        organization_id: Uuid,
+        user_slug: &str,
+        role: OrganizationRole,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "UPDATE organization_member SET role = $3 WHERE organization_id = $1 AND user_slug = $2",
+            organization_id,
+            user_slug,
+            role.to_string(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to update member role: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    pub async fn delete_member(
+        &self,
+        organization_id: Uuid,
+        user_slug: &str,
+    ) -> Result<(), anyhow::Error> {
+        let response = sqlx::query!(
+            "DELETE FROM organization_member WHERE organization_id = $1 AND user_slug = $2",
+            organization_id,
+            user_slug
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete organization member"))
+        }
+    }
+
+    pub async fn insert_invite(
+        &self,
+        organization_id: Uuid,
+        code: &str,
+        role: OrganizationRole,
+        created_by: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO organization_invite (organization_id, code, role, created_by, expires_at)
+            VALUES ($1, $2, $3, $4, $5)",
+            organization_id,
+            code,
+            role.to_string(),
+            created_by,
+            expires_at as _,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert organization invite: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    /// Invites that didn't expire yet
+    pub async fn get_organization_invites(
+        &self,
+        organization_id: Uuid,
+    ) -> Result<Vec<OrganizationInvite>, anyhow::Error> {
+        sqlx::query!(
+            r#"SELECT id, organization_id, code, role, created_by,
+            expires_at AS "expires_at: DateTime<Utc>"
+            FROM organization_invite WHERE organization_id = $1 AND expires_at > now()
+            ORDER BY created_at DESC"#,
+            organization_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get organization invites: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok(OrganizationInvite {
+                id: row.id,
+                organization_id: row.organization_id,
+                code: row.code,
+                role: parse_role(&row.role)?,
+                created_by: row.created_by,
+                expires_at: row.expires_at,
+            })
+        })
+        .collect()
+    }
+
+    pub async fn get_invite(
+        &self,
+        code: &str,
+    ) -> Result<Option<OrganizationInvite>, anyhow::Error> {
+        sqlx::query!(
+            r#"SELECT id, organization_id, code, role, created_by,
+            expires_at AS "expires_at: DateTime<Utc>"
+            FROM organization_invite WHERE code = $1 AND expires_at > now()"#,
+            code
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get organization invite: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(|row| {
+            Ok(OrganizationInvite {
+                id: row.id,
+                organization_id: row.organization_id,
+                code: row.code,
+                role: parse_role(&row.role)?,
+                created_by: row.created_by,
+                expires_at: row.expires_at,
+            })
+        })
+        .transpose()
+    }
+
+    /// Adds the user to the organization and deletes the invite.
+    /// Users that were already members keep their role.
+    pub async fn accept_invite(
+        &self,
+        invite: &OrganizationInvite,
+        user_slug: &str,
+        user_name: &str,
+    ) -> Result<(), anyhow::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted = sqlx::query!("DELETE FROM organization_invite WHERE id = $1", invite.id)
+            .execute(&mut *tx)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("invite was already used"));
+        }
+
+        sqlx::query!(
+            "INSERT INTO organization_member (organization_id, user_slug, name, role)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (organization_id, user_slug) DO NOTHING",
+            invite.organization_id,
+            user_slug,
+            user_name,
+            invite.role.to_string(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert organization member: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete_invite(
+        &self,
+        organization_id: Uuid,
+        id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        let response = sqlx::query!(
+            "DELETE FROM organization_invite WHERE organization_id = $1 AND id = $2",
+            organization_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete organization invite"))
+        }
+    }
+
+    pub async fn get_organization_tournaments(
+        &self,
+        organization_id: Uuid,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        sqlx::query_scalar!(
+            "SELECT tournament_slug FROM organization_tournament
+            WHERE organization_id = $1 ORDER BY tournament_slug",
+            organization_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get organization tournaments: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    /// Tournaments of all the organizations the user is a member of
+    pub async fn get_user_tournaments(
+        &self,
+        user_slug: &str,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        sqlx::query_scalar!(
+            "SELECT t.tournament_slug FROM organization_tournament t
+            JOIN organization_member m ON m.organization_id = t.organization_id
+            WHERE m.user_slug = $1 ORDER BY t.tournament_slug",
+            user_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get user tournaments: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    /// Moves the tournament to the organization
+    pub async fn link_tournament(
+        &self,
+        organization_id: Uuid,
+        tournament_slug: &str,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO organization_tournament (tournament_slug, organization_id) VALUES ($1, $2)
+            ON CONFLICT (tournament_slug) DO UPDATE SET organization_id = $2",
+            tournament_slug,
+            organization_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to link tournament: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    pub async fn unlink_tournament(
+        &self,
+        organization_id: Uuid,
+        tournament_slug: &str,
+    ) -> Result<(), anyhow::Error> {
+        let response = sqlx::query!(
+            "DELETE FROM organization_tournament WHERE organization_id = $1 AND tournament_slug = $2",
+            organization_id,
+            tournament_slug
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to unlink tournament"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_role_permissions() {
+        use OrganizationRole::*;
+
+        assert!(Owner.can(Permission::ManageMembers));
+        assert!(!Producer.can(Permission::ManageMembers));
+        assert!(Producer.can(Permission::InviteMembers));
+        assert!(ScoreKeeper.can(Permission::Scoreboard));
+        assert!(!ScoreKeeper.can(Permission::Casters));
+        assert!(Caster.can(Permission::Casters));
+        assert!(!Caster.can(Permission::Scoreboard));
+        assert!(!Caster.can(Permission::ManageTournament));
+
+        assert!(Owner.can_grant(Owner));
+        assert!(Producer.can_grant(ScoreKeeper));
+        assert!(!Producer.can_grant(Owner));
+        assert!(!Caster.can_grant(Caster));
+    }
+
+    #[test]
+    fn test_role_roundtrip() {
+        for role in OrganizationRole::ALL {
+            assert_eq!(OrganizationRole::parse(&role.to_string()), Some(role));
+        }
+    }
+}
@@ -7,6 +7,9 @@ pub struct Overlay {
     pub id: Uuid,
     pub tournament_slug: String,
     pub name: Option<String>,
+    pub startgg_sync: bool,
+    /// start.gg phase group shown by the bracket overlay
+    pub bracket_phase_group_id: Option<String>,
 }
 
 impl DB {
@@ -19,6 +22,8 @@ impl DB {
                 id: row.id,
                 tournament_slug: row.tournament_slug,
                 name: row.name,
+                startgg_sync: row.startgg_sync,
+                bracket_phase_group_id: row.bracket_phase_group_id,
             })
     }
 
@@ -37,10 +42,46 @@ impl DB {
             id: row.id,
             tournament_slug: row.tournament_slug,
             name: row.name,
+            startgg_sync: row.startgg_sync,
+            bracket_phase_group_id: row.bracket_phase_group_id,
         })
         .collect::<Vec<_>>())
     }
 
+    pub async fn get_startgg_synced_overlays(&self) -> Result<Vec<Overlay>, sqlx::Error> {
+        Ok(
+            sqlx::query!("SELECT * from stream_overlay WHERE startgg_sync = true")
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|row| Overlay {
+                    id: row.id,
+                    tournament_slug: row.tournament_slug,
+                    name: row.name,
+                    startgg_sync: row.startgg_sync,
+                    bracket_phase_group_id: row.bracket_phase_group_id,
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    pub async fn get_bracket_overlays(&self) -> Result<Vec<Overlay>, sqlx::Error> {
+        Ok(
+            sqlx::query!("SELECT * from stream_overlay WHERE bracket_phase_group_id IS NOT NULL")
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|row| Overlay {
+                    id: row.id,
+                    tournament_slug: row.tournament_slug,
+                    name: row.name,
+                    startgg_sync: row.startgg_sync,
+                    bracket_phase_group_id: row.bracket_phase_group_id,
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
     pub async fn create_overlay(&self, tournament_slug: &str) -> Result<Overlay, anyhow::Error> {
         let id = uuid::Uuid::new_v4();
         let query = sqlx::query!(
@@ -55,6 +96,8 @@ impl DB {
                 id,
                 tournament_slug: tournament_slug.to_string(),
                 name: None,
+                startgg_sync: false,
+                bracket_phase_group_id: None,
             })
         } else {
             Err(anyhow::anyhow!("failed to create new overlay"))
@@ -78,6 +121,48 @@ impl DB {
         }
     }
 
+    pub async fn set_overlay_startgg_sync(
+        &self,
+        id: Uuid,
+        startgg_sync: bool,
+    ) -> Result<Overlay, anyhow::Error> {
+        let query = sqlx::query!(
+            "UPDATE stream_overlay SET startgg_sync = $1 WHERE id = $2",
+            startgg_sync,
+            id,
+        );
+
+        let response = query.execute(&self.pool).await?;
+        if response.rows_affected() > 0 {
+            self.get_overlay(id)
+                .await
+                .map_err(|_| anyhow::anyhow!("failed to get updated overlay"))
+        } else {
+            Err(anyhow::anyhow!("failed to update overlay start.gg sync"))
+        }
+    }
+
+    pub async fn set_overlay_bracket_phase_group(
+        &self,
+        id: Uuid,
+        phase_group_id: Option<&str>,
+    ) -> Result<Overlay, anyhow::Error> {
+        let query = sqlx::query!(
+            "UPDATE stream_overlay SET bracket_phase_group_id = $1 WHERE id = $2",
+            phase_group_id,
+            id,
+        );
+
+        let response = query.execute(&self.pool).await?;
+        if response.rows_affected() > 0 {
+            self.get_overlay(id)
+                .await
+                .map_err(|_| anyhow::anyhow!("failed to get updated overlay"))
+        } else {
+            Err(anyhow::anyhow!("failed to update overlay bracket"))
+        }
+    }
+
     pub async fn assign_teams(
         &self,
         overlay_id: Uuid,
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::casters::{casters_from_json, Caster};
+use super::matches::Match;
+use super::scoreboard::Scoreboard;
+use super::wait_timer::{WaitTimer, WaitType};
+use super::DB;
+
+/// State of a part of the overlay, as kept in its history
+#[derive(Debug, Clone)]
+pub enum OverlayState {
+    Scoreboard(Option<Scoreboard>),
+    Casters(Vec<Caster>),
+    WaitTimer(Option<WaitTimer>),
+    Matches(Vec<Match>),
+}
+
+impl OverlayState {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OverlayState::Scoreboard(_) => "scoreboard",
+            OverlayState::Casters(_) => "casters",
+            OverlayState::WaitTimer(_) => "wait_timer",
+            OverlayState::Matches(_) => "matches",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OverlayState::Scoreboard(_) => "Placar",
+            OverlayState::Casters(_) => "Casters",
+            OverlayState::WaitTimer(_) => "Timer",
+            OverlayState::Matches(_) => "Partidas",
+        }
+    }
+
+    /// Short description of the state, shown in the history
+    pub fn summary(&self) -> String {
+        match self {
+            OverlayState::Scoreboard(Some(s)) => format!(
+                "{} x {}{}",
+                s.team_a_score,
+                s.team_b_score,
+                if s.sides_swapped {
+                    " (lados invertidos)"
+                } else {
+                    ""
+                }
+            ),
+            OverlayState::Casters(casters) if !casters.is_empty() => casters
+                .iter()
+                .map(|c| match c.role.as_str() {
+                    "" => c.name.clone(),
+                    role => format!("{} ({})", c.name, role),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            OverlayState::WaitTimer(Some(t)) => format!(
+                "{} às {}",
+                match t.wait_type {
+                    WaitType::Nothing => "Sem motivo",
+                    WaitType::Starting => "Iniciando",
+                    WaitType::Break => "Pausa",
+                    WaitType::Ending => "Finalizando",
+                },
+                t.wait_until.format("%H:%M")
+            ),
+            OverlayState::Matches(matches) => matches
+                .iter()
+                .map(|m| {
+                    format!(
+                        "{} {} x {} {}",
+                        m.team_a.name, m.team_a_score, m.team_b_score, m.team_b.name
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            OverlayState::Scoreboard(None)
+            | OverlayState::Casters(_)
+            | OverlayState::WaitTimer(None) => "-".to_string(),
+        }
+    }
+
+    /// Whether both states hold the same data, used to tell if the overlay changed since an event
+    pub fn same_as(&self, other: &OverlayState) -> bool {
+        let value = |state: &OverlayState| {
+            state
+                .to_json()
+                .ok()
+                .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        };
+        self.kind() == other.kind() && value(self).is_some() && value(self) == value(other)
+    }
+
+    fn to_json(&self) -> serde_json::Result<String> {
+        match self {
+            OverlayState::Scoreboard(s) => serde_json::to_string(s),
+            OverlayState::Casters(c) => serde_json::to_string(c),
+            OverlayState::WaitTimer(t) => serde_json::to_string(t),
+            OverlayState::Matches(m) => serde_json::to_string(m),
+        }
+    }
+
+    fn from_json(kind: &str, json: &str) -> anyhow::Result<Self> {
+        Ok(match kind {
+            "scoreboard" => OverlayState::Scoreboard(serde_json::from_str(json)?),
+            "casters" => OverlayState::Casters(casters_from_json(json)?),
+            "wait_timer" => OverlayState::WaitTimer(serde_json::from_str(json)?),
+            "matches" => OverlayState::Matches(serde_json::from_str(json)?),
+            _ => return Err(anyhow::anyhow!("unknown overlay event kind: {}", kind)),
+        })
+    }
+}
+
+/// A change made to the overlay, with the state before and after it
+#[derive(Debug, Clone)]
+pub struct OverlayEvent {
+    pub id: i64,
+    pub actor: String,
+    pub before: OverlayState,
+    pub after: OverlayState,
+    pub created_at: DateTime<Utc>,
+    pub undone_at: Option<DateTime<Utc>>,
+    pub undone_by: Option<String>,
+}
+
+struct OverlayEventRow {
+    id: i64,
+    actor: String,
+    kind: String,
+    before: String,
+    after: String,
+    created_at: DateTime<Utc>,
+    undone_at: Option<DateTime<Utc>>,
+    undone_by: Option<String>,
+}
+
+impl TryFrom<OverlayEventRow> for OverlayEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(row: OverlayEventRow) -> Result<Self, Self::Error> {
+        Ok(OverlayEvent {
+            id: row.id,
+            actor: row.actor,
+            before: OverlayState::from_json(&row.kind, &row.before)?,
+            after: OverlayState::from_json(&row.kind, &row.after)?,
+            created_at: row.created_at,
+            undone_at: row.undone_at,
+            undone_by: row.undone_by,
+        })
+    }
+}
+
+impl DB {
+    pub async fn insert_overlay_event(
+        &self,
+        overlay_id: Uuid,
+        actor: &str,
+        before: &OverlayState,
+        after: &OverlayState,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO overlay_events (overlay_id, actor, kind, before, after)
+            VALUES ($1, $2, $3, $4::text::jsonb, $5::text::jsonb)",
+            overlay_id,
+            actor,
+            after.kind(),
+            before.to_json()?,
+            after.to_json()?,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert overlay event: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    /// Latest changes of the overlay, newest first
+    pub async fn get_overlay_events(
+        &self,
+        overlay_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<OverlayEvent>, anyhow::Error> {
+        sqlx::query_as!(
+            OverlayEventRow,
+            r#"SELECT id, actor, kind, before::text as "before!", after::text as "after!",
+                created_at as "created_at: DateTime<Utc>", undone_at as "undone_at: DateTime<Utc>", undone_by
+            FROM overlay_events WHERE overlay_id = $1 ORDER BY id DESC LIMIT $2"#,
+            overlay_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get overlay events: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .into_iter()
+        .map(OverlayEvent::try_from)
+        .collect()
+    }
+
+    /// The latest change that wasn't undone
+    pub async fn get_undoable_overlay_event(
+        &self,
+        overlay_id: Uuid,
+    ) -> Result<Option<OverlayEvent>, anyhow::Error> {
+        sqlx::query_as!(
+            OverlayEventRow,
+            r#"SELECT id, actor, kind, before::text as "before!", after::text as "after!",
+                created_at as "created_at: DateTime<Utc>", undone_at as "undone_at: DateTime<Utc>", undone_by
+            FROM overlay_events WHERE overlay_id = $1 AND undone_at IS NULL
+            ORDER BY id DESC LIMIT 1"#,
+            overlay_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get overlay event: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(OverlayEvent::try_from)
+        .transpose()
+    }
+
+    /// The last undone change, as long as no other change was made after it was undone
+    pub async fn get_redoable_overlay_event(
+        &self,
+        overlay_id: Uuid,
+    ) -> Result<Option<OverlayEvent>, anyhow::Error> {
+        sqlx::query_as!(
+            OverlayEventRow,
+            r#"SELECT id, actor, kind, before::text as "before!", after::text as "after!",
+                created_at as "created_at: DateTime<Utc>", undone_at as "undone_at: DateTime<Utc>", undone_by
+            FROM overlay_events WHERE overlay_id = $1
+                AND undone_at > (SELECT max(created_at) FROM overlay_events WHERE overlay_id = $1)
+            ORDER BY undone_at DESC LIMIT 1"#,
+            overlay_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get overlay event: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(OverlayEvent::try_from)
+        .transpose()
+    }
+
+    /// Marks the change as undone by the actor, or as applied again when `undone_by` is `None`
+    pub async fn set_overlay_event_undone(
+        &self,
+        id: i64,
+        undone_by: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "UPDATE overlay_events SET
+                undone_at = CASE WHEN $2::varchar IS NULL THEN NULL ELSE now() END,
+                undone_by = $2
+            WHERE id = $1",
+            id,
+            undone_by,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to update overlay event: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::assets::{image_reference, image_url};
 use super::DB;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scoreboard {
     pub overlay_id: Uuid,
     pub team_a: String,
@@ -13,6 +15,18 @@ pub struct Scoreboard {
     pub team_b_standing: String,
     pub championship_phase: Option<String>,
     pub logo: String,
+    pub startgg_set_id: Option<String>,
+    pub best_of: i32,
+    /// Team A is shown on the right, e.g. after the teams change sides between games
+    #[serde(default)]
+    pub sides_swapped: bool,
+}
+
+impl Scoreboard {
+    /// Whether team A goes on the right, given the side the theme puts it on
+    pub fn swap_sides(&self, theme_swap_sides: bool) -> bool {
+        self.sides_swapped != theme_swap_sides
+    }
 }
 
 impl DB {
@@ -36,7 +50,10 @@ impl DB {
             team_a_standing: row.team_a_standing.unwrap_or_default(),
             team_b_standing: row.team_b_standing.unwrap_or_default(),
             championship_phase: row.championship_phase,
-            logo: row.logo,
+            logo: image_url(row.logo_asset_id, Some(row.logo)).unwrap_or_default(),
+            startgg_set_id: row.startgg_set_id,
+            best_of: row.best_of,
+            sides_swapped: row.sides_swapped,
         })
     }
 
@@ -44,11 +61,12 @@ impl DB {
         &self,
         scoreboard: Scoreboard,
     ) -> Result<Scoreboard, anyhow::Error> {
+        let (logo_asset_id, logo) = image_reference(Some(&scoreboard.logo));
         let query = sqlx::query!(
             r#"INSERT INTO scoreboard
-                (overlay_id, team_a, team_b, team_a_score, team_b_score, team_a_standing, team_b_standing, championship_phase, logo)
+                (overlay_id, team_a, team_b, team_a_score, team_b_score, team_a_standing, team_b_standing, championship_phase, logo, startgg_set_id, best_of, sides_swapped, logo_asset_id)
                 VALUES
-                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
                 ON CONFLICT (overlay_id) DO
                 UPDATE SET
                     team_a = $2,
@@ -58,7 +76,11 @@ impl DB {
                     team_a_standing = $6,
                     team_b_standing = $7,
                     championship_phase = $8,
-                    logo = $9
+                    logo = $9,
+                    startgg_set_id = $10,
+                    best_of = $11,
+                    sides_swapped = $12,
+                    logo_asset_id = $13
             "#,
             scoreboard.overlay_id,
             scoreboard.team_a,
@@ -68,7 +90,11 @@ impl DB {
             scoreboard.team_a_standing,
             scoreboard.team_b_standing,
             scoreboard.championship_phase,
-            scoreboard.logo,
+            logo.unwrap_or_default(),
+            scoreboard.startgg_set_id,
+            scoreboard.best_of,
+            scoreboard.sides_swapped,
+            logo_asset_id,
         );
         query
             .execute(&self.pool)
@@ -77,4 +103,62 @@ impl DB {
             .inspect_err(|e| tracing::error!("{}", e))?;
         Ok(scoreboard)
     }
+
+    /// Sets the score of one team, or adds `delta` to it, in a single statement so concurrent
+    /// updates aren't lost. Scores never go below zero.
+    /// Returns the scoreboard before and after the change, `None` without a scoreboard.
+    pub async fn update_team_score(
+        &self,
+        overlay_id: Uuid,
+        team_a: bool,
+        score: Option<i32>,
+        delta: Option<i32>,
+    ) -> Result<Option<(Scoreboard, Scoreboard)>, anyhow::Error> {
+        let row = sqlx::query!(
+            r#"UPDATE scoreboard s SET
+                team_a_score = CASE WHEN $2 THEN GREATEST(COALESCE($3, s.team_a_score + $4), 0)
+                    ELSE s.team_a_score END,
+                team_b_score = CASE WHEN $2 THEN s.team_b_score
+                    ELSE GREATEST(COALESCE($3, s.team_b_score + $4), 0) END
+            FROM (
+                SELECT overlay_id, team_a_score, team_b_score FROM scoreboard
+                WHERE overlay_id = $1 FOR UPDATE
+            ) previous
+            WHERE s.overlay_id = previous.overlay_id
+            RETURNING s.*, previous.team_a_score AS "previous_team_a_score!",
+                previous.team_b_score AS "previous_team_b_score!"
+            "#,
+            overlay_id,
+            team_a,
+            score,
+            delta,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to update team score: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+
+        Ok(row.map(|row| {
+            let scoreboard = Scoreboard {
+                overlay_id,
+                team_a: row.team_a,
+                team_b: row.team_b,
+                team_a_score: row.team_a_score,
+                team_b_score: row.team_b_score,
+                team_a_standing: row.team_a_standing.unwrap_or_default(),
+                team_b_standing: row.team_b_standing.unwrap_or_default(),
+                championship_phase: row.championship_phase,
+                logo: image_url(row.logo_asset_id, Some(row.logo)).unwrap_or_default(),
+                startgg_set_id: row.startgg_set_id,
+                best_of: row.best_of,
+                sides_swapped: row.sides_swapped,
+            };
+            let previous = Scoreboard {
+                team_a_score: row.previous_team_a_score,
+                team_b_score: row.previous_team_b_score,
+                ..scoreboard.clone()
+            };
+            (previous, scoreboard)
+        }))
+    }
 }
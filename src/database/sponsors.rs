@@ -0,0 +1,279 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::assets::{image_reference, image_url};
+use super::DB;
+
+/// Sponsor of the tournament, rotated in the sponsor slot of the overlays
+#[derive(Debug, Clone)]
+pub struct Sponsor {
+    pub id: Uuid,
+    pub tournament_slug: String,
+    pub name: String,
+    /// Usually an image from the assets of the tournament
+    pub logo: String,
+    /// How many times the sponsor is shown in each turn of the rotation
+    pub weight: i32,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+/// Sponsor as stored, with the logo either uploaded or an external link
+struct SponsorRow {
+    id: Uuid,
+    tournament_slug: String,
+    name: String,
+    logo: String,
+    logo_asset_id: Option<Uuid>,
+    weight: i32,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+}
+
+impl From<SponsorRow> for Sponsor {
+    fn from(row: SponsorRow) -> Self {
+        Sponsor {
+            id: row.id,
+            tournament_slug: row.tournament_slug,
+            name: row.name,
+            logo: image_url(row.logo_asset_id, Some(row.logo)).unwrap_or_default(),
+            weight: row.weight,
+            starts_at: row.starts_at,
+            ends_at: row.ends_at,
+        }
+    }
+}
+
+impl Sponsor {
+    pub fn is_active(&self, now: &DateTime<Utc>) -> bool {
+        self.starts_at.is_none_or(|starts_at| starts_at <= *now)
+            && self.ends_at.is_none_or(|ends_at| *now < ends_at)
+    }
+}
+
+/// Overlays the sponsor slot is embedded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SponsorSlot {
+    Waiting,
+    Scoreboard,
+    Casters,
+}
+
+impl SponsorSlot {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "waiting" => Some(SponsorSlot::Waiting),
+            "scoreboard" => Some(SponsorSlot::Scoreboard),
+            "casters" => Some(SponsorSlot::Casters),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            SponsorSlot::Waiting => "waiting",
+            SponsorSlot::Scoreboard => "scoreboard",
+            SponsorSlot::Casters => "casters",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SponsorSlot::Waiting => "Espera",
+            SponsorSlot::Scoreboard => "Placar",
+            SponsorSlot::Casters => "Casters",
+        }
+    }
+}
+
+/// Time a sponsor was on screen in a slot on the day, across all the overlays
+#[derive(Debug, Clone)]
+pub struct SponsorImpressions {
+    pub sponsor_id: Uuid,
+    pub sponsor_name: String,
+    pub day: NaiveDate,
+    pub slot: SponsorSlot,
+    pub impressions: i64,
+    pub seconds: i64,
+}
+
+impl DB {
+    pub async fn get_tournament_sponsors(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Vec<Sponsor>, anyhow::Error> {
+        sqlx::query_as!(
+            SponsorRow,
+            r#"SELECT id, tournament_slug, name, logo, logo_asset_id, weight,
+            starts_at AS "starts_at: DateTime<Utc>", ends_at AS "ends_at: DateTime<Utc>"
+            FROM sponsor WHERE tournament_slug = $1 ORDER BY name"#,
+            tournament_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get sponsors: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(|rows| rows.into_iter().map(Sponsor::from).collect())
+    }
+
+    pub async fn get_sponsor(
+        &self,
+        tournament_slug: &str,
+        id: Uuid,
+    ) -> Result<Sponsor, anyhow::Error> {
+        sqlx::query_as!(
+            SponsorRow,
+            r#"SELECT id, tournament_slug, name, logo, logo_asset_id, weight,
+            starts_at AS "starts_at: DateTime<Utc>", ends_at AS "ends_at: DateTime<Utc>"
+            FROM sponsor WHERE tournament_slug = $1 AND id = $2"#,
+            tournament_slug,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get sponsor: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(Sponsor::from)
+    }
+
+    pub async fn insert_sponsor(&self, sponsor: &Sponsor) -> Result<(), anyhow::Error> {
+        let (logo_asset_id, logo) = image_reference(Some(&sponsor.logo));
+        sqlx::query!(
+            "INSERT INTO sponsor (id, tournament_slug, name, logo, logo_asset_id, weight, starts_at, ends_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            sponsor.id,
+            sponsor.tournament_slug,
+            sponsor.name,
+            logo.unwrap_or_default(),
+            logo_asset_id,
+            sponsor.weight,
+            sponsor.starts_at as _,
+            sponsor.ends_at as _,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert sponsor: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    pub async fn update_sponsor(&self, sponsor: &Sponsor) -> Result<(), anyhow::Error> {
+        let (logo_asset_id, logo) = image_reference(Some(&sponsor.logo));
+        let response = sqlx::query!(
+            "UPDATE sponsor SET name = $3, logo = $4, logo_asset_id = $5, weight = $6, starts_at = $7, ends_at = $8
+            WHERE tournament_slug = $1 AND id = $2",
+            sponsor.tournament_slug,
+            sponsor.id,
+            sponsor.name,
+            logo.unwrap_or_default(),
+            logo_asset_id,
+            sponsor.weight,
+            sponsor.starts_at as _,
+            sponsor.ends_at as _,
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to update sponsor"))
+        }
+    }
+
+    pub async fn delete_sponsor(
+        &self,
+        tournament_slug: &str,
+        id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        let response = sqlx::query!(
+            "DELETE FROM sponsor WHERE tournament_slug = $1 AND id = $2",
+            tournament_slug,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete sponsor"))
+        }
+    }
+
+    /// Token the overlay pages send with the impressions of their sponsor slot
+    pub async fn get_sponsor_token(&self, overlay_id: Uuid) -> Result<Uuid, anyhow::Error> {
+        sqlx::query_scalar!(
+            "SELECT sponsor_token FROM stream_overlay WHERE id = $1",
+            overlay_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get sponsor token: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    /// Adds one showing of the sponsor to the impressions of the day, unless the overlay already
+    /// reported it in the slot less than `interval_seconds` ago. Returns whether it was added.
+    pub async fn record_sponsor_impression(
+        &self,
+        sponsor_id: Uuid,
+        overlay_id: Uuid,
+        slot: SponsorSlot,
+        seconds: i32,
+        interval_seconds: i32,
+    ) -> Result<bool, anyhow::Error> {
+        let response = sqlx::query!(
+            "INSERT INTO sponsor_impression (sponsor_id, overlay_id, slot, impressions, seconds)
+            VALUES ($1, $2, $3, 1, $4)
+            ON CONFLICT (sponsor_id, overlay_id, slot, day)
+            DO UPDATE SET impressions = sponsor_impression.impressions + 1,
+            seconds = sponsor_impression.seconds + $4, last_reported_at = now()
+            WHERE sponsor_impression.last_reported_at <= now() - make_interval(secs => $5)",
+            sponsor_id,
+            overlay_id,
+            slot.value(),
+            seconds,
+            interval_seconds as f64,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to record sponsor impression: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(response.rows_affected() > 0)
+    }
+
+    /// Impressions of the sponsors of the tournament per day and slot, the latest first
+    pub async fn get_sponsor_impressions(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Vec<SponsorImpressions>, anyhow::Error> {
+        sqlx::query!(
+            r#"SELECT i.sponsor_id, s.name, i.day AS "day: NaiveDate", i.slot,
+            SUM(i.impressions) AS "impressions!", SUM(i.seconds) AS "seconds!"
+            FROM sponsor_impression i
+            JOIN sponsor s ON s.id = i.sponsor_id
+            WHERE s.tournament_slug = $1
+            GROUP BY i.sponsor_id, s.name, i.day, i.slot
+            ORDER BY i.day DESC, s.name, i.slot"#,
+            tournament_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get sponsor impressions: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .into_iter()
+        .map(|row| {
+            Ok(SponsorImpressions {
+                sponsor_id: row.sponsor_id,
+                sponsor_name: row.name,
+                day: row.day,
+                slot: SponsorSlot::parse(&row.slot)
+                    .ok_or_else(|| anyhow::anyhow!("unknown sponsor slot: {}", row.slot))?,
+                impressions: row.impressions,
+                seconds: row.seconds,
+            })
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::startgg::tournaments::StartGGTeam;
+
+use super::matches::Match;
+use super::DB;
+
+#[derive(Debug, Clone)]
+pub struct TeamStanding {
+    pub team: StartGGTeam,
+    pub wins: i32,
+    pub losses: i32,
+    pub games_won: i32,
+    pub games_lost: i32,
+}
+
+impl TeamStanding {
+    pub fn game_differential(&self) -> i32 {
+        self.games_won - self.games_lost
+    }
+
+    /// Same "W-L" format operators used to type into the scoreboard
+    pub fn record(&self) -> String {
+        format!("{}-{}", self.wins, self.losses)
+    }
+}
+
+/// Computes the standings table from completed matches.
+///
+/// Teams are ranked by wins, then by head-to-head wins among the teams tied on wins,
+/// then by game differential, games won and finally by name.
+pub fn compute_standings(matches: &[Match]) -> Vec<TeamStanding> {
+    let mut standings: Vec<TeamStanding> = vec![];
+    let mut head_to_head: HashMap<(String, String), i32> = HashMap::new();
+
+    for m in matches.iter().filter(|m| m.completed) {
+        for (team, games_won, games_lost) in [
+            (&m.team_a, m.team_a_score, m.team_b_score),
+            (&m.team_b, m.team_b_score, m.team_a_score),
+        ] {
+            let position = match standings.iter().position(|s| s.team.id == team.id) {
+                Some(position) => position,
+                None => {
+                    standings.push(TeamStanding {
+                        team: team.clone(),
+                        wins: 0,
+                        losses: 0,
+                        games_won: 0,
+                        games_lost: 0,
+                    });
+                    standings.len() - 1
+                }
+            };
+            let standing = &mut standings[position];
+            standing.games_won += games_won;
+            standing.games_lost += games_lost;
+            if games_won > games_lost {
+                standing.wins += 1;
+            } else if games_won < games_lost {
+                standing.losses += 1;
+            }
+        }
+
+        let winner_loser = if m.team_a_score > m.team_b_score {
+            Some((&m.team_a.id, &m.team_b.id))
+        } else if m.team_b_score > m.team_a_score {
+            Some((&m.team_b.id, &m.team_a.id))
+        } else {
+            None
+        };
+        if let Some((winner, loser)) = winner_loser {
+            *head_to_head
+                .entry((winner.clone(), loser.clone()))
+                .or_default() += 1;
+        }
+    }
+
+    // Head-to-head only counts the matches played against the other teams with the same wins
+    let head_to_head_wins = |standing: &TeamStanding, standings: &[TeamStanding]| -> i32 {
+        standings
+            .iter()
+            .filter(|other| other.wins == standing.wins && other.team.id != standing.team.id)
+            .map(|other| {
+                head_to_head
+                    .get(&(standing.team.id.clone(), other.team.id.clone()))
+                    .copied()
+                    .unwrap_or_default()
+            })
+            .sum()
+    };
+    let tiebreakers = standings
+        .iter()
+        .map(|s| (s.team.id.clone(), head_to_head_wins(s, &standings)))
+        .collect::<HashMap<_, _>>();
+
+    standings.sort_by(|a, b| {
+        b.wins
+            .cmp(&a.wins)
+            .then(tiebreakers[&b.team.id].cmp(&tiebreakers[&a.team.id]))
+            .then(b.game_differential().cmp(&a.game_differential()))
+            .then(b.games_won.cmp(&a.games_won))
+            .then(a.team.name.cmp(&b.team.name))
+    });
+
+    standings
+}
+
+impl DB {
+    /// Standings of a tournament, computed from the completed matches of all of its overlays,
+    /// which includes the matches synced from start.gg sets
+    pub async fn get_tournament_standings(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Vec<TeamStanding>, anyhow::Error> {
+        let matches = self
+            .get_tournament_completed_matches(tournament_slug)
+            .await?;
+
+        // The same start.gg set can be linked to matches of more than one overlay
+        let mut seen_sets = HashSet::new();
+        let matches = matches
+            .into_iter()
+            .filter(|m| match &m.startgg_set_id {
+                Some(set_id) => seen_sets.insert(set_id.clone()),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(compute_standings(&matches))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn team(id: &str) -> StartGGTeam {
+        StartGGTeam {
+            id: id.to_string(),
+            name: id.to_string(),
+            nickname: None,
+            image: None,
+            team_members: vec![],
+        }
+    }
+
+    fn completed(team_a: &str, team_b: &str, team_a_score: i32, team_b_score: i32) -> Match {
+        Match {
+            id: Uuid::new_v4(),
+            overlay_id: None,
+            tournament_slug: "test".to_string(),
+            team_a: team(team_a),
+            team_b: team(team_b),
+            team_a_score,
+            team_b_score,
+            completed: true,
+            in_progress: false,
+            featured: false,
+            startgg_set_id: None,
+            best_of: 3,
+            games: vec![],
+        }
+    }
+
+    #[test]
+    fn test_compute_standings() {
+        let matches = vec![
+            completed("a", "b", 2, 0),
+            completed("b", "c", 2, 1),
+            completed("c", "a", 2, 0),
+            completed("d", "a", 0, 2),
+            completed("d", "b", 0, 2),
+            completed("c", "d", 2, 0),
+        ];
+
+        let standings = compute_standings(&matches);
+        let order = standings
+            .iter()
+            .map(|s| s.team.id.as_str())
+            .collect::<Vec<_>>();
+
+        // a, b and c are tied on wins and head-to-head, c has the best game differential
+        assert_eq!(order, vec!["c", "a", "b", "d"]);
+        assert_eq!(standings[0].record(), "2-1");
+        assert_eq!(standings[0].game_differential(), 3);
+        assert_eq!(standings[3].record(), "0-3");
+    }
+
+    #[test]
+    fn test_head_to_head_tiebreaker() {
+        let matches = vec![
+            completed("a", "b", 1, 2),
+            completed("a", "c", 2, 0),
+            completed("a", "d", 2, 0),
+            completed("b", "c", 0, 2),
+            completed("b", "d", 2, 1),
+            completed("c", "d", 0, 2),
+        ];
+
+        let standings = compute_standings(&matches);
+        let order = standings
+            .iter()
+            .map(|s| s.team.id.as_str())
+            .collect::<Vec<_>>();
+
+        // a has the better game differential, but lost to b
+        assert_eq!(order, vec!["b", "a", "d", "c"]);
+    }
+}
@@ -0,0 +1,227 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use uuid::Uuid;
+
+use super::assets::{image_reference, image_url};
+use super::casters::Caster;
+use super::overlay::Overlay;
+use super::DB;
+
+/// Someone who works the broadcasts of the organization, picked for the casters of any overlay
+#[derive(Debug, Clone)]
+pub struct Talent {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    pub handle: Option<String>,
+    pub pronouns: Option<String>,
+    pub image: Option<String>,
+    /// Push ID the talent gets in the VDO.ninja room of every overlay
+    pub push_id: String,
+}
+
+/// Talent as stored, with the photo either uploaded or an external link
+struct TalentRow {
+    id: Uuid,
+    organization_id: Uuid,
+    name: String,
+    handle: Option<String>,
+    pronouns: Option<String>,
+    image: Option<String>,
+    image_asset_id: Option<Uuid>,
+    push_id: String,
+}
+
+impl From<TalentRow> for Talent {
+    fn from(row: TalentRow) -> Self {
+        Talent {
+            id: row.id,
+            organization_id: row.organization_id,
+            name: row.name,
+            handle: row.handle,
+            pronouns: row.pronouns,
+            image: image_url(row.image_asset_id, row.image),
+            push_id: row.push_id,
+        }
+    }
+}
+
+/// A broadcast the talent worked: an overlay they were in as a caster on the day
+#[derive(Debug, Clone)]
+pub struct TalentAppearance {
+    pub overlay_id: Uuid,
+    pub day: NaiveDate,
+    pub tournament_slug: String,
+    pub overlay_name: Option<String>,
+    pub role: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl DB {
+    pub async fn get_organization_talent(
+        &self,
+        organization_id: Uuid,
+    ) -> Result<Vec<Talent>, anyhow::Error> {
+        sqlx::query_as!(
+            TalentRow,
+            "SELECT id, organization_id, name, handle, pronouns, image, image_asset_id, push_id FROM talent
+            WHERE organization_id = $1 ORDER BY name",
+            organization_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get talent: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(|rows| rows.into_iter().map(Talent::from).collect())
+    }
+
+    /// Talent of the organization that manages the tournament, if any
+    pub async fn get_tournament_talent(
+        &self,
+        tournament_slug: &str,
+    ) -> Result<Vec<Talent>, anyhow::Error> {
+        sqlx::query_as!(
+            TalentRow,
+            "SELECT t.id, t.organization_id, t.name, t.handle, t.pronouns, t.image, t.image_asset_id, t.push_id
+            FROM talent t
+            JOIN organization_tournament o ON o.organization_id = t.organization_id
+            WHERE o.tournament_slug = $1 ORDER BY t.name",
+            tournament_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get tournament talent: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(|rows| rows.into_iter().map(Talent::from).collect())
+    }
+
+    pub async fn get_talent(
+        &self,
+        organization_id: Uuid,
+        id: Uuid,
+    ) -> Result<Talent, anyhow::Error> {
+        sqlx::query_as!(
+            TalentRow,
+            "SELECT id, organization_id, name, handle, pronouns, image, image_asset_id, push_id FROM talent
+            WHERE organization_id = $1 AND id = $2",
+            organization_id,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get talent: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+        .map(Talent::from)
+    }
+
+    pub async fn insert_talent(&self, talent: &Talent) -> Result<(), anyhow::Error> {
+        let (image_asset_id, image) = image_reference(talent.image.as_deref());
+        sqlx::query!(
+            "INSERT INTO talent (id, organization_id, name, handle, pronouns, image, image_asset_id, push_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            talent.id,
+            talent.organization_id,
+            talent.name,
+            talent.handle,
+            talent.pronouns,
+            image,
+            image_asset_id,
+            talent.push_id,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert talent: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    pub async fn update_talent(&self, talent: &Talent) -> Result<(), anyhow::Error> {
+        let (image_asset_id, image) = image_reference(talent.image.as_deref());
+        let response = sqlx::query!(
+            "UPDATE talent SET name = $3, handle = $4, pronouns = $5, image = $6, image_asset_id = $7, push_id = $8
+            WHERE organization_id = $1 AND id = $2",
+            talent.organization_id,
+            talent.id,
+            talent.name,
+            talent.handle,
+            talent.pronouns,
+            image,
+            image_asset_id,
+            talent.push_id,
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to update talent"))
+        }
+    }
+
+    pub async fn delete_talent(
+        &self,
+        organization_id: Uuid,
+        id: Uuid,
+    ) -> Result<(), anyhow::Error> {
+        let response = sqlx::query!(
+            "DELETE FROM talent WHERE organization_id = $1 AND id = $2",
+            organization_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        if response.rows_affected() > 0 {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("failed to delete talent"))
+        }
+    }
+
+    /// Counts the broadcast of the overlay for the talent among the casters, once a day
+    pub async fn record_talent_appearances(
+        &self,
+        overlay: &Overlay,
+        casters: &[Caster],
+    ) -> Result<(), anyhow::Error> {
+        for caster in casters {
+            let Some(talent_id) = caster.talent_id else {
+                continue;
+            };
+            sqlx::query!(
+                "INSERT INTO talent_appearance (talent_id, overlay_id, tournament_slug, overlay_name, role)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (talent_id, overlay_id, day)
+                DO UPDATE SET role = $5, overlay_name = $4, last_seen_at = now()",
+                talent_id,
+                overlay.id,
+                overlay.tournament_slug,
+                overlay.name,
+                caster.role,
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to record talent appearance: {}", e))
+            .inspect_err(|e| tracing::error!("{}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Broadcasts the talent worked, the latest first
+    pub async fn get_talent_appearances(
+        &self,
+        talent_id: Uuid,
+    ) -> Result<Vec<TalentAppearance>, anyhow::Error> {
+        sqlx::query_as!(
+            TalentAppearance,
+            r#"SELECT overlay_id, day AS "day: NaiveDate", tournament_slug, overlay_name, role,
+            first_seen_at AS "first_seen_at: DateTime<Utc>", last_seen_at AS "last_seen_at: DateTime<Utc>"
+            FROM talent_appearance WHERE talent_id = $1
+            ORDER BY day DESC, first_seen_at DESC"#,
+            talent_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get talent appearances: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+}
@@ -1,5 +1,8 @@
-use crate::startgg::tournaments::{StartGGImage, StartGGTeam};
+use std::collections::HashMap;
 
+use crate::startgg::tournaments::{StartGGImage, StartGGTeam, StartGGTeamMember};
+
+use super::assets::{image_reference, image_url};
 use super::DB;
 
 // #[derive(Debug)]
@@ -11,6 +14,7 @@ use super::DB;
 // }
 
 impl DB {
+    /// The team without its members, they are loaded with `get_team_members` where needed
     pub async fn get_team(&self, team_id: &str) -> Result<StartGGTeam, anyhow::Error> {
         sqlx::query!("SELECT * from team WHERE id = $1", team_id)
             .fetch_one(&self.pool)
@@ -20,7 +24,7 @@ impl DB {
             .map(|row| StartGGTeam {
                 name: row.name,
                 nickname: row.nickname,
-                image: row.image.map(|img| StartGGImage {
+                image: image_url(row.image_asset_id, row.image).map(|img| StartGGImage {
                     url: img,
                     height: 0f64,
                     width: 0f64,
@@ -30,10 +34,59 @@ impl DB {
             })
     }
 
+    pub async fn get_team_members(
+        &self,
+        team_id: &str,
+    ) -> Result<Vec<StartGGTeamMember>, anyhow::Error> {
+        Ok(sqlx::query!(
+            "SELECT * from team_member WHERE team_id = $1 ORDER BY captain DESC, alternate ASC, gamer_tag ASC",
+            team_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get team members: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .into_iter()
+        .map(|row| StartGGTeamMember {
+            gamer_tag: row.gamer_tag,
+            prefix: row.prefix,
+            capitain: row.captain,
+            alternate: row.alternate,
+            discord_id: row.discord_id,
+        })
+        .collect())
+    }
+
     pub async fn get_tournament_teams(
         &self,
         tournament_slug: &str,
     ) -> Result<Vec<StartGGTeam>, anyhow::Error> {
+        let mut members: HashMap<String, Vec<StartGGTeamMember>> = HashMap::new();
+        for row in sqlx::query!(
+            r#"SELECT team_member.* from team_member
+               JOIN team ON team.id = team_member.team_id
+               WHERE team.tournament_slug = $1
+               ORDER BY captain DESC, alternate ASC, gamer_tag ASC
+            "#,
+            tournament_slug
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get team members: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        {
+            members
+                .entry(row.team_id)
+                .or_default()
+                .push(StartGGTeamMember {
+                    gamer_tag: row.gamer_tag,
+                    prefix: row.prefix,
+                    capitain: row.captain,
+                    alternate: row.alternate,
+                    discord_id: row.discord_id,
+                });
+        }
+
         Ok(sqlx::query!(
             "SELECT * from team WHERE tournament_slug = $1 ORDER BY name ASC",
             tournament_slug
@@ -46,13 +99,13 @@ impl DB {
             rows.into_iter().map(|row| StartGGTeam {
                 name: row.name,
                 nickname: row.nickname,
-                image: row.image.map(|img| StartGGImage {
+                image: image_url(row.image_asset_id, row.image).map(|img| StartGGImage {
                     url: img,
                     height: 0f64,
                     width: 0f64,
                 }),
+                team_members: members.remove(&row.id).unwrap_or_default(),
                 id: row.id,
-                team_members: vec![],
             })
         })?
         .collect::<Vec<_>>())
@@ -63,24 +116,28 @@ impl DB {
         tournament_slug: &str,
         team: &StartGGTeam,
     ) -> Result<(), anyhow::Error> {
+        let (image_asset_id, image) =
+            image_reference(team.image.as_ref().map(|img| img.url.as_str()));
         let query = if let Some(nickname) = &team.nickname {
             sqlx::query!(
-                "INSERT INTO team (tournament_slug, id, name, nickname, image) VALUES ($1, $2, $3, $4, $5)
-            ON CONFLICT (id) DO UPDATE SET name = $3, nickname = $4, image = $5",
+                "INSERT INTO team (tournament_slug, id, name, nickname, image, image_asset_id) VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO UPDATE SET name = $3, nickname = $4, image = $5, image_asset_id = $6",
                 tournament_slug,
                 team.id,
                 team.name,
                 nickname,
-                team.image.clone().map(|img| img.url)
+                image,
+                image_asset_id
             )
         } else {
             sqlx::query!(
-                "INSERT INTO team (tournament_slug, id, name, image) VALUES ($1, $2, $3, $4)
-            ON CONFLICT (id) DO UPDATE SET name = $3, image = $4",
+                "INSERT INTO team (tournament_slug, id, name, image, image_asset_id) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET name = $3, image = $4, image_asset_id = $5",
                 tournament_slug,
                 team.id,
                 team.name,
-                team.image.clone().map(|img| img.url)
+                image,
+                image_asset_id
             )
         };
         query
@@ -88,6 +145,43 @@ impl DB {
             .await
             .map_err(|e| anyhow::anyhow!("failed to upsert team: {}", e.to_string()))
             .inspect_err(|e| tracing::error!("{}", e))?;
+
+        // start.gg may not return the members of every team, so keep the stored ones in that case
+        if !team.team_members.is_empty() {
+            self.replace_team_members(&team.id, &team.team_members)
+                .await?;
+        }
         Ok(())
     }
+
+    async fn replace_team_members(
+        &self,
+        team_id: &str,
+        members: &[StartGGTeamMember],
+    ) -> Result<(), anyhow::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!("DELETE FROM team_member WHERE team_id = $1", team_id)
+            .execute(&mut *tx)
+            .await?;
+        for member in members {
+            sqlx::query!(
+                "INSERT INTO team_member (team_id, gamer_tag, prefix, captain, alternate, discord_id) VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (team_id, gamer_tag) DO NOTHING",
+                team_id,
+                member.gamer_tag,
+                member.prefix,
+                member.capitain,
+                member.alternate,
+                member.discord_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to update team members: {}", e))
+            .inspect_err(|e| tracing::error!("{}", e))
+    }
 }
@@ -0,0 +1,132 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::assets::{image_reference, image_url};
+use super::DB;
+
+/// Visual identity of a tournament's overlays
+#[derive(Debug, Clone, Serialize)]
+pub struct Theme {
+    pub tournament_slug: String,
+    pub primary_color: String,
+    pub secondary_color: String,
+    pub background_color: String,
+    pub panel_color: String,
+    pub surface_color: String,
+    pub text_color: String,
+    pub team_a_color: String,
+    pub team_b_color: String,
+    /// Google Fonts family name
+    pub font_family: String,
+    pub logo: String,
+    pub background_image: String,
+    /// Shows team B on the left side of the scoreboard
+    pub swap_scoreboard_sides: bool,
+}
+
+impl Theme {
+    /// Theme used by tournaments that didn't customize their overlays, same as the table defaults
+    pub fn default_for(tournament_slug: &str) -> Self {
+        Self {
+            tournament_slug: tournament_slug.to_string(),
+            primary_color: "#4180B0".to_string(),
+            secondary_color: "#878787".to_string(),
+            background_color: "#1D1D1D".to_string(),
+            panel_color: "#121212".to_string(),
+            surface_color: "#323232".to_string(),
+            text_color: "#FFFFFF".to_string(),
+            team_a_color: "#6592FD".to_string(),
+            team_b_color: "#B93D55".to_string(),
+            font_family: "Montserrat".to_string(),
+            logo: "/assets/SASL-with-text.png".to_string(),
+            background_image: "/assets/background-logo.png".to_string(),
+            swap_scoreboard_sides: false,
+        }
+    }
+}
+
+impl DB {
+    pub async fn get_theme(&self, tournament_slug: &str) -> Result<Theme, anyhow::Error> {
+        Ok(sqlx::query!(
+            "SELECT * from theme WHERE tournament_slug = $1",
+            tournament_slug
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get theme: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?
+        .map(|row| Theme {
+            tournament_slug: row.tournament_slug,
+            primary_color: row.primary_color,
+            secondary_color: row.secondary_color,
+            background_color: row.background_color,
+            panel_color: row.panel_color,
+            surface_color: row.surface_color,
+            text_color: row.text_color,
+            team_a_color: row.team_a_color,
+            team_b_color: row.team_b_color,
+            font_family: row.font_family,
+            logo: image_url(row.logo_asset_id, Some(row.logo)).unwrap_or_default(),
+            background_image: image_url(row.background_image_asset_id, Some(row.background_image))
+                .unwrap_or_default(),
+            swap_scoreboard_sides: row.swap_scoreboard_sides,
+        })
+        .unwrap_or_else(|| Theme::default_for(tournament_slug)))
+    }
+
+    /// Theme of the tournament the overlay belongs to
+    pub async fn get_overlay_theme(&self, overlay_id: Uuid) -> Result<Theme, anyhow::Error> {
+        let overlay = self.get_overlay(overlay_id).await?;
+        self.get_theme(&overlay.tournament_slug).await
+    }
+
+    pub async fn upsert_theme(&self, theme: &Theme) -> Result<(), anyhow::Error> {
+        let (logo_asset_id, logo) = image_reference(Some(&theme.logo));
+        let (background_image_asset_id, background_image) =
+            image_reference(Some(&theme.background_image));
+        let query = sqlx::query!(
+            r#"INSERT INTO theme
+                (tournament_slug, primary_color, secondary_color, background_color, panel_color, surface_color, text_color, team_a_color, team_b_color, font_family, logo, background_image, swap_scoreboard_sides, logo_asset_id, background_image_asset_id)
+                VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                ON CONFLICT (tournament_slug) DO
+                UPDATE SET
+                    primary_color = $2,
+                    secondary_color = $3,
+                    background_color = $4,
+                    panel_color = $5,
+                    surface_color = $6,
+                    text_color = $7,
+                    team_a_color = $8,
+                    team_b_color = $9,
+                    font_family = $10,
+                    logo = $11,
+                    background_image = $12,
+                    swap_scoreboard_sides = $13,
+                    logo_asset_id = $14,
+                    background_image_asset_id = $15
+            "#,
+            theme.tournament_slug,
+            theme.primary_color,
+            theme.secondary_color,
+            theme.background_color,
+            theme.panel_color,
+            theme.surface_color,
+            theme.text_color,
+            theme.team_a_color,
+            theme.team_b_color,
+            theme.font_family,
+            logo.unwrap_or_default(),
+            background_image.unwrap_or_default(),
+            theme.swap_scoreboard_sides,
+            logo_asset_id,
+            background_image_asset_id,
+        );
+        query
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to upsert theme: {}", e.to_string()))
+            .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use uuid::Uuid;
+
+use super::DB;
+
+const VDO_NINJA_URL: &str = "https://vdo.ninja/";
+
+/// VDO.ninja room the casters of the overlay push their video to
+#[derive(Debug, Clone)]
+pub struct VdoRoom {
+    pub overlay_id: Uuid,
+    pub password: String,
+}
+
+impl VdoRoom {
+    /// Room ID derived from the overlay, without the fixed digits of the UUIDv4
+    pub fn room_id(&self) -> String {
+        let mut id = self.overlay_id.simple().to_string();
+        id.remove(12); // UUIDv4 version number
+        id.remove(15); // UUIDv4 variant
+        id
+    }
+
+    pub fn director_link(&self) -> String {
+        format!(
+            "{}?director={}&password={}",
+            VDO_NINJA_URL,
+            self.room_id(),
+            self.password
+        )
+    }
+
+    /// Link the caster opens to join the room and push their camera
+    pub fn invite_link(&self, push_id: &str) -> String {
+        format!(
+            "{}?room={}&password={}&vd=0&avatar&push={}",
+            VDO_NINJA_URL,
+            self.room_id(),
+            self.password,
+            push_id
+        )
+    }
+
+    /// Link embedded in the overlay, showing only the caster's video
+    pub fn view_link(&self, push_id: &str) -> String {
+        format!(
+            "{}?view={}&solo&room={}&password={}",
+            VDO_NINJA_URL,
+            push_id,
+            self.room_id(),
+            self.password
+        )
+    }
+}
+
+impl DB {
+    pub async fn get_vdo_room(&self, overlay_id: Uuid) -> Result<Option<VdoRoom>, anyhow::Error> {
+        sqlx::query_as!(
+            VdoRoom,
+            "SELECT overlay_id, password FROM vdo_room WHERE overlay_id = $1",
+            overlay_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to get vdo room: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))
+    }
+
+    /// Creates the room unless the overlay already has one, in which case that one is kept
+    pub async fn insert_vdo_room(&self, room: &VdoRoom) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO vdo_room (overlay_id, password) VALUES ($1, $2)
+            ON CONFLICT (overlay_id) DO NOTHING",
+            room.overlay_id,
+            room.password,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to insert vdo room: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+
+    pub async fn upsert_vdo_room(&self, room: &VdoRoom) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO vdo_room (overlay_id, password) VALUES ($1, $2)
+            ON CONFLICT (overlay_id) DO UPDATE SET password = $2",
+            room.overlay_id,
+            room.password,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to upsert vdo room: {}", e))
+        .inspect_err(|e| tracing::error!("{}", e))?;
+        Ok(())
+    }
+}
@@ -1,10 +1,10 @@
 use chrono::{DateTime, FixedOffset};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::DB;
 
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum WaitType {
     Nothing,
@@ -49,7 +49,7 @@ impl std::fmt::Display for WaitTimerError {
 
 impl std::error::Error for WaitTimerError {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WaitTimer {
     pub overlay_id: Uuid,
     pub wait_until: DateTime<FixedOffset>,
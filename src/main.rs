@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use axum::Router;
@@ -9,6 +10,7 @@ use self::startgg::oauth::OAuthConfig;
 
 mod database;
 mod discord;
+mod obs;
 mod omegastrikers;
 mod routes;
 mod startgg;
@@ -30,8 +32,6 @@ struct App {
     startgg_oauth_client_id: String,
     #[arg(long, env = "STARTGG_OAUTH_CLIENT_SECRET")]
     startgg_oauth_client_secret: String,
-    #[arg(long, env = "STARTGG_TOKEN")]
-    startgg_token: String,
     #[arg(long, default_value = "http://127.0.0.1:3000/oauth/startgg_callback")]
     startgg_redirect_uri: String,
 
@@ -65,6 +65,9 @@ async fn shuttle_main(
     let startgg_redirect_uri = secrets
         .get("startgg_redirect_uri")
         .expect("Failed to load startgg_redirect_uri");
+    let startgg_token = secrets.get("startgg_token");
+    let startgg_endpoint = secrets.get("startgg_endpoint");
+    let assets_dir = secrets.get("assets_dir").map(PathBuf::from);
 
     let db = Arc::new(database::DB::from_pool(db_pool).await?);
 
@@ -75,11 +78,15 @@ async fn shuttle_main(
         &startgg_oauth_client_id,
         &startgg_oauth_client_secret,
         &startgg_redirect_uri,
+        startgg_token,
+        startgg_endpoint,
+        assets_dir,
     )
     .await?;
     Ok(shuttle_axum::AxumService(router))
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn common_main(
     db: Arc<DB>,
     omegastrikers_identity_file: &str,
@@ -87,7 +94,14 @@ async fn common_main(
     startgg_oauth_client_id: &str,
     startgg_oauth_client_secret: &str,
     startgg_redirect_uri: &str,
+    startgg_token: Option<String>,
+    startgg_endpoint: Option<String>,
+    assets_dir: Option<PathBuf>,
 ) -> anyhow::Result<Router> {
+    if let Some(dir) = &assets_dir {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+
     // let omegastrikers_client =
     //     omegastrikers::OmegaApiClient::new_from_file(omegastrikers_identity_file)?;
 
@@ -96,7 +110,7 @@ async fn common_main(
 
     // let _handler = tokio::spawn(async move { discord_bot.start().await });
 
-    let router = init_router(
+    let state = Arc::new(
         routes::AppState::builder(
             OAuthConfig {
                 startgg_client_id: startgg_oauth_client_id.to_string(),
@@ -105,8 +119,21 @@ async fn common_main(
             },
             db,
         )
+        .startgg_token(startgg_token)
+        .startgg_endpoint(startgg_endpoint)
+        .assets_dir(assets_dir)
         .build(),
     );
 
+    if state.startgg_token.is_some() {
+        let _sync_handler = tokio::spawn(routes::stream_overlay::startgg_sync::startgg_sync_task(
+            state.clone(),
+        ));
+    } else {
+        tracing::warn!("No start.gg token configured, start.gg set and bracket sync is disabled");
+    }
+
+    let router = init_router(state);
+
     Ok(router)
 }
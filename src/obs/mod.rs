@@ -0,0 +1,290 @@
+//! Minimal obs-websocket v5 client, only the requests used to drive the stream scenes.
+//! Protocol reference: https://github.com/obsproject/obs-websocket/blob/master/docs/generated/protocol.md
+
+use std::time::Duration;
+
+use base64::prelude::*;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const RPC_VERSION: u64 = 1;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+mod op {
+    pub const HELLO: u64 = 0;
+    pub const IDENTIFY: u64 = 1;
+    pub const IDENTIFIED: u64 = 2;
+    pub const REQUEST: u64 = 6;
+    pub const REQUEST_RESPONSE: u64 = 7;
+}
+
+/// `base64(sha256(base64(sha256(password + salt)) + challenge))`
+fn authentication(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = BASE64_STANDARD.encode(Sha256::digest(format!("{password}{salt}")));
+    BASE64_STANDARD.encode(Sha256::digest(format!("{secret}{challenge}")))
+}
+
+pub struct ObsClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_request_id: u64,
+}
+
+impl ObsClient {
+    pub async fn connect(url: &str, password: Option<&str>) -> anyhow::Result<Self> {
+        let (stream, _) =
+            tokio::time::timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(url))
+                .await
+                .map_err(|_| anyhow::anyhow!("timed out connecting to OBS at {}", url))??;
+
+        let mut client = Self {
+            stream,
+            next_request_id: 0,
+        };
+
+        let hello = client.receive(op::HELLO).await?;
+        let mut identify = json!({
+            "rpcVersion": RPC_VERSION,
+            // No events are needed, only requests
+            "eventSubscriptions": 0,
+        });
+        if let Some(auth) = hello.get("authentication") {
+            let password =
+                password.ok_or_else(|| anyhow::anyhow!("OBS websocket requires a password"))?;
+            identify["authentication"] = json!(authentication(
+                password,
+                auth["salt"].as_str().unwrap_or_default(),
+                auth["challenge"].as_str().unwrap_or_default(),
+            ));
+        }
+        client.send(op::IDENTIFY, identify).await?;
+        client.receive(op::IDENTIFIED).await?;
+
+        Ok(client)
+    }
+
+    async fn send(&mut self, op: u64, data: Value) -> anyhow::Result<()> {
+        let message = json!({ "op": op, "d": data }).to_string();
+        self.stream.send(Message::Text(message.into())).await?;
+        Ok(())
+    }
+
+    /// Waits for the next message with the given op code, skipping any other
+    async fn receive(&mut self, expected_op: u64) -> anyhow::Result<Value> {
+        while let Some(message) = self.stream.next().await {
+            let message = message?;
+            if let Message::Close(frame) = &message {
+                return Err(anyhow::anyhow!(
+                    "OBS closed the connection: {}",
+                    frame
+                        .as_ref()
+                        .map(|f| f.reason.to_string())
+                        .unwrap_or_default()
+                ));
+            }
+            if !message.is_text() {
+                continue;
+            }
+
+            let mut value: Value = serde_json::from_str(message.to_text()?)?;
+            if value["op"].as_u64() == Some(expected_op) {
+                return Ok(value["d"].take());
+            }
+        }
+        Err(anyhow::anyhow!("OBS closed the connection"))
+    }
+
+    async fn request(&mut self, request_type: &str, request_data: Value) -> anyhow::Result<Value> {
+        self.next_request_id += 1;
+        let request_id = self.next_request_id.to_string();
+
+        self.send(
+            op::REQUEST,
+            json!({
+                "requestType": request_type,
+                "requestId": request_id,
+                "requestData": request_data,
+            }),
+        )
+        .await?;
+
+        loop {
+            let mut response = self.receive(op::REQUEST_RESPONSE).await?;
+            if response["requestId"].as_str() != Some(&request_id) {
+                continue;
+            }
+
+            let status = &response["requestStatus"];
+            if status["result"].as_bool() != Some(true) {
+                return Err(anyhow::anyhow!(
+                    "OBS request {} failed ({}): {}",
+                    request_type,
+                    status["code"],
+                    status["comment"].as_str().unwrap_or_default()
+                ));
+            }
+            return Ok(response["responseData"].take());
+        }
+    }
+
+    pub async fn get_scene_names(&mut self) -> anyhow::Result<Vec<String>> {
+        let response = self.request("GetSceneList", json!({})).await?;
+        Ok(response["scenes"]
+            .as_array()
+            .map(|scenes| {
+                scenes
+                    .iter()
+                    .filter_map(|s| s["sceneName"].as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    pub async fn set_current_program_scene(&mut self, scene_name: &str) -> anyhow::Result<()> {
+        self.request("SetCurrentProgramScene", json!({ "sceneName": scene_name }))
+            .await?;
+        Ok(())
+    }
+
+    /// Shows or hides a source in the scene
+    pub async fn set_source_enabled(
+        &mut self,
+        scene_name: &str,
+        source_name: &str,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        let response = self
+            .request(
+                "GetSceneItemId",
+                json!({ "sceneName": scene_name, "sourceName": source_name }),
+            )
+            .await?;
+        let scene_item_id = response["sceneItemId"]
+            .as_i64()
+            .ok_or_else(|| anyhow::anyhow!("source {} not found in {}", source_name, scene_name))?;
+
+        self.request(
+            "SetSceneItemEnabled",
+            json!({
+                "sceneName": scene_name,
+                "sceneItemId": scene_item_id,
+                "sceneItemEnabled": enabled,
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn close(mut self) {
+        let _ = self.stream.close(None).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Accepts a single connection requiring authentication and answers every request
+    /// successfully, returning the requests it got
+    async fn mock_obs(listener: TcpListener, password: &str) -> Vec<Value> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let send =
+            |op: u64, d: Value| Message::Text(json!({ "op": op, "d": d }).to_string().into());
+
+        ws.send(send(
+            op::HELLO,
+            json!({
+                "obsWebSocketVersion": "5.0.0",
+                "rpcVersion": 1,
+                "authentication": { "challenge": "challenge", "salt": "salt" },
+            }),
+        ))
+        .await
+        .unwrap();
+
+        let identify: Value =
+            serde_json::from_str(ws.next().await.unwrap().unwrap().to_text().unwrap()).unwrap();
+        assert_eq!(identify["op"], op::IDENTIFY);
+        if identify["d"]["authentication"] != authentication(password, "salt", "challenge") {
+            ws.close(None).await.unwrap();
+            return vec![];
+        }
+        ws.send(send(op::IDENTIFIED, json!({ "negotiatedRpcVersion": 1 })))
+            .await
+            .unwrap();
+
+        let mut requests = vec![];
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            let request: Value = serde_json::from_str(&text).unwrap();
+            let d = request["d"].clone();
+            let response_data = match d["requestType"].as_str() {
+                Some("GetSceneItemId") => json!({ "sceneItemId": 7 }),
+                Some("GetSceneList") => json!({ "scenes": [{ "sceneName": "Ingame" }] }),
+                _ => json!({}),
+            };
+            ws.send(send(
+                op::REQUEST_RESPONSE,
+                json!({
+                    "requestType": d["requestType"],
+                    "requestId": d["requestId"],
+                    "requestStatus": { "result": true, "code": 100 },
+                    "responseData": response_data,
+                }),
+            ))
+            .await
+            .unwrap();
+            requests.push(d);
+        }
+        requests
+    }
+
+    #[tokio::test]
+    async fn test_scene_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(mock_obs(listener, "hunter2"));
+
+        let mut client = ObsClient::connect(&url, Some("hunter2")).await.unwrap();
+        assert_eq!(client.get_scene_names().await.unwrap(), vec!["Ingame"]);
+        client.set_current_program_scene("Ingame").await.unwrap();
+        client
+            .set_source_enabled("Ingame", "Placar", false)
+            .await
+            .unwrap();
+        client.close().await;
+
+        let requests = server.await.unwrap();
+        let request_types = requests
+            .iter()
+            .map(|r| r["requestType"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            request_types,
+            vec![
+                "GetSceneList",
+                "SetCurrentProgramScene",
+                "GetSceneItemId",
+                "SetSceneItemEnabled"
+            ]
+        );
+        assert_eq!(requests[1]["requestData"]["sceneName"], "Ingame");
+        assert_eq!(requests[3]["requestData"]["sceneItemId"], 7);
+        assert_eq!(requests[3]["requestData"]["sceneItemEnabled"], false);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(mock_obs(listener, "hunter2"));
+
+        assert!(ObsClient::connect(&url, Some("wrong")).await.is_err());
+        assert!(server.await.unwrap().is_empty());
+    }
+}
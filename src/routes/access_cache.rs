@@ -0,0 +1,85 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::startgg::tournaments::StartGGTournament;
+
+/// How long the tournaments a user administers on start.gg are trusted before asking again
+const ACCESS_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug)]
+struct Entry {
+    fetched_at: Instant,
+    tournament_slugs: HashSet<String>,
+}
+
+/// Per user cache of the tournaments they are start.gg admins of, so the access check doesn't
+/// go through every page of their tournaments on each request
+#[derive(Debug, Default)]
+pub struct TournamentAccessCache {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl TournamentAccessCache {
+    pub fn get(&self, user_slug: &str) -> Option<HashSet<String>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(user_slug) {
+            Some(entry) if entry.fetched_at.elapsed() < ACCESS_TTL => {
+                Some(entry.tournament_slugs.clone())
+            }
+            Some(_) => {
+                entries.remove(user_slug);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, user_slug: &str, tournaments: &[StartGGTournament]) -> HashSet<String> {
+        let tournament_slugs: HashSet<String> =
+            tournaments.iter().map(|t| t.slug.clone()).collect();
+        let mut entries = self.entries.lock().unwrap();
+        // Drop whatever expired while we're here, so users that never come back don't pile up
+        entries.retain(|_, entry| entry.fetched_at.elapsed() < ACCESS_TTL);
+        entries.insert(
+            user_slug.to_string(),
+            Entry {
+                fetched_at: Instant::now(),
+                tournament_slugs: tournament_slugs.clone(),
+            },
+        );
+        tournament_slugs
+    }
+
+    pub fn invalidate(&self, user_slug: &str) {
+        self.entries.lock().unwrap().remove(user_slug);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tournament(slug: &str) -> StartGGTournament {
+        StartGGTournament {
+            name: slug.to_string(),
+            images: vec![],
+            slug: slug.to_string(),
+            url: format!("/tournament/{slug}"),
+        }
+    }
+
+    #[test]
+    fn test_access_cache() {
+        let cache = TournamentAccessCache::default();
+        assert!(cache.get("user/a").is_none());
+
+        cache.insert("user/a", &[tournament("cup-1"), tournament("cup-2")]);
+        let slugs = cache.get("user/a").unwrap();
+        assert!(slugs.contains("cup-1") && slugs.contains("cup-2"));
+        assert!(cache.get("user/b").is_none());
+
+        cache.invalidate("user/a");
+        assert!(cache.get("user/a").is_none());
+    }
+}
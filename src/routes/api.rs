@@ -0,0 +1,616 @@
+//! Public JSON API of the overlays state, described in `assets/openapi.json`.
+//! Payloads are the same as the websocket protocol updates.
+//! Changing the state requires a tournament API token with the matching scope.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::{DateTime, FixedOffset};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::api_tokens::ApiClient;
+use super::error::AppError;
+use super::stream_overlay::casters::{
+    overlay_casters, set_casters, vdo_room, with_videos, MAX_CASTERS,
+};
+use super::stream_overlay::history;
+use super::stream_overlay::standings::{broadcast_standings, team_record};
+use super::stream_overlay::waiting::{broadcast_todays_matches, get_wait_timer, set_wait_timer};
+use super::stream_overlay::websocket::{
+    CasterState, CastersState, MatchesState, ScoreboardState, TeamState, TimerState,
+};
+use super::stream_overlay::{broadcast_scoreboard, lineups};
+use super::AppState;
+use crate::database::api_tokens::ApiScope;
+use crate::database::casters::Caster;
+use crate::database::matches::Match;
+use crate::database::overlay_events::OverlayState;
+use crate::database::scoreboard::Scoreboard;
+use crate::database::wait_timer::{WaitTimer, WaitType};
+use crate::startgg::tournaments::StartGGTeam;
+
+#[derive(Debug, Serialize)]
+pub struct TeamMemberState {
+    pub gamer_tag: String,
+    pub prefix: Option<String>,
+    pub captain: bool,
+    pub alternate: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TournamentTeam {
+    #[serde(flatten)]
+    pub team: TeamState,
+    pub members: Vec<TeamMemberState>,
+}
+
+impl From<&StartGGTeam> for TournamentTeam {
+    fn from(team: &StartGGTeam) -> Self {
+        Self {
+            team: team.into(),
+            members: team
+                .team_members
+                .iter()
+                .map(|m| TeamMemberState {
+                    gamer_tag: m.gamer_tag.clone(),
+                    prefix: m.prefix.clone(),
+                    captain: m.capitain,
+                    alternate: m.alternate,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Fails with 404 for unknown overlays, instead of an empty state
+async fn ensure_overlay(state: &AppState, overlay_id: Uuid) -> Result<(), AppError> {
+    state
+        .db
+        .get_overlay(overlay_id)
+        .await
+        .map_err(|_| AppError::from("Overlay não encontrado").with_status(StatusCode::NOT_FOUND))?;
+    Ok(())
+}
+
+#[axum::debug_handler]
+pub async fn scoreboard(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_overlay(&state, overlay_id).await?;
+    let scoreboard = get_scoreboard(&state, overlay_id).await?;
+    let team_a = state.db.get_team(&scoreboard.team_a).await?;
+    let team_b = state.db.get_team(&scoreboard.team_b).await?;
+    let theme = state.db.get_overlay_theme(overlay_id).await?;
+
+    Ok(Json(ScoreboardState::new(
+        &scoreboard,
+        &team_a,
+        &team_b,
+        theme.swap_scoreboard_sides,
+    )))
+}
+
+async fn get_scoreboard(state: &AppState, overlay_id: Uuid) -> Result<Scoreboard, AppError> {
+    state
+        .db
+        .get_scoreboard(overlay_id)
+        .await
+        .map_err(|_| AppError::from("Nenhum placar configurado").with_status(StatusCode::NOT_FOUND))
+}
+
+/// Saves the scoreboard changed by the API client and sends it to the overlay
+async fn save_scoreboard(
+    state: &AppState,
+    client: &ApiClient,
+    previous: Scoreboard,
+    scoreboard: Scoreboard,
+) -> Result<Json<ScoreboardState>, AppError> {
+    let scoreboard = state.db.upsert_scoreboard(scoreboard).await?;
+    publish_scoreboard(state, client, previous, scoreboard).await
+}
+
+/// Records the change made by the API client and sends the saved scoreboard to the overlay
+async fn publish_scoreboard(
+    state: &AppState,
+    client: &ApiClient,
+    previous: Scoreboard,
+    scoreboard: Scoreboard,
+) -> Result<Json<ScoreboardState>, AppError> {
+    let overlay_id = scoreboard.overlay_id;
+    let teams_changed =
+        previous.team_a != scoreboard.team_a || previous.team_b != scoreboard.team_b;
+    history::record_change(
+        state,
+        overlay_id,
+        &client.actor(),
+        OverlayState::Scoreboard(Some(previous)),
+        OverlayState::Scoreboard(Some(scoreboard.clone())),
+    )
+    .await;
+
+    let team_a = state.db.get_team(&scoreboard.team_a).await?;
+    let team_b = state.db.get_team(&scoreboard.team_b).await?;
+    broadcast_scoreboard(state, overlay_id, &scoreboard, &team_a, &team_b).await?;
+    if teams_changed {
+        lineups::broadcast_lineups(state, overlay_id).await?;
+    }
+    let theme = state.db.get_overlay_theme(overlay_id).await?;
+
+    Ok(Json(ScoreboardState::new(
+        &scoreboard,
+        &team_a,
+        &team_b,
+        theme.swap_scoreboard_sides,
+    )))
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreboardSide {
+    A,
+    B,
+}
+
+/// Sets the score of a team, or changes it by `delta`
+#[derive(Debug, Deserialize)]
+pub struct ScoreUpdate {
+    team: ScoreboardSide,
+    score: Option<i32>,
+    delta: Option<i32>,
+}
+
+async fn apply_score_update(
+    state: &AppState,
+    client: &ApiClient,
+    overlay_id: Uuid,
+    update: ScoreUpdate,
+) -> Result<Json<ScoreboardState>, AppError> {
+    client
+        .authorize(state, overlay_id, ApiScope::Scoreboard)
+        .await?;
+    if update.score.is_some() == update.delta.is_some() {
+        return Err(
+            AppError::from("Informe score ou delta").with_status(StatusCode::UNPROCESSABLE_ENTITY)
+        );
+    }
+
+    let (previous, scoreboard) = state
+        .db
+        .update_team_score(
+            overlay_id,
+            matches!(update.team, ScoreboardSide::A),
+            update.score,
+            update.delta,
+        )
+        .await?
+        .ok_or_else(|| {
+            AppError::from("Nenhum placar configurado").with_status(StatusCode::NOT_FOUND)
+        })?;
+    publish_scoreboard(state, client, previous, scoreboard).await
+}
+
+#[axum::debug_handler]
+pub async fn update_score(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+    client: ApiClient,
+    Json(update): Json<ScoreUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    apply_score_update(&state, &client, overlay_id, update).await
+}
+
+#[axum::debug_handler]
+pub async fn matches(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_overlay(&state, overlay_id).await?;
+    let matches = state.db.get_overlay_matches(overlay_id).await?;
+
+    Ok(Json(MatchesState::from(matches.as_slice())))
+}
+
+#[axum::debug_handler]
+pub async fn casters(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_overlay(&state, overlay_id).await?;
+    let casters = overlay_casters(&state, overlay_id).await?;
+
+    Ok(Json(CastersState::from(casters.as_slice())))
+}
+
+#[axum::debug_handler]
+pub async fn timer(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_overlay(&state, overlay_id).await?;
+    let wait_timer = state.db.get_wait_timer(&overlay_id).await.ok();
+
+    Ok(Json(TimerState::from(&wait_timer)))
+}
+
+/// Starts the timer at `wait_until`, or `minutes` from now.
+/// The wait type is kept when not given.
+#[derive(Debug, Deserialize)]
+pub struct TimerUpdate {
+    minutes: Option<i64>,
+    wait_until: Option<DateTime<FixedOffset>>,
+    wait_type: Option<WaitType>,
+}
+
+async fn apply_timer_update(
+    state: Arc<AppState>,
+    client: &ApiClient,
+    overlay_id: Uuid,
+    update: TimerUpdate,
+) -> Result<Json<TimerState>, AppError> {
+    client
+        .authorize(&state, overlay_id, ApiScope::Timer)
+        .await?;
+    let previous = get_wait_timer(state.clone(), &overlay_id).await;
+
+    let wait_until = match (update.wait_until, update.minutes) {
+        (Some(wait_until), None) => wait_until,
+        (None, Some(minutes)) if minutes >= 0 => {
+            // Keeps the timezone of the current timer, America/Sao_Paulo by default
+            let offset = previous
+                .as_ref()
+                .map(|t| *t.wait_until.offset())
+                .unwrap_or_else(|| FixedOffset::west_opt(3 * 60 * 60).unwrap());
+            (chrono::Utc::now() + chrono::Duration::minutes(minutes)).with_timezone(&offset)
+        }
+        _ => {
+            return Err(AppError::from("Informe wait_until ou minutes")
+                .with_status(StatusCode::UNPROCESSABLE_ENTITY))
+        }
+    };
+    let wait_type = update
+        .wait_type
+        .or(previous.as_ref().map(|t| t.wait_type.clone()))
+        .unwrap_or(WaitType::Starting);
+
+    let wait_timer = set_wait_timer(
+        state.clone(),
+        WaitTimer {
+            overlay_id,
+            wait_until,
+            wait_type,
+        },
+    )
+    .await?;
+    history::record_change(
+        &state,
+        overlay_id,
+        &client.actor(),
+        OverlayState::WaitTimer(previous),
+        OverlayState::WaitTimer(wait_timer.clone()),
+    )
+    .await;
+
+    Ok(Json(TimerState::from(&wait_timer)))
+}
+
+#[axum::debug_handler]
+pub async fn update_timer(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+    client: ApiClient,
+    Json(update): Json<TimerUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    apply_timer_update(state, &client, overlay_id, update).await
+}
+
+/// Either the list of casters or, as in the two caster version, the narrator and the commenter
+#[derive(Debug, Deserialize)]
+pub struct CastersUpdate {
+    casters: Option<Vec<CasterState>>,
+    narrator: Option<CasterState>,
+    commenter: Option<CasterState>,
+}
+
+impl CastersUpdate {
+    fn casters(self) -> Vec<CasterState> {
+        if let Some(casters) = self.casters {
+            return casters;
+        }
+        let with_role = |caster: CasterState, role: &str| CasterState {
+            role: if caster.role.is_empty() {
+                role.to_string()
+            } else {
+                caster.role
+            },
+            ..caster
+        };
+        [
+            self.narrator.map(|c| with_role(c, "Narrador")),
+            self.commenter.map(|c| with_role(c, "Comentarista")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+#[axum::debug_handler]
+pub async fn update_casters(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+    client: ApiClient,
+    Json(update): Json<CastersUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    client
+        .authorize(&state, overlay_id, ApiScope::Casters)
+        .await?;
+
+    let update = update.casters();
+    if update.len() > MAX_CASTERS {
+        return Err(AppError::from(format!("No máximo {} casters", MAX_CASTERS))
+            .with_status(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+    let room = vdo_room(&state, overlay_id).await?;
+    let casters = update
+        .into_iter()
+        .map(|caster| {
+            let push_id = caster.push_id.filter(|p| !p.is_empty());
+            // Casters sent back as they were read come with the generated view link
+            let generated = push_id
+                .as_ref()
+                .is_some_and(|p| room.view_link(p) == caster.stream_video);
+            Caster {
+                overlay_id,
+                name: caster.name,
+                role: caster.role,
+                stream_video: if generated {
+                    String::new()
+                } else {
+                    caster.stream_video
+                },
+                image: caster.image.filter(|i| !i.is_empty()),
+                handle: caster.handle.filter(|h| !h.is_empty()),
+                pronouns: caster.pronouns.filter(|p| !p.is_empty()),
+                push_id,
+                talent_id: caster.talent_id,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let previous = state.db.get_casters(&overlay_id).await?;
+    let casters = set_casters(&state, overlay_id, casters).await?;
+    history::record_change(
+        &state,
+        overlay_id,
+        &client.actor(),
+        OverlayState::Casters(previous),
+        OverlayState::Casters(casters.clone()),
+    )
+    .await;
+
+    let casters = with_videos(&room, casters);
+    Ok(Json(CastersState::from(casters.as_slice())))
+}
+
+#[axum::debug_handler]
+pub async fn tournament_teams(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let teams = state.db.get_tournament_teams(&tournament_slug).await?;
+
+    Ok(Json(
+        teams.iter().map(TournamentTeam::from).collect::<Vec<_>>(),
+    ))
+}
+
+// Quick actions, meant to be bound to hardware buttons like a Stream Deck.
+// They take no body and answer with the new state, like the endpoints above.
+
+const MAX_BREAK_MINUTES: i64 = 180;
+
+#[axum::debug_handler]
+pub async fn increment_score(
+    State(state): State<Arc<AppState>>,
+    Path((overlay_id, team)): Path<(Uuid, ScoreboardSide)>,
+    client: ApiClient,
+) -> Result<impl IntoResponse, AppError> {
+    let update = ScoreUpdate {
+        team,
+        score: None,
+        delta: Some(1),
+    };
+    apply_score_update(&state, &client, overlay_id, update).await
+}
+
+#[axum::debug_handler]
+pub async fn decrement_score(
+    State(state): State<Arc<AppState>>,
+    Path((overlay_id, team)): Path<(Uuid, ScoreboardSide)>,
+    client: ApiClient,
+) -> Result<impl IntoResponse, AppError> {
+    let update = ScoreUpdate {
+        team,
+        score: None,
+        delta: Some(-1),
+    };
+    apply_score_update(&state, &client, overlay_id, update).await
+}
+
+/// Puts the teams on the other side of the scoreboard, keeping who is team A and B
+#[axum::debug_handler]
+pub async fn swap_sides(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+    client: ApiClient,
+) -> Result<impl IntoResponse, AppError> {
+    client
+        .authorize(&state, overlay_id, ApiScope::Scoreboard)
+        .await?;
+    let scoreboard = get_scoreboard(&state, overlay_id).await?;
+
+    let swapped = Scoreboard {
+        sides_swapped: !scoreboard.sides_swapped,
+        ..scoreboard.clone()
+    };
+    save_scoreboard(&state, &client, scoreboard, swapped).await
+}
+
+#[axum::debug_handler]
+pub async fn reset_series(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+    client: ApiClient,
+) -> Result<impl IntoResponse, AppError> {
+    client
+        .authorize(&state, overlay_id, ApiScope::Scoreboard)
+        .await?;
+    let scoreboard = get_scoreboard(&state, overlay_id).await?;
+    let matches = state.db.get_overlay_matches(overlay_id).await?;
+
+    if let Some(current) = scoreboard_match(&scoreboard, &matches) {
+        let m = matches[current].clone();
+        state.db.set_match_games(m.id, &[]).await?;
+        state
+            .db
+            .upsert_match(Match {
+                team_a_score: 0,
+                team_b_score: 0,
+                completed: false,
+                games: vec![],
+                ..m.clone()
+            })
+            .await?;
+        publish_matches(&state, &client, overlay_id, matches).await?;
+        if m.completed {
+            broadcast_standings(&state, &m.tournament_slug).await?;
+        }
+    }
+
+    let reset = Scoreboard {
+        team_a_score: 0,
+        team_b_score: 0,
+        ..scoreboard.clone()
+    };
+    save_scoreboard(&state, &client, scoreboard, reset).await
+}
+
+/// Position of the match on the scoreboard in today's matches, whichever side each team is on
+fn scoreboard_match(scoreboard: &Scoreboard, matches: &[Match]) -> Option<usize> {
+    matches.iter().position(|m| {
+        (m.team_a.id == scoreboard.team_a && m.team_b.id == scoreboard.team_b)
+            || (m.team_a.id == scoreboard.team_b && m.team_b.id == scoreboard.team_a)
+    })
+}
+
+/// Records the change made by the API client to today's matches and sends them to the overlay
+async fn publish_matches(
+    state: &AppState,
+    client: &ApiClient,
+    overlay_id: Uuid,
+    previous: Vec<Match>,
+) -> Result<(), AppError> {
+    let matches = state.db.get_overlay_matches(overlay_id).await?;
+    history::record_change(
+        state,
+        overlay_id,
+        &client.actor(),
+        OverlayState::Matches(previous),
+        OverlayState::Matches(matches.clone()),
+    )
+    .await;
+    broadcast_todays_matches(state, overlay_id, &matches)?;
+    Ok(())
+}
+
+/// Puts the first match still to be played after the current one on the scoreboard,
+/// going back to the start of today's matches when there are none after it
+#[axum::debug_handler]
+pub async fn next_match(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+    client: ApiClient,
+) -> Result<impl IntoResponse, AppError> {
+    client
+        .authorize(&state, overlay_id, ApiScope::Scoreboard)
+        .await?;
+    let scoreboard = get_scoreboard(&state, overlay_id).await?;
+    let matches = state.db.get_overlay_matches(overlay_id).await?;
+
+    let current = scoreboard_match(&scoreboard, &matches);
+    let next = matches
+        .iter()
+        .enumerate()
+        .filter(|(i, m)| !m.completed && Some(*i) != current)
+        .min_by_key(|(i, _)| current.is_some_and(|c| *i < c))
+        .map(|(_, m)| m.clone())
+        .ok_or_else(|| {
+            AppError::from("Nenhuma partida a jogar na lista de hoje")
+                .with_status(StatusCode::NOT_FOUND)
+        })?;
+
+    // The next match becomes the one being played and shown, the current one stops being played
+    for (i, m) in matches.iter().enumerate() {
+        let in_progress = m.id == next.id || (m.in_progress && Some(i) != current);
+        let featured = m.id == next.id;
+        if m.in_progress != in_progress || m.featured != featured {
+            state
+                .db
+                .upsert_match(Match {
+                    in_progress,
+                    featured,
+                    ..m.clone()
+                })
+                .await?;
+        }
+    }
+    publish_matches(&state, &client, overlay_id, matches).await?;
+
+    let overlay = state.db.get_overlay(overlay_id).await?;
+    let tournament_standings = state
+        .db
+        .get_tournament_standings(&overlay.tournament_slug)
+        .await?;
+
+    let updated = Scoreboard {
+        team_a: next.team_a.id.clone(),
+        team_b: next.team_b.id.clone(),
+        team_a_score: next.team_a_score,
+        team_b_score: next.team_b_score,
+        team_a_standing: team_record(&tournament_standings, &next.team_a.id),
+        team_b_standing: team_record(&tournament_standings, &next.team_b.id),
+        startgg_set_id: next.startgg_set_id.clone(),
+        best_of: next.best_of,
+        sides_swapped: false,
+        ..scoreboard.clone()
+    };
+    save_scoreboard(&state, &client, scoreboard, updated).await
+}
+
+/// Starts a break ending in the given minutes, usually 5, 10 or 15
+#[axum::debug_handler]
+pub async fn start_break(
+    State(state): State<Arc<AppState>>,
+    Path((overlay_id, minutes)): Path<(Uuid, i64)>,
+    client: ApiClient,
+) -> Result<impl IntoResponse, AppError> {
+    if !(1..=MAX_BREAK_MINUTES).contains(&minutes) {
+        return Err(AppError::from(format!(
+            "A pausa deve ter entre 1 e {} minutos",
+            MAX_BREAK_MINUTES
+        ))
+        .with_status(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+
+    let update = TimerUpdate {
+        minutes: Some(minutes),
+        wait_until: None,
+        wait_type: Some(WaitType::Break),
+    };
+    apply_timer_update(state, &client, overlay_id, update).await
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{FromRequestParts, Path, State};
+use axum::http::{header, request::Parts};
+use axum::response::{Html, IntoResponse};
+use axum_extra::extract::Form;
+use axum_htmx::HxRequest;
+use base64::Engine;
+use rand::{rngs::OsRng, TryRngCore};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::database::api_tokens::{ApiScope, ApiToken};
+use crate::startgg::auth::AuthSession;
+use crate::startgg::oauth::StartggUser;
+
+use super::error::AppError;
+use super::views::filters;
+use super::AppState;
+
+/// Makes leaked tokens easy to spot, e.g. by secret scanners
+const TOKEN_PREFIX: &str = "ovt_";
+
+/// Random URL-safe secret, also used for invite codes
+pub(super) fn generate_token(prefix: &str) -> Result<String, AppError> {
+    let mut bytes = [0u8; 32];
+    OsRng.try_fill_bytes(&mut bytes)?;
+    Ok(format!(
+        "{}{}",
+        prefix,
+        base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(bytes)
+    ))
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Client of the control API, authenticated by an `Authorization: Bearer <token>` header
+#[derive(Debug, Clone)]
+pub struct ApiClient(pub ApiToken);
+
+impl FromRequestParts<Arc<AppState>> for ApiClient {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let unauthorized = || AppError::from("Token de API inválido").with_unauthorized();
+
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or_else(unauthorized)?;
+
+        let api_token = state
+            .db
+            .use_api_token(&hash_token(token))
+            .await?
+            .ok_or_else(unauthorized)?;
+
+        Ok(ApiClient(api_token))
+    }
+}
+
+impl ApiClient {
+    /// How the token is identified in the overlays history
+    pub fn actor(&self) -> String {
+        format!("API: {}", self.0.name)
+    }
+
+    /// Fails unless the token has the scope and belongs to the tournament of the overlay
+    pub async fn authorize(
+        &self,
+        state: &AppState,
+        overlay_id: Uuid,
+        scope: ApiScope,
+    ) -> Result<(), AppError> {
+        let overlay = state.db.get_overlay(overlay_id).await.map_err(|_| {
+            AppError::from("Overlay não encontrado").with_status(StatusCode::NOT_FOUND)
+        })?;
+
+        if overlay.tournament_slug != self.0.tournament_slug {
+            return Err(
+                AppError::from("O token não pertence ao campeonato do overlay")
+                    .with_status(StatusCode::FORBIDDEN),
+            );
+        }
+        if !self.0.allows(scope) {
+            return Err(
+                AppError::from(format!("O token não tem permissão de {}", scope))
+                    .with_status(StatusCode::FORBIDDEN),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Template)]
+#[template(path = "api_tokens.html", blocks = ["tokens"])]
+pub struct ApiTokensTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub tournament_slug: String,
+    pub api_tokens: Vec<ApiToken>,
+    /// Only shown right after creating the token, it can't be recovered later
+    pub new_token: Option<String>,
+    pub error: Option<String>,
+}
+
+#[axum::debug_handler]
+pub async fn api_tokens_handler(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    HxRequest(is_hx_request): HxRequest,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let api_tokens = state.db.get_tournament_api_tokens(&tournament_slug).await?;
+
+    let template = ApiTokensTemplate {
+        maybe_user: None,
+        tournament_slug,
+        api_tokens,
+        new_token: None,
+        error: None,
+    };
+
+    if is_hx_request {
+        return Ok(Html(template.as_tokens().render()?));
+    }
+
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let user = startgg_client.fetch_startgg_user().await?;
+
+    Ok(Html(
+        ApiTokensTemplate {
+            maybe_user: Some(user),
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenForm {
+    name: String,
+    #[serde(default)]
+    scopes: Vec<ApiScope>,
+}
+
+#[axum::debug_handler]
+pub async fn create_api_token(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    _auth_session: AuthSession,
+    Form(form): Form<CreateApiTokenForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let name = form.name.trim();
+    let mut new_token = None;
+    let mut error = None;
+
+    if name.is_empty() {
+        error = Some("Informe um nome para o token".to_string());
+    } else if form.scopes.is_empty() {
+        error = Some("Selecione ao menos uma permissão".to_string());
+    } else {
+        let token = generate_token(TOKEN_PREFIX)?;
+        state
+            .db
+            .insert_api_token(&tournament_slug, name, &hash_token(&token), &form.scopes)
+            .await?;
+        tracing::info!("Created api token {} for {}", name, tournament_slug);
+        new_token = Some(token);
+    }
+
+    let api_tokens = state.db.get_tournament_api_tokens(&tournament_slug).await?;
+
+    Ok(Html(
+        ApiTokensTemplate {
+            maybe_user: None,
+            tournament_slug,
+            api_tokens,
+            new_token,
+            error,
+        }
+        .as_tokens()
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn delete_api_token(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, token_id)): Path<(String, Uuid)>,
+    _auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .db
+        .delete_api_token(&tournament_slug, token_id)
+        .await?;
+    tracing::info!("Revoked api token {} of {}", token_id, tournament_slug);
+
+    let api_tokens = state.db.get_tournament_api_tokens(&tournament_slug).await?;
+
+    Ok(Html(
+        ApiTokensTemplate {
+            maybe_user: None,
+            tournament_slug,
+            api_tokens,
+            new_token: None,
+            error: None,
+        }
+        .as_tokens()
+        .render()?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_hash() {
+        let token = generate_token(TOKEN_PREFIX).unwrap_or_default();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(hash_token(&token).len(), 64);
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(
+            hash_token(&token),
+            hash_token(&generate_token(TOKEN_PREFIX).unwrap_or_default())
+        );
+    }
+}
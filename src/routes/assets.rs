@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Multipart, Path, Query, State};
+use axum::http::header;
+use axum::response::{Html, IntoResponse};
+use axum_htmx::HxRequest;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::assets::Asset;
+use crate::startgg::auth::AuthSession;
+use crate::startgg::oauth::StartggUser;
+
+use super::error::AppError;
+use super::views::filters;
+use super::AppState;
+
+pub const MAX_ASSET_SIZE: usize = 5 * 1024 * 1024;
+
+/// Error for images uploaded to another tournament, which can't be shown by this one
+pub const FOREIGN_IMAGE: &str = "A imagem foi enviada para outro campeonato";
+
+/// Content type of the image, detected from its first bytes.
+/// SVGs aren't accepted since they can carry scripts.
+fn image_content_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+#[derive(Template)]
+#[template(path = "assets.html", blocks = ["gallery"])]
+pub struct AssetsTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub tournament_slug: String,
+    pub assets: Vec<Asset>,
+    /// Id of the input filled when an asset is picked, only set when used as a picker
+    pub target: Option<String>,
+    pub error: Option<String>,
+}
+
+impl AssetsTemplate {
+    /// Keeps the picker target on the gallery's own requests
+    fn query(&self) -> String {
+        match &self.target {
+            Some(target) => format!("?target={}", target),
+            None => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssetsQuery {
+    target: Option<String>,
+}
+
+impl AssetsQuery {
+    fn target(self) -> Option<String> {
+        self.target.filter(|t| {
+            !t.is_empty()
+                && t.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+    }
+}
+
+#[axum::debug_handler]
+pub async fn assets_handler(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    Query(query): Query<AssetsQuery>,
+    HxRequest(is_hx_request): HxRequest,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let assets = state.db.get_tournament_assets(&tournament_slug).await?;
+
+    let template = AssetsTemplate {
+        maybe_user: None,
+        tournament_slug,
+        assets,
+        target: query.target(),
+        error: None,
+    };
+
+    if is_hx_request {
+        return Ok(Html(template.as_gallery().render()?));
+    }
+
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let user = startgg_client.fetch_startgg_user().await?;
+
+    Ok(Html(
+        AssetsTemplate {
+            maybe_user: Some(user),
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn upload_asset(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    Query(query): Query<AssetsQuery>,
+    _auth_session: AuthSession,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    let mut error = None;
+
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let filename = field.file_name().unwrap_or("imagem").to_string();
+        let data = field.bytes().await?;
+
+        if data.is_empty() {
+            error = Some("Selecione um arquivo".to_string());
+            break;
+        }
+        if data.len() > MAX_ASSET_SIZE {
+            error = Some(format!(
+                "{} excede o tamanho máximo de {} MB",
+                filename,
+                MAX_ASSET_SIZE / 1024 / 1024
+            ));
+            break;
+        }
+        let Some(content_type) = image_content_type(&data) else {
+            error = Some(format!(
+                "{} não é uma imagem PNG, JPEG, GIF ou WebP",
+                filename
+            ));
+            break;
+        };
+
+        let asset = state
+            .db
+            .insert_asset(
+                &tournament_slug,
+                &filename,
+                content_type,
+                data.len() as i32,
+                state.assets_dir.is_none().then_some(&data[..]),
+            )
+            .await?;
+
+        if let Some(dir) = &state.assets_dir {
+            if let Err(e) = tokio::fs::write(dir.join(asset.id.to_string()), &data).await {
+                tracing::error!("Failed to write asset {}: {}", asset.id, e);
+                state.db.delete_asset(&tournament_slug, asset.id).await?;
+                return Err(format!("Falha ao salvar {}", filename).into());
+            }
+        }
+    }
+
+    let assets = state.db.get_tournament_assets(&tournament_slug).await?;
+
+    Ok(Html(
+        AssetsTemplate {
+            maybe_user: None,
+            tournament_slug,
+            assets,
+            target: query.target(),
+            error,
+        }
+        .as_gallery()
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn delete_asset(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, asset_id)): Path<(String, Uuid)>,
+    Query(query): Query<AssetsQuery>,
+    _auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let mut references = state.db.get_asset_references(asset_id).await?;
+    references.dedup();
+
+    let error = if references.is_empty() {
+        state.db.delete_asset(&tournament_slug, asset_id).await?;
+
+        if let Some(dir) = &state.assets_dir {
+            let _ = tokio::fs::remove_file(dir.join(asset_id.to_string()))
+                .await
+                .inspect_err(|e| tracing::error!("Failed to remove asset {}: {}", asset_id, e));
+        }
+        None
+    } else {
+        Some(format!(
+            "A imagem ainda é usada por: {}",
+            references.join(", ")
+        ))
+    };
+
+    let assets = state.db.get_tournament_assets(&tournament_slug).await?;
+
+    Ok(Html(
+        AssetsTemplate {
+            maybe_user: None,
+            tournament_slug,
+            assets,
+            target: query.target(),
+            error,
+        }
+        .as_gallery()
+        .render()?,
+    ))
+}
+
+/// Serves the uploaded image, public so the overlays can load it
+#[axum::debug_handler]
+pub async fn serve_asset(
+    State(state): State<Arc<AppState>>,
+    Path(asset_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let not_found = |_: anyhow::Error| {
+        AppError::from("Imagem não encontrada").with_status(StatusCode::NOT_FOUND)
+    };
+
+    let (asset, data) = state.db.get_asset(asset_id).await.map_err(not_found)?;
+    let data = match (data, &state.assets_dir) {
+        (Some(data), _) => data,
+        (None, Some(dir)) => tokio::fs::read(dir.join(asset.id.to_string()))
+            .await
+            .map_err(|e| not_found(e.into()))?,
+        (None, None) => {
+            tracing::error!(
+                "Asset {} is stored in disk, but no assets dir is configured",
+                asset.id
+            );
+            return Err(not_found(anyhow::anyhow!("missing assets dir")));
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, asset.content_type),
+            (
+                header::CACHE_CONTROL,
+                "public, max-age=31536000, immutable".to_string(),
+            ),
+        ],
+        data,
+    ))
+}
@@ -10,6 +10,7 @@ use chrono::{Duration, Utc};
 use rand::{rngs::OsRng, TryRngCore};
 use url::Url;
 
+use crate::startgg::auth::AuthSession;
 use crate::startgg::oauth::{
     self, OAuthCallbackParams, OAuthConfig, TokenResponse, REQUIRED_SCOPES,
 };
@@ -166,7 +167,16 @@ pub async fn oauth_callback_handler(
 }
 
 #[axum::debug_handler]
-pub async fn logout_handler(mut jar: CookieJar) -> Result<(CookieJar, Redirect), AppError> {
+pub async fn logout_handler(
+    State(state): State<Arc<AppState>>,
+    auth_session: Option<AuthSession>,
+    mut jar: CookieJar,
+) -> Result<(CookieJar, Redirect), AppError> {
+    // Whoever logs in next on this account gets their access checked again
+    if let Some(auth_session) = auth_session {
+        state.tournament_access.invalidate(&auth_session.user.slug);
+    }
+
     // Remove all auth-related cookies by expiring them immediately
     jar = jar.remove(Cookie::from(oauth::ACCESS_TOKEN_COOKIE));
     jar = jar.remove(Cookie::from(oauth::REFRESH_TOKEN_COOKIE));
@@ -0,0 +1,330 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+
+use super::sse::{SSEDestination, SSEvent};
+
+/// Events buffered per topic before slow subscribers start lagging, also the number of events
+/// kept to be replayed to reconnecting subscribers
+const TOPIC_CAPACITY: usize = 64;
+
+#[derive(Debug)]
+struct History {
+    events: VecDeque<SSEvent>,
+    /// Events up to this ID are no longer (or were never) in the history
+    evicted_up_to: u64,
+}
+
+#[derive(Debug)]
+struct Topic {
+    sender: broadcast::Sender<SSEvent>,
+    history: Mutex<History>,
+    published: AtomicU64,
+    lagged: AtomicU64,
+}
+
+impl Topic {
+    fn new(last_id: u64) -> Self {
+        Self {
+            sender: broadcast::channel(TOPIC_CAPACITY).0,
+            history: Mutex::new(History {
+                events: VecDeque::with_capacity(TOPIC_CAPACITY),
+                evicted_up_to: last_id,
+            }),
+            published: AtomicU64::new(0),
+            lagged: AtomicU64::new(0),
+        }
+    }
+
+    /// Events published after `last_event_id`, or `None` when some of them aren't kept anymore
+    fn events_since(&self, last_event_id: u64) -> Option<Vec<SSEvent>> {
+        let history = self.history.lock().unwrap();
+        if last_event_id < history.evicted_up_to {
+            return None;
+        }
+
+        Some(
+            history
+                .events
+                .iter()
+                .filter(|e| e.id.is_some_and(|id| id > last_event_id))
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum HubMessage {
+    Event(SSEvent),
+    /// The subscriber is new or missed events, it must be sent the whole state.
+    /// Carries the ID of the last published event, to be used for the state events.
+    Resync(u64),
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicMetrics {
+    pub topic: String,
+    pub subscribers: usize,
+    pub published: u64,
+    pub lagged: u64,
+}
+
+/// Pub/sub of overlay events, with one bounded channel per destination so a busy overlay
+/// doesn't make the subscribers of the others lag.
+#[derive(Debug)]
+pub struct EventHub {
+    topics: Mutex<HashMap<SSEDestination, Arc<Topic>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        // IDs start from the current time so the ones sent before a restart are never valid
+        let start_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        Self {
+            topics: Mutex::default(),
+            next_id: Arc::new(AtomicU64::new(start_id + 1)),
+        }
+    }
+}
+
+impl EventHub {
+    /// ID of the last published event
+    pub fn last_id(&self) -> u64 {
+        self.next_id.load(Ordering::SeqCst) - 1
+    }
+
+    /// Sends the event to the subscribers of its destination, returning how many got it
+    pub fn publish(&self, mut event: SSEvent) -> usize {
+        let mut topics = self.topics.lock().unwrap();
+        // Always take an ID, so subscribers know they missed something even if nobody listened
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let destination = event.destination.clone();
+        let Some(topic) = topics.get(&destination) else {
+            return 0;
+        };
+
+        event.id = Some(id);
+        {
+            let mut history = topic.history.lock().unwrap();
+            if history.events.len() == TOPIC_CAPACITY {
+                if let Some(evicted) = history.events.pop_front() {
+                    history.evicted_up_to = evicted.id.unwrap_or_default();
+                }
+            }
+            history.events.push_back(event.clone());
+        }
+
+        topic.published.fetch_add(1, Ordering::Relaxed);
+        match topic.sender.send(event) {
+            Ok(subscribers) => subscribers,
+            Err(_) => {
+                // Every subscriber is gone
+                topics.remove(&destination);
+                0
+            }
+        }
+    }
+
+    /// Subscribes to the destination and to the events sent to everyone.
+    /// The stream starts with the events missed since `last_event_id` when they can still be
+    /// replayed, otherwise with a [`HubMessage::Resync`].
+    pub fn subscribe(
+        &self,
+        destination: SSEDestination,
+        last_event_id: Option<u64>,
+    ) -> BoxStream<'static, HubMessage> {
+        let mut destinations = vec![SSEDestination::Everyone];
+        if destination != SSEDestination::Everyone {
+            destinations.push(destination);
+        }
+
+        let mut topics = self.topics.lock().unwrap();
+        let last_id = self.last_id();
+        let mut missed = last_event_id.filter(|id| *id <= last_id).map(|_| vec![]);
+        let mut streams = vec![];
+        for destination in destinations {
+            let topic = topics
+                .entry(destination.clone())
+                .or_insert_with(|| Arc::new(Topic::new(last_id)))
+                .clone();
+
+            missed = missed
+                .zip(last_event_id)
+                .and_then(|(mut missed, last_event_id)| {
+                    missed.extend(topic.events_since(last_event_id)?);
+                    Some(missed)
+                });
+            streams.push(self.topic_stream(destination, topic));
+        }
+        drop(topics);
+
+        let first = match missed {
+            Some(mut missed) => {
+                missed.sort_by_key(|e| e.id);
+                missed.into_iter().map(HubMessage::Event).collect()
+            }
+            None => vec![HubMessage::Resync(last_id)],
+        };
+        stream::iter(first)
+            .chain(stream::select_all(streams))
+            .boxed()
+    }
+
+    fn topic_stream(
+        &self,
+        destination: SSEDestination,
+        topic: Arc<Topic>,
+    ) -> BoxStream<'static, HubMessage> {
+        let next_id = self.next_id.clone();
+
+        BroadcastStream::new(topic.sender.subscribe())
+            .map(move |res| match res {
+                Ok(event) => HubMessage::Event(event),
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    topic.lagged.fetch_add(missed, Ordering::Relaxed);
+                    tracing::warn!("Subscriber of {} lagged by {} events", destination, missed);
+                    HubMessage::Resync(next_id.load(Ordering::SeqCst) - 1)
+                }
+            })
+            .boxed()
+    }
+
+    /// Subscriber counts per topic, topics without subscribers are dropped
+    pub fn metrics(&self) -> Vec<TopicMetrics> {
+        let mut topics = self.topics.lock().unwrap();
+        topics.retain(|_, topic| topic.sender.receiver_count() > 0);
+
+        let mut metrics = topics
+            .iter()
+            .map(|(destination, topic)| TopicMetrics {
+                topic: destination.to_string(),
+                subscribers: topic.sender.receiver_count(),
+                published: topic.published.load(Ordering::Relaxed),
+                lagged: topic.lagged.load(Ordering::Relaxed),
+            })
+            .collect::<Vec<_>>();
+        metrics.sort_by(|a, b| a.topic.cmp(&b.topic));
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::routes::sse::SSEventType;
+
+    fn event(destination: SSEDestination, data: &str) -> SSEvent {
+        SSEvent {
+            id: None,
+            destination,
+            event: SSEventType::Test,
+            data: data.to_string(),
+        }
+    }
+
+    async fn next_data(subscriber: &mut BoxStream<'static, HubMessage>) -> String {
+        match subscriber.next().await {
+            Some(HubMessage::Event(e)) => e.data,
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlay_topics() {
+        let hub = EventHub::default();
+        let overlay_a = SSEDestination::Overlay(Uuid::new_v4());
+        let overlay_b = SSEDestination::Overlay(Uuid::new_v4());
+
+        let mut subscriber = hub.subscribe(overlay_a.clone(), None);
+        assert!(matches!(
+            subscriber.next().await,
+            Some(HubMessage::Resync(_))
+        ));
+        assert_eq!(hub.publish(event(overlay_b.clone(), "b")), 0);
+        assert_eq!(hub.publish(event(overlay_a.clone(), "a")), 1);
+        assert_eq!(hub.publish(event(SSEDestination::Everyone, "all")), 1);
+
+        let mut received = vec![
+            next_data(&mut subscriber).await,
+            next_data(&mut subscriber).await,
+        ];
+        received.sort();
+        assert_eq!(received, vec!["a", "all"]);
+
+        let metrics = hub.metrics();
+        assert_eq!(metrics.len(), 2);
+        assert!(metrics.iter().all(|m| m.subscribers == 1));
+
+        drop(subscriber);
+        assert!(hub.metrics().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lagged_subscriber() {
+        let hub = EventHub::default();
+        let overlay = SSEDestination::Overlay(Uuid::new_v4());
+
+        let mut subscriber = hub.subscribe(overlay.clone(), None);
+        assert!(matches!(
+            subscriber.next().await,
+            Some(HubMessage::Resync(_))
+        ));
+        for i in 0..TOPIC_CAPACITY + 3 {
+            hub.publish(event(overlay.clone(), &i.to_string()));
+        }
+
+        assert!(matches!(
+            subscriber.next().await,
+            Some(HubMessage::Resync(id)) if id == hub.last_id()
+        ));
+        let metrics = hub.metrics();
+        let topic = metrics.iter().find(|m| m.topic == overlay.to_string());
+        assert_eq!(topic.map(|m| m.lagged), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_replay_since_last_event_id() {
+        let hub = EventHub::default();
+        let overlay = SSEDestination::Overlay(Uuid::new_v4());
+
+        // Keeps the topic alive while the other subscriber reconnects
+        let _subscriber = hub.subscribe(overlay.clone(), None);
+        hub.publish(event(overlay.clone(), "first"));
+        let last_event_id = hub.last_id();
+        hub.publish(event(overlay.clone(), "second"));
+        hub.publish(event(SSEDestination::Everyone, "third"));
+
+        let mut reconnected = hub.subscribe(overlay.clone(), Some(last_event_id));
+        assert_eq!(next_data(&mut reconnected).await, "second");
+        assert_eq!(next_data(&mut reconnected).await, "third");
+
+        // IDs from before a restart or already evicted can't be replayed
+        let mut restarted = hub.subscribe(overlay.clone(), Some(1));
+        assert!(matches!(
+            restarted.next().await,
+            Some(HubMessage::Resync(_))
+        ));
+
+        for i in 0..TOPIC_CAPACITY {
+            hub.publish(event(overlay.clone(), &i.to_string()));
+        }
+        let mut evicted = hub.subscribe(overlay.clone(), Some(last_event_id));
+        assert!(matches!(evicted.next().await, Some(HubMessage::Resync(_))));
+    }
+}
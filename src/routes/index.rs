@@ -6,7 +6,6 @@ use axum::response::{Html, IntoResponse};
 
 use crate::startgg::auth::AuthSession;
 use crate::startgg::oauth::StartggUser;
-use crate::startgg::StartGGClient;
 
 use super::error::AppError;
 use super::views::filters;
@@ -25,7 +24,8 @@ pub async fn index_handler(
 ) -> Result<impl IntoResponse, AppError> {
     // If AuthSession exists, try to get user data for display
     let user = if let Some(session) = auth_session {
-        StartGGClient::new(&state.http_client, &session.access_token)
+        state
+            .startgg_client(&session.access_token)
             .fetch_startgg_user()
             .await
             .ok()
@@ -1,26 +1,39 @@
-use axum::http::header;
+use axum::extract::DefaultBodyLimit;
+use axum::http::{header, Method};
+use axum::middleware::from_fn_with_state;
 use axum::routing::{delete, post, put};
 use axum::{body::Body, http::Request, routing::get, Extension, Router};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::broadcast;
 use tower::ServiceBuilder;
+use tower_http::cors::{Any, CorsLayer};
 use tower_http::normalize_path::NormalizePathLayer;
 use tower_http::sensitive_headers::SetSensitiveHeadersLayer;
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::trace::TraceLayer;
 use uuid::Uuid;
 
+use crate::database::organizations::Permission;
 use crate::database::DB;
 use crate::startgg;
 use crate::startgg::oauth::OAuthConfig;
+use crate::startgg::{StartGGApi, StartGGClient};
 
+mod access_cache;
+mod api;
+mod api_tokens;
 mod app;
+mod assets;
 pub mod auth;
 pub mod error;
+mod hub;
 mod index;
 mod obs;
+mod organizations;
+mod sponsors;
 mod sse;
-mod stream_overlay;
+pub mod stream_overlay;
+mod talent;
 mod tournament;
 pub mod views;
 
@@ -29,8 +42,15 @@ pub struct AppState {
     pub http_client: reqwest::Client,
     pub oauth_config: OAuthConfig,
     pub db: Arc<DB>,
-    pub events_receiver: broadcast::Receiver<sse::SSEvent>,
-    pub events_sender: broadcast::Sender<sse::SSEvent>,
+    /// Server-side start.gg token, used for requests that aren't made on behalf of a user
+    pub startgg_token: Option<String>,
+    pub startgg_api: StartGGApi,
+    /// Local directory for uploaded assets, they are stored in the database when not set
+    pub assets_dir: Option<PathBuf>,
+    pub events: hub::EventHub,
+    pub tournament_access: access_cache::TournamentAccessCache,
+    pub brackets: stream_overlay::bracket::BracketCache,
+    pub websocket_sessions: stream_overlay::websocket::WebsocketSessions,
 }
 
 #[derive(Debug)]
@@ -38,6 +58,9 @@ pub struct AppStateBuilder {
     oauth_config: OAuthConfig,
     db: Arc<DB>,
     http_client: Option<reqwest::Client>,
+    startgg_token: Option<String>,
+    startgg_endpoint: Option<String>,
+    assets_dir: Option<PathBuf>,
 }
 
 impl AppState {
@@ -45,9 +68,16 @@ impl AppState {
         AppStateBuilder {
             oauth_config,
             http_client: None,
+            startgg_token: None,
+            startgg_endpoint: None,
+            assets_dir: None,
             db,
         }
     }
+
+    pub fn startgg_client<'a>(&'a self, token: &'a str) -> StartGGClient<'a> {
+        StartGGClient::new(&self.http_client, &self.startgg_api, token)
+    }
 }
 
 impl AppStateBuilder {
@@ -58,21 +88,48 @@ impl AppStateBuilder {
         }
     }
 
-    pub fn build(self) -> AppState {
-        let (sender, receiver) = broadcast::channel(32);
+    pub fn startgg_token(self, token: Option<String>) -> AppStateBuilder {
+        AppStateBuilder {
+            startgg_token: token,
+            ..self
+        }
+    }
+
+    /// GraphQL endpoint of the start.gg API, defaults to api.start.gg
+    pub fn startgg_endpoint(self, endpoint: Option<String>) -> AppStateBuilder {
+        AppStateBuilder {
+            startgg_endpoint: endpoint,
+            ..self
+        }
+    }
+
+    pub fn assets_dir(self, dir: Option<PathBuf>) -> AppStateBuilder {
+        AppStateBuilder {
+            assets_dir: dir,
+            ..self
+        }
+    }
 
+    pub fn build(self) -> AppState {
         AppState {
             http_client: self.http_client.unwrap_or_default(),
             oauth_config: self.oauth_config,
             db: self.db,
-            events_sender: sender,
-            events_receiver: receiver,
+            startgg_token: self.startgg_token,
+            startgg_api: self
+                .startgg_endpoint
+                .map(StartGGApi::new)
+                .unwrap_or_default(),
+            assets_dir: self.assets_dir,
+            events: hub::EventHub::default(),
+            tournament_access: access_cache::TournamentAccessCache::default(),
+            brackets: stream_overlay::bracket::BracketCache::default(),
+            websocket_sessions: stream_overlay::websocket::WebsocketSessions::default(),
         }
     }
 }
 
-pub fn init_router(state: AppState) -> Router {
-    let s = Arc::new(state);
+pub fn init_router(s: Arc<AppState>) -> Router {
     Router::new()
         .route("/", get(index::index_handler))
         .nest_service("/favicon.ico", ServeFile::new("assets/favicon.ico"))
@@ -82,24 +139,114 @@ pub fn init_router(state: AppState) -> Router {
         .route("/logout", get(auth::logout_handler))
         .route("/sse", get(sse::handle_sse))
         .route("/send-sse", get(sse::send_event))
+        .route("/sse/metrics", get(sse::events_metrics))
+        .route("/uploads/{asset_id}", get(assets::serve_asset))
+        .nest("/api/v1",
+            Router::new()
+                .route_service("/openapi.json", ServeFile::new("assets/openapi.json"))
+                .route("/overlay/{overlay_id}/scoreboard", get(api::scoreboard))
+                .route("/overlay/{overlay_id}/scoreboard/score", post(api::update_score))
+                .route("/overlay/{overlay_id}/matches", get(api::matches))
+                .route("/overlay/{overlay_id}/casters", get(api::casters).put(api::update_casters))
+                .route("/overlay/{overlay_id}/timer", get(api::timer).post(api::update_timer))
+                .route("/overlay/{overlay_id}/actions/score/{team}/increment", post(api::increment_score))
+                .route("/overlay/{overlay_id}/actions/score/{team}/decrement", post(api::decrement_score))
+                .route("/overlay/{overlay_id}/actions/swap-sides", post(api::swap_sides))
+                .route("/overlay/{overlay_id}/actions/reset-series", post(api::reset_series))
+                .route("/overlay/{overlay_id}/actions/next-match", post(api::next_match))
+                .route("/overlay/{overlay_id}/actions/break/{minutes}", post(api::start_break))
+                .route("/tournament/{tournament_slug}/teams", get(api::tournament_teams))
+                .layer(CorsLayer::new()
+                    .allow_origin(Any)
+                    .allow_methods([Method::GET, Method::POST, Method::PUT])
+                    .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]))
+        )
         .nest("/app",
             Router::new()
                 .route("/", get(tournament::tournaments_handler))
                 .route("/tournament", get(tournament::tournaments_handler))
+                .route("/organizations", get(organizations::organizations_handler).post(organizations::create_organization))
+                .nest("/organizations/{organization_id}", Router::new()
+                    .route("/", get(organizations::organization_handler))
+                    .merge(Router::new()
+                        .route("/members", put(organizations::update_member_role).delete(organizations::delete_member))
+                        .route_layer(from_fn_with_state(Permission::ManageMembers, organizations::require_permission))
+                    )
+                    .merge(Router::new()
+                        .route("/invites", post(organizations::create_invite))
+                        .route("/invites/{invite_id}", delete(organizations::delete_invite))
+                        .route_layer(from_fn_with_state(Permission::InviteMembers, organizations::require_permission))
+                    )
+                    .merge(Router::new()
+                        .route("/tournaments", post(organizations::link_tournament))
+                        .route("/tournaments/{tournament_slug}", delete(organizations::unlink_tournament))
+                        .route_layer(from_fn_with_state(Permission::ManageTournament, organizations::require_permission))
+                    )
+                    .merge(Router::new()
+                        .route("/talent", get(talent::talent_handler).post(talent::create_talent))
+                        .route("/talent/{talent_id}", get(talent::talent_history_handler)
+                            .put(talent::update_talent)
+                            .delete(talent::delete_talent))
+                        .route_layer(from_fn_with_state(Permission::Casters, organizations::require_permission))
+                    )
+                    .layer(from_fn_with_state(s.clone(), organizations::organization_access_middleware))
+                )
+                .route("/invite/{code}", get(organizations::invite_handler).post(organizations::accept_invite))
                 .nest("/tournament/{tournament_slug}", Router::new()
                     .route("/", get(tournament::tournament_setup))
-                    .route("/overlay", put(tournament::create_overlay))
+                    .route("/standings", get(tournament::standings_handler))
+                    .route("/assets", get(assets::assets_handler)
+                        .post(assets::upload_asset)
+                        .layer(DefaultBodyLimit::max(assets::MAX_ASSET_SIZE + 64 * 1024)))
+                    .merge(Router::new()
+                        .route("/overlay", put(tournament::create_overlay))
+                        .route("/theme", get(tournament::theme_handler).post(tournament::update_theme))
+                        .route("/assets/{asset_id}", delete(assets::delete_asset))
+                        .route("/api-tokens", get(api_tokens::api_tokens_handler).post(api_tokens::create_api_token))
+                        .route("/api-tokens/{token_id}", delete(api_tokens::delete_api_token))
+                        .route("/sponsors", get(sponsors::sponsors_handler).post(sponsors::create_sponsor))
+                        .route("/sponsors/{sponsor_id}", put(sponsors::update_sponsor).delete(sponsors::delete_sponsor))
+                        .route_layer(from_fn_with_state(Permission::ManageTournament, organizations::require_permission))
+                    )
                     .nest("/overlay/{overlay_id}", Router::new()
-                        .route("/", delete(tournament::delete_overlay).patch(tournament::update_overlay))
-                        .route("/obs-config", get(obs::obs_page))
-                        .route("/teams", get(tournament::team_setup_handler))
-                        .route("/ingame", put(stream_overlay::update_ingame_scoreboard))
-                        .route("/teams/nickname", post(tournament::update_team_nickname))
-                        .route("/teams/image", post(tournament::update_team_image))
-                        .route("/casters", get(tournament::casters_handler).put(stream_overlay::casters::update_casters))
                         .route("/waiting", get(stream_overlay::waiting::waiting_setup))
-                        .route("/waiting/matches", post(stream_overlay::waiting::todays_matches_update))
-                        .route("/waiting/timer", post(stream_overlay::waiting::timer_update))
+                        .route("/history", get(stream_overlay::history::history_handler))
+                        .merge(Router::new()
+                            .route("/", delete(tournament::delete_overlay).patch(tournament::update_overlay))
+                            .route("/obs-config", get(obs::obs_page).post(obs::obs_config_update))
+                            .route("/obs-config/live", post(obs::obs_go_live))
+                            .route("/waiting/startgg_sync", post(stream_overlay::waiting::startgg_sync_update))
+                            .route("/bracket", get(stream_overlay::bracket::bracket_setup).post(stream_overlay::bracket::bracket_update))
+                            .route("/history/undo", post(stream_overlay::history::undo))
+                            .route("/history/redo", post(stream_overlay::history::redo))
+                            .route_layer(from_fn_with_state(Permission::ManageTournament, organizations::require_permission))
+                        )
+                        .merge(Router::new()
+                            .route("/teams", get(tournament::team_setup_handler))
+                            .route("/ingame", put(stream_overlay::update_ingame_scoreboard))
+                            .route("/ingame/report", post(stream_overlay::report_ingame_scoreboard))
+                            .route("/ingame/sides", post(stream_overlay::swap_ingame_sides))
+                            .route("/lineups", get(stream_overlay::lineups::lineups_setup).post(stream_overlay::lineups::lineups_update))
+                            .route("/teams/nickname", post(tournament::update_team_nickname))
+                            .route("/teams/image", post(tournament::update_team_image))
+                            .route("/lower_third", get(stream_overlay::lower_third::lower_third_setup).post(stream_overlay::lower_third::update_lower_third))
+                            .route("/lower_third/pin", post(stream_overlay::lower_third::pin_lower_third_message))
+                            .route("/lower_third/messages", post(stream_overlay::lower_third::add_lower_third_message))
+                            .route("/lower_third/messages/{message_id}", put(stream_overlay::lower_third::update_lower_third_message)
+                                .delete(stream_overlay::lower_third::delete_lower_third_message))
+                            .route("/lower_third/messages/{message_id}/move", post(stream_overlay::lower_third::move_lower_third_message))
+                            .route("/waiting/matches", post(stream_overlay::waiting::todays_matches_update))
+                            .route("/waiting/timer", post(stream_overlay::waiting::timer_update))
+                            .route("/matches/{match_id}/games", post(stream_overlay::waiting::add_match_game))
+                            .route("/matches/{match_id}/games/{game_number}", delete(stream_overlay::waiting::delete_match_game))
+                            .route_layer(from_fn_with_state(Permission::Scoreboard, organizations::require_permission))
+                        )
+                        .merge(Router::new()
+                            .route("/casters", get(tournament::casters_handler).put(stream_overlay::casters::update_casters))
+                            .route("/casters/vdo-password", post(stream_overlay::casters::regenerate_vdo_password))
+                            .route_layer(from_fn_with_state(Permission::Casters, organizations::require_permission))
+                        )
+                        .route_layer(from_fn_with_state(s.clone(), tournament::overlay_access_middleware))
                     )
                     .layer(axum::middleware::from_fn_with_state(s.clone(), tournament::tournament_access_middleware))
                 )
@@ -115,7 +262,14 @@ pub fn init_router(state: AppState) -> Router {
             .route("/waiting/next_up_match", get(stream_overlay::waiting::todays_matches_single_overlay))
             .route("/waiting/standalone_timer", get(stream_overlay::waiting::standalone_timer_overlay))
             .route("/casters", get(stream_overlay::casters::casters_overlay))
+            .route("/bracket", get(stream_overlay::bracket::bracket_overlay))
+            .route("/standings", get(stream_overlay::standings::standings_overlay))
+            .route("/lineups", get(stream_overlay::lineups::lineups_overlay))
+            .route("/lower_third", get(stream_overlay::lower_third::lower_third_overlay))
+            .route("/sponsors", get(stream_overlay::sponsors::sponsors_overlay))
+            .route("/sponsors/impressions", post(stream_overlay::sponsors::record_impression))
             .route("/background", get(stream_overlay::background::background))
+            .route("/theme.css", get(stream_overlay::theme::theme_css))
             .route("/partial", get(stream_overlay::partial::partial))
             .nest("/partial", Router::new()
                 .route("/timer", get(stream_overlay::partial::partial))
@@ -1,25 +1,229 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use super::error::AppError;
 use super::views::filters;
 use super::AppState;
+use crate::database::obs::ObsConfig;
+use crate::database::wait_timer::WaitType;
+use crate::obs::ObsClient;
 use crate::startgg::auth::AuthSession;
 use crate::startgg::oauth::StartggUser;
 use askama::Template;
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::response::{Html, IntoResponse};
+use axum::Form;
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// How long OBS has to answer before the switch or the scene list is given up on
+const OBS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Switches OBS to the scene configured for the wait type.
+/// The scoreboard source is only shown while live, it's shown before switching to the live
+/// scene and hidden after switching away from it.
+async fn switch_scene(config: &ObsConfig, wait_type: &WaitType) -> anyhow::Result<()> {
+    let Some(scene) = config.scene_for(wait_type) else {
+        return Ok(());
+    };
+    let live = *wait_type == WaitType::Nothing;
+
+    let mut client = ObsClient::connect(&config.url, config.password.as_deref()).await?;
+    let result: anyhow::Result<()> = async {
+        let scoreboard = config
+            .live_scene
+            .as_deref()
+            .zip(config.scoreboard_source.as_deref());
+        if let (true, Some((live_scene, source))) = (live, scoreboard) {
+            client.set_source_enabled(live_scene, source, true).await?;
+        }
+        client.set_current_program_scene(scene).await?;
+        if let (false, Some((live_scene, source))) = (live, scoreboard) {
+            client.set_source_enabled(live_scene, source, false).await?;
+        }
+        Ok(())
+    }
+    .await;
+    client.close().await;
+
+    result
+}
+
+/// Called when the overlay's wait type changes. The switch runs in the background and OBS
+/// failures are only logged, an unreachable OBS shouldn't block the wait timer update.
+pub fn wait_type_changed(state: Arc<AppState>, overlay_id: Uuid, wait_type: WaitType) {
+    tokio::spawn(async move {
+        let config = match state.db.get_obs_config(overlay_id).await {
+            Ok(config) if config.enabled => config,
+            _ => return,
+        };
+
+        let result = tokio::time::timeout(OBS_TIMEOUT, switch_scene(&config, &wait_type))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("OBS didn't answer in time")));
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to switch OBS scene for overlay {}: {}",
+                overlay_id,
+                e
+            );
+        }
+    });
+}
+
+async fn fetch_scene_names(config: &ObsConfig) -> Result<Vec<String>, String> {
+    let fetch = async {
+        let mut client = ObsClient::connect(&config.url, config.password.as_deref()).await?;
+        let scenes = client.get_scene_names().await;
+        client.close().await;
+        scenes
+    };
+    tokio::time::timeout(OBS_TIMEOUT, fetch)
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("OBS didn't answer in time")))
+        .map_err(|e| e.to_string())
+}
 
 #[derive(Template)]
-#[template(path = "obs.html")]
+#[template(path = "obs.html", blocks = ["obs_config"])]
 pub struct ObsTemplate {
-    maybe_user: Option<StartggUser>
+    maybe_user: Option<StartggUser>,
+    tournament_slug: String,
+    overlay_id: Uuid,
+    config: ObsConfig,
+    /// Scenes found in OBS, or the connection error. Only checked when the integration is enabled.
+    scenes: Option<Result<Vec<String>, String>>,
+    message: Option<String>,
 }
 
 #[axum::debug_handler]
 pub async fn obs_page(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let user = startgg_client.fetch_startgg_user().await?;
+
+    let config = state.db.get_obs_config(overlay_id).await?;
+    let scenes = match config.enabled {
+        true => Some(fetch_scene_names(&config).await),
+        false => None,
+    };
+
+    Ok(Html(
+        ObsTemplate {
+            maybe_user: Some(user),
+            tournament_slug,
+            overlay_id,
+            config,
+            scenes,
+            message: None,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObsConfigForm {
+    #[serde(default)]
+    enabled: bool,
+    url: String,
+    /// Left empty to keep the saved password
+    password: String,
+    #[serde(default)]
+    clear_password: bool,
+    starting_scene: String,
+    break_scene: String,
+    ending_scene: String,
+    live_scene: String,
+    scoreboard_source: String,
+}
+
+/// The server connects to whatever ws/wss address is saved here, including hosts inside its own
+/// network, so saving is only routed for members with `Permission::ManageTournament`.
+#[axum::debug_handler]
+pub async fn obs_config_update(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    _auth_session: AuthSession,
+    Form(form): Form<ObsConfigForm>,
+) -> Result<impl IntoResponse, AppError> {
+    if !form.url.starts_with("ws://") && !form.url.starts_with("wss://") {
+        return Err("O endereço do OBS deve começar com ws:// ou wss://".into());
+    }
+
+    let saved = state.db.get_obs_config(overlay_id).await?;
+    let non_empty = |s: String| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+
+    let config = ObsConfig {
+        overlay_id,
+        enabled: form.enabled,
+        url: form.url.trim().to_string(),
+        password: match (form.clear_password, non_empty(form.password)) {
+            (true, _) => None,
+            (false, Some(password)) => Some(password),
+            (false, None) => saved.password,
+        },
+        starting_scene: non_empty(form.starting_scene),
+        break_scene: non_empty(form.break_scene),
+        ending_scene: non_empty(form.ending_scene),
+        live_scene: non_empty(form.live_scene),
+        scoreboard_source: non_empty(form.scoreboard_source),
+    };
+    state.db.upsert_obs_config(&config).await?;
+
+    let scenes = match config.enabled {
+        true => Some(fetch_scene_names(&config).await),
+        false => None,
+    };
+
+    Ok(Html(
+        ObsTemplate {
+            maybe_user: None,
+            tournament_slug,
+            overlay_id,
+            config,
+            scenes,
+            message: Some("Configuração salva".to_string()),
+        }
+        .as_obs_config()
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn obs_go_live(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
     _auth_session: AuthSession,
 ) -> Result<impl IntoResponse, AppError> {
+    let config = state.db.get_obs_config(overlay_id).await?;
+
+    let message = if !config.enabled {
+        "A integração com o OBS está desativada".to_string()
+    } else if config.live_scene.is_none() {
+        "Configure a cena ao vivo antes".to_string()
+    } else {
+        let result = tokio::time::timeout(OBS_TIMEOUT, switch_scene(&config, &WaitType::Nothing))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("OBS didn't answer in time")));
+        match result {
+            Ok(()) => "Ao vivo!".to_string(),
+            Err(e) => format!("Falha ao trocar a cena: {}", e),
+        }
+    };
 
-    Ok(Html(ObsTemplate { maybe_user: None }.render()?))
+    Ok(Html(
+        ObsTemplate {
+            maybe_user: None,
+            tournament_slug,
+            overlay_id,
+            config,
+            scenes: None,
+            message: Some(message),
+        }
+        .as_obs_config()
+        .render()?,
+    ))
 }
@@ -0,0 +1,577 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, Query, Request, State};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::{Extension, Form};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::organizations::{
+    Membership, Organization, OrganizationInvite, OrganizationMember, OrganizationRole, Permission,
+};
+use crate::startgg::auth::AuthSession;
+use crate::startgg::oauth::StartggUser;
+use crate::startgg::tournaments::StartGGTournament;
+
+use super::api_tokens::generate_token;
+use super::error::AppError;
+use super::views::filters;
+use super::AppState;
+
+const INVITE_PREFIX: &str = "inv_";
+const INVITE_DAYS: i64 = 7;
+
+/// Role of the user in the tournament. start.gg admins of the tournament are owners,
+/// everyone else needs to be a member of the organization that manages it.
+pub async fn tournament_role(
+    state: &AppState,
+    auth_session: &AuthSession,
+    tournament_slug: &str,
+) -> Result<Option<OrganizationRole>, AppError> {
+    if admin_tournament_slugs(state, auth_session)
+        .await?
+        .contains(tournament_slug)
+    {
+        return Ok(Some(OrganizationRole::Owner));
+    }
+
+    Ok(state
+        .db
+        .get_tournament_role(tournament_slug, &auth_session.user.slug)
+        .await?)
+}
+
+/// Slugs of the tournaments the user is a start.gg admin of, cached for a few minutes
+async fn admin_tournament_slugs(
+    state: &AppState,
+    auth_session: &AuthSession,
+) -> Result<HashSet<String>, AppError> {
+    if let Some(slugs) = state.tournament_access.get(&auth_session.user.slug) {
+        return Ok(slugs);
+    }
+
+    let tournaments = state
+        .startgg_client(&auth_session.access_token)
+        .fetch_tournaments_organized_by_user()
+        .await?;
+    Ok(state
+        .tournament_access
+        .insert(&auth_session.user.slug, &tournaments))
+}
+
+/// Route layer that only lets the request through when the role the access middleware
+/// put in the request extensions allows the permission
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let role = req
+        .extensions()
+        .get::<OrganizationRole>()
+        .copied()
+        .ok_or_else(|| AppError::from("Sem acesso a esta página").with_unauthorized())?;
+
+    if !role.can(permission) {
+        return Err(
+            AppError::from(format!("O papel {} não permite esta ação", role.label()))
+                .with_status(StatusCode::FORBIDDEN),
+        );
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrganizationPathExtractor {
+    organization_id: Uuid,
+}
+
+pub async fn organization_access_middleware(
+    State(state): State<Arc<AppState>>,
+    Path(path_extractor): Path<OrganizationPathExtractor>,
+    auth_session: AuthSession,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let role = state
+        .db
+        .get_organization_role(path_extractor.organization_id, &auth_session.user.slug)
+        .await?
+        .ok_or_else(|| AppError::from("Você não é membro desta organização").with_unauthorized())?;
+
+    req.extensions_mut().insert(role);
+    Ok(next.run(req).await)
+}
+
+#[derive(Template)]
+#[template(path = "organizations.html", blocks = ["organizations"])]
+pub struct OrganizationsTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub memberships: Vec<Membership>,
+    pub error: Option<String>,
+}
+
+#[axum::debug_handler]
+pub async fn organizations_handler(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let memberships = state
+        .db
+        .get_user_memberships(&auth_session.user.slug)
+        .await?;
+
+    Ok(Html(
+        OrganizationsTemplate {
+            maybe_user: Some(auth_session.user),
+            memberships,
+            error: None,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationForm {
+    name: String,
+}
+
+#[axum::debug_handler]
+pub async fn create_organization(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession,
+    Form(form): Form<CreateOrganizationForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let name = form.name.trim();
+    let mut error = None;
+
+    if name.is_empty() {
+        error = Some("Informe um nome para a organização".to_string());
+    } else {
+        let organization = state
+            .db
+            .insert_organization(name, &auth_session.user.slug, &auth_session.actor())
+            .await?;
+        tracing::info!(
+            "{} created organization {}",
+            auth_session.actor(),
+            organization.id
+        );
+    }
+
+    let memberships = state
+        .db
+        .get_user_memberships(&auth_session.user.slug)
+        .await?;
+
+    Ok(Html(
+        OrganizationsTemplate {
+            maybe_user: None,
+            memberships,
+            error,
+        }
+        .as_organizations()
+        .render()?,
+    ))
+}
+
+#[derive(Template)]
+#[template(
+    path = "organization.html",
+    blocks = ["members", "invites", "tournaments"]
+)]
+pub struct OrganizationTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub organization: Organization,
+    /// Role of the user viewing the page
+    pub role: OrganizationRole,
+    pub members: Vec<OrganizationMember>,
+    pub invites: Vec<OrganizationInvite>,
+    pub tournaments: Vec<String>,
+    /// start.gg tournaments the user administrates, which can be linked to the organization
+    pub admin_tournaments: Vec<StartGGTournament>,
+    pub error: Option<String>,
+}
+
+impl OrganizationTemplate {
+    fn can_manage_members(&self) -> bool {
+        self.role.can(Permission::ManageMembers)
+    }
+
+    fn can_invite_members(&self) -> bool {
+        self.role.can(Permission::InviteMembers)
+    }
+
+    fn can_manage_tournaments(&self) -> bool {
+        self.role.can(Permission::ManageTournament)
+    }
+
+    fn can_manage_talent(&self) -> bool {
+        self.role.can(Permission::Casters)
+    }
+
+    /// Roles the user can give in invites
+    fn grantable_roles(&self) -> Vec<OrganizationRole> {
+        OrganizationRole::ALL
+            .into_iter()
+            .filter(|r| self.role.can_grant(*r))
+            .collect()
+    }
+
+    async fn new(
+        state: &AppState,
+        auth_session: &AuthSession,
+        organization_id: Uuid,
+        role: OrganizationRole,
+    ) -> Result<Self, AppError> {
+        let admin_tournaments = if role.can(Permission::ManageTournament) {
+            let tournaments = state
+                .startgg_client(&auth_session.access_token)
+                .fetch_tournaments_organized_by_user()
+                .await?;
+            state
+                .tournament_access
+                .insert(&auth_session.user.slug, &tournaments);
+            tournaments
+        } else {
+            vec![]
+        };
+
+        Ok(OrganizationTemplate {
+            maybe_user: None,
+            organization: state.db.get_organization(organization_id).await?,
+            role,
+            members: state.db.get_organization_members(organization_id).await?,
+            invites: state.db.get_organization_invites(organization_id).await?,
+            tournaments: state
+                .db
+                .get_organization_tournaments(organization_id)
+                .await?,
+            admin_tournaments,
+            error: None,
+        })
+    }
+}
+
+#[axum::debug_handler]
+pub async fn organization_handler(
+    State(state): State<Arc<AppState>>,
+    Path(organization_id): Path<Uuid>,
+    Extension(role): Extension<OrganizationRole>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let template = OrganizationTemplate::new(&state, &auth_session, organization_id, role).await?;
+
+    Ok(Html(
+        OrganizationTemplate {
+            maybe_user: Some(auth_session.user),
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemberRoleForm {
+    user_slug: String,
+    role: OrganizationRole,
+}
+
+/// Fails when the change would leave the organization without owners
+async fn ensure_other_owner(
+    state: &AppState,
+    organization_id: Uuid,
+    user_slug: &str,
+) -> Result<(), String> {
+    let members = state
+        .db
+        .get_organization_members(organization_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let has_other_owner = members
+        .iter()
+        .any(|m| m.role == OrganizationRole::Owner && m.user_slug != user_slug);
+
+    if has_other_owner {
+        Ok(())
+    } else {
+        Err("A organização precisa de ao menos um dono".to_string())
+    }
+}
+
+#[axum::debug_handler]
+pub async fn update_member_role(
+    State(state): State<Arc<AppState>>,
+    Path(organization_id): Path<Uuid>,
+    Extension(role): Extension<OrganizationRole>,
+    auth_session: AuthSession,
+    Form(form): Form<MemberRoleForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut error = None;
+
+    if form.role != OrganizationRole::Owner {
+        error = ensure_other_owner(&state, organization_id, &form.user_slug)
+            .await
+            .err();
+    }
+    if error.is_none() {
+        state
+            .db
+            .update_member_role(organization_id, &form.user_slug, form.role)
+            .await?;
+        tracing::info!(
+            "{} made {} a {} of organization {}",
+            auth_session.actor(),
+            form.user_slug,
+            form.role,
+            organization_id
+        );
+    }
+
+    let template = OrganizationTemplate::new(&state, &auth_session, organization_id, role).await?;
+    Ok(Html(
+        OrganizationTemplate { error, ..template }
+            .as_members()
+            .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemberQuery {
+    user_slug: String,
+}
+
+#[axum::debug_handler]
+pub async fn delete_member(
+    State(state): State<Arc<AppState>>,
+    Path(organization_id): Path<Uuid>,
+    Query(query): Query<MemberQuery>,
+    Extension(role): Extension<OrganizationRole>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let error = ensure_other_owner(&state, organization_id, &query.user_slug)
+        .await
+        .err();
+
+    if error.is_none() {
+        state
+            .db
+            .delete_member(organization_id, &query.user_slug)
+            .await?;
+        tracing::info!(
+            "{} removed {} from organization {}",
+            auth_session.actor(),
+            query.user_slug,
+            organization_id
+        );
+    }
+
+    let template = OrganizationTemplate::new(&state, &auth_session, organization_id, role).await?;
+    Ok(Html(
+        OrganizationTemplate { error, ..template }
+            .as_members()
+            .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteForm {
+    role: OrganizationRole,
+}
+
+#[axum::debug_handler]
+pub async fn create_invite(
+    State(state): State<Arc<AppState>>,
+    Path(organization_id): Path<Uuid>,
+    Extension(role): Extension<OrganizationRole>,
+    auth_session: AuthSession,
+    Form(form): Form<CreateInviteForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut error = None;
+
+    if role.can_grant(form.role) {
+        let code = generate_token(INVITE_PREFIX)?;
+        let expires_at = chrono::Utc::now() + chrono::Duration::days(INVITE_DAYS);
+        state
+            .db
+            .insert_invite(
+                organization_id,
+                &code,
+                form.role,
+                &auth_session.actor(),
+                expires_at,
+            )
+            .await?;
+        tracing::info!(
+            "{} invited a {} to organization {}",
+            auth_session.actor(),
+            form.role,
+            organization_id
+        );
+    } else {
+        error = Some(format!(
+            "O papel {} não pode convidar {}",
+            role.label(),
+            form.role.label()
+        ));
+    }
+
+    let template = OrganizationTemplate::new(&state, &auth_session, organization_id, role).await?;
+    Ok(Html(
+        OrganizationTemplate { error, ..template }
+            .as_invites()
+            .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn delete_invite(
+    State(state): State<Arc<AppState>>,
+    Path((organization_id, invite_id)): Path<(Uuid, Uuid)>,
+    Extension(role): Extension<OrganizationRole>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    state.db.delete_invite(organization_id, invite_id).await?;
+
+    let template = OrganizationTemplate::new(&state, &auth_session, organization_id, role).await?;
+    let html = template.as_invites().render()?;
+    Ok(Html(html))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkTournamentForm {
+    tournament_slug: String,
+}
+
+/// Only start.gg admins of the tournament can put it under the organization
+#[axum::debug_handler]
+pub async fn link_tournament(
+    State(state): State<Arc<AppState>>,
+    Path(organization_id): Path<Uuid>,
+    Extension(role): Extension<OrganizationRole>,
+    auth_session: AuthSession,
+    Form(form): Form<LinkTournamentForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let template = OrganizationTemplate::new(&state, &auth_session, organization_id, role).await?;
+    let mut error = None;
+
+    if template
+        .admin_tournaments
+        .iter()
+        .any(|t| t.slug == form.tournament_slug)
+    {
+        state
+            .db
+            .link_tournament(organization_id, &form.tournament_slug)
+            .await?;
+        tracing::info!(
+            "{} linked tournament {} to organization {}",
+            auth_session.actor(),
+            form.tournament_slug,
+            organization_id
+        );
+    } else {
+        error = Some("Você não é admin deste campeonato no start.gg".to_string());
+    }
+
+    let tournaments = state
+        .db
+        .get_organization_tournaments(organization_id)
+        .await?;
+    Ok(Html(
+        OrganizationTemplate {
+            tournaments,
+            error,
+            ..template
+        }
+        .as_tournaments()
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn unlink_tournament(
+    State(state): State<Arc<AppState>>,
+    Path((organization_id, tournament_slug)): Path<(Uuid, String)>,
+    Extension(role): Extension<OrganizationRole>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .db
+        .unlink_tournament(organization_id, &tournament_slug)
+        .await?;
+    tracing::info!(
+        "{} unlinked tournament {} from organization {}",
+        auth_session.actor(),
+        tournament_slug,
+        organization_id
+    );
+
+    let template = OrganizationTemplate::new(&state, &auth_session, organization_id, role).await?;
+    let html = template.as_tournaments().render()?;
+    Ok(Html(html))
+}
+
+#[derive(Template)]
+#[template(path = "organization_invite.html")]
+pub struct InviteTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub code: String,
+    pub invite: Option<(OrganizationInvite, Organization)>,
+}
+
+#[axum::debug_handler]
+pub async fn invite_handler(
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let invite = match state.db.get_invite(&code).await? {
+        Some(invite) => {
+            let organization = state.db.get_organization(invite.organization_id).await?;
+            Some((invite, organization))
+        }
+        None => None,
+    };
+
+    Ok(Html(
+        InviteTemplate {
+            maybe_user: Some(auth_session.user),
+            code,
+            invite,
+        }
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn accept_invite(
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let invite = state.db.get_invite(&code).await?.ok_or_else(|| {
+        AppError::from("Convite inválido ou expirado").with_status(StatusCode::NOT_FOUND)
+    })?;
+
+    state
+        .db
+        .accept_invite(&invite, &auth_session.user.slug, &auth_session.actor())
+        .await?;
+    tracing::info!(
+        "{} joined organization {} as {}",
+        auth_session.actor(),
+        invite.organization_id,
+        invite.role
+    );
+
+    Ok(Redirect::to(&format!(
+        "/app/organizations/{}",
+        invite.organization_id
+    )))
+}
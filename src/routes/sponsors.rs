@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use axum::Form;
+use axum_htmx::HxRequest;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::sponsors::{Sponsor, SponsorImpressions};
+use crate::startgg::auth::AuthSession;
+use crate::startgg::oauth::StartggUser;
+
+use super::assets::FOREIGN_IMAGE;
+use super::error::AppError;
+use super::stream_overlay::sponsors::{broadcast_sponsors, SLOT_SECONDS};
+use super::views::filters;
+use super::AppState;
+
+const MAX_WEIGHT: i32 = 10;
+
+#[derive(Template)]
+#[template(path = "sponsors.html", blocks = ["sponsor_list"])]
+pub struct SponsorsTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub tournament_slug: String,
+    pub sponsors: Vec<Sponsor>,
+    pub impressions: Vec<SponsorImpressions>,
+    pub now: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+impl SponsorsTemplate {
+    async fn new(state: &AppState, tournament_slug: String) -> Result<Self, AppError> {
+        Ok(SponsorsTemplate {
+            maybe_user: None,
+            sponsors: state.db.get_tournament_sponsors(&tournament_slug).await?,
+            impressions: state.db.get_sponsor_impressions(&tournament_slug).await?,
+            tournament_slug,
+            now: Utc::now(),
+            error: None,
+        })
+    }
+
+    /// Impressions and seconds on screen of each sponsor that was shown
+    fn totals(&self) -> Vec<(&str, i64, i64)> {
+        self.sponsors
+            .iter()
+            .map(|sponsor| {
+                self.impressions
+                    .iter()
+                    .filter(|i| i.sponsor_id == sponsor.id)
+                    .fold(
+                        (sponsor.name.as_str(), 0, 0),
+                        |(name, count, seconds), i| {
+                            (name, count + i.impressions, seconds + i.seconds)
+                        },
+                    )
+            })
+            .filter(|(_, count, _)| *count > 0)
+            .collect()
+    }
+
+    fn slot_seconds(&self) -> i32 {
+        SLOT_SECONDS
+    }
+}
+
+#[axum::debug_handler]
+pub async fn sponsors_handler(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    HxRequest(is_hx_request): HxRequest,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let template = SponsorsTemplate::new(&state, tournament_slug).await?;
+
+    if is_hx_request {
+        return Ok(Html(template.as_sponsor_list().render()?));
+    }
+
+    Ok(Html(
+        SponsorsTemplate {
+            maybe_user: Some(auth_session.user),
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SponsorForm {
+    name: String,
+    logo: String,
+    weight: Option<i32>,
+    #[serde(default)]
+    starts_at: String,
+    #[serde(default)]
+    ends_at: String,
+    /// Minutes behind UTC of the browser, as in `Date.getTimezoneOffset`
+    timezone_offset: i32,
+}
+
+impl SponsorForm {
+    /// Sponsor with the fields of the form, or why they aren't valid
+    fn sponsor(self, tournament_slug: &str, id: Uuid) -> Result<Sponsor, &'static str> {
+        let name = self.name.trim().to_string();
+        let logo = self.logo.trim().to_string();
+        if name.is_empty() || logo.is_empty() {
+            return Err("O nome e o logo são obrigatórios");
+        }
+
+        let tz_offset = FixedOffset::west_opt(self.timezone_offset * 60)
+            .unwrap_or_else(|| FixedOffset::west_opt(3 * 60 * 60).unwrap()); // Use America/Sao_Paulo as default
+        let local_time = |value: &str| -> Result<Option<DateTime<Utc>>, &'static str> {
+            if value.is_empty() {
+                return Ok(None);
+            }
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+                .ok()
+                .and_then(|time| time.and_local_timezone(tz_offset).earliest())
+                .map(|time| Some(time.with_timezone(&Utc)))
+                .ok_or("Data inválida")
+        };
+        let starts_at = local_time(&self.starts_at)?;
+        let ends_at = local_time(&self.ends_at)?;
+        if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+            if ends_at <= starts_at {
+                return Err("O fim da exibição precisa ser depois do início");
+            }
+        }
+
+        Ok(Sponsor {
+            id,
+            tournament_slug: tournament_slug.to_string(),
+            name,
+            logo,
+            weight: self.weight.unwrap_or(1).clamp(1, MAX_WEIGHT),
+            starts_at,
+            ends_at,
+        })
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_sponsor(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    auth_session: AuthSession,
+    Form(form): Form<SponsorForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut error = None;
+
+    match form.sponsor(&tournament_slug, Uuid::new_v4()) {
+        Ok(sponsor)
+            if !state
+                .db
+                .is_tournament_image(&tournament_slug, &sponsor.logo)
+                .await? =>
+        {
+            error = Some(FOREIGN_IMAGE.to_string())
+        }
+        Ok(sponsor) => {
+            state.db.insert_sponsor(&sponsor).await?;
+            tracing::info!(
+                "{} added sponsor {} to {}",
+                auth_session.actor(),
+                sponsor.name,
+                tournament_slug
+            );
+            broadcast_sponsors(&state, &tournament_slug).await?;
+        }
+        Err(e) => error = Some(e.to_string()),
+    }
+
+    let template = SponsorsTemplate::new(&state, tournament_slug).await?;
+    Ok(Html(
+        SponsorsTemplate { error, ..template }
+            .as_sponsor_list()
+            .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn update_sponsor(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, sponsor_id)): Path<(String, Uuid)>,
+    Form(form): Form<SponsorForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut error = None;
+
+    match form.sponsor(&tournament_slug, sponsor_id) {
+        Ok(sponsor)
+            if !state
+                .db
+                .is_tournament_image(&tournament_slug, &sponsor.logo)
+                .await? =>
+        {
+            error = Some(FOREIGN_IMAGE.to_string())
+        }
+        Ok(sponsor) => {
+            state.db.update_sponsor(&sponsor).await?;
+            broadcast_sponsors(&state, &tournament_slug).await?;
+        }
+        Err(e) => error = Some(e.to_string()),
+    }
+
+    let template = SponsorsTemplate::new(&state, tournament_slug).await?;
+    Ok(Html(
+        SponsorsTemplate { error, ..template }
+            .as_sponsor_list()
+            .render()?,
+    ))
+}
+
+/// Also removes the impressions of the sponsor
+#[axum::debug_handler]
+pub async fn delete_sponsor(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, sponsor_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .db
+        .delete_sponsor(&tournament_slug, sponsor_id)
+        .await?;
+    tracing::info!(
+        "{} removed sponsor {} from {}",
+        auth_session.actor(),
+        sponsor_id,
+        tournament_slug
+    );
+    broadcast_sponsors(&state, &tournament_slug).await?;
+
+    let template = SponsorsTemplate::new(&state, tournament_slug).await?;
+    let html = template.as_sponsor_list().render()?;
+    Ok(Html(html))
+}
@@ -1,27 +1,53 @@
+use std::str::FromStr;
 use std::sync::Arc;
 use std::{convert::Infallible, fmt::Display};
 
 use axum::extract::Query;
+use axum::http::HeaderMap;
 use axum::{
     extract::State,
     response::{
         sse::{self, KeepAlive, Sse},
         IntoResponse,
     },
+    Json,
 };
-use futures_util::StreamExt;
+use futures_util::{future, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio_stream::wrappers::BroadcastStream;
+use uuid::Uuid;
 
 use crate::startgg::auth::AuthSession;
 
 use super::error::AppError;
+use super::stream_overlay::snapshot::subscribe_with_resync;
 use super::AppState;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash)]
 pub enum SSEDestination {
     Everyone,
-    Channel(String),
+    Overlay(Uuid),
+}
+
+impl Display for SSEDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SSEDestination::Everyone => write!(f, "everyone"),
+            SSEDestination::Overlay(id) => write!(f, "overlay_{}", id),
+        }
+    }
+}
+
+impl FromStr for SSEDestination {
+    type Err = String;
+
+    /// Parses the `channel` names used by the overlay pages, i.e. `overlay_<id>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("overlay_").map(Uuid::parse_str) {
+            Some(Ok(id)) => Ok(SSEDestination::Overlay(id)),
+            _ if s == "everyone" => Ok(SSEDestination::Everyone),
+            _ => Err(format!("Invalid channel: {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -36,6 +62,11 @@ pub enum SSEventType {
     WaitInfoUpdate,
     WaitInfoStandaloneUpdate,
     WebsocketEvent,
+    BracketUpdate,
+    StandingsUpdate,
+    LineupsUpdate,
+    LowerThirdUpdate,
+    SponsorsUpdate,
 }
 
 impl Display for SSEventType {
@@ -52,16 +83,33 @@ impl Display for SSEventType {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct SSEvent {
+    /// Set by the event hub when published
+    pub id: Option<u64>,
     pub destination: SSEDestination,
     pub event: SSEventType,
     pub data: String,
 }
 
+impl SSEvent {
+    pub fn overlay(overlay_id: Uuid, event: SSEventType, data: String) -> Self {
+        Self {
+            id: None,
+            destination: SSEDestination::Overlay(overlay_id),
+            event,
+            data,
+        }
+    }
+}
+
 impl From<SSEvent> for sse::Event {
     fn from(val: SSEvent) -> Self {
-        sse::Event::default()
+        let event = sse::Event::default()
             .event(val.event.to_string())
-            .data(val.data)
+            .data(val.data);
+        match val.id {
+            Some(id) => event.id(id.to_string()),
+            None => event,
+        }
     }
 }
 
@@ -71,29 +119,37 @@ pub struct EventFilter {
     channel: Option<String>,
 }
 
+impl EventFilter {
+    fn destination(&self) -> Result<SSEDestination, AppError> {
+        match &self.channel {
+            Some(channel) => Ok(channel.parse::<SSEDestination>()?),
+            None => Ok(SSEDestination::Everyone),
+        }
+    }
+}
+
 #[axum::debug_handler]
 pub async fn handle_sse(
     State(state): State<Arc<AppState>>,
     Query(filter): Query<EventFilter>,
+    headers: HeaderMap,
     _auth_session: Option<AuthSession>,
 ) -> Result<impl IntoResponse, AppError> {
-    let rx = state.events_sender.subscribe();
-
-    let stream = BroadcastStream::new(rx)
-        .filter_map(|res| async { res.ok() })
-        .map(move |event: SSEvent| match &event.destination {
-            SSEDestination::Everyone => Ok(event.into()),
-            SSEDestination::Channel(c) => match (&filter.channel, &filter.event) {
-                (None, Some(evt)) if *evt == event.event => Ok(event.into()),
-                (Some(channel), None) if *channel == *c => Ok(event.into()),
-                (Some(channel), Some(evt)) if *evt == event.event && *channel == *c => {
-                    Ok(event.into())
-                }
-                _ => Err(()),
-            },
+    let destination = filter.destination()?;
+    // Sent by the browser when the EventSource reconnects
+    let last_event_id = headers
+        .get("Last-Event-ID")
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.parse::<u64>().ok());
+
+    let stream = subscribe_with_resync(state, destination, last_event_id, filter.event.clone())
+        .filter(move |event| {
+            future::ready(match &filter.event {
+                Some(evt) => *evt == event.event,
+                None => true,
+            })
         })
-        .filter_map(|res| async { res.ok() })
-        .map(|e| Ok(e) as Result<sse::Event, Infallible>);
+        .map(|e| Ok(e.into()) as Result<sse::Event, Infallible>);
 
     Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
@@ -103,16 +159,20 @@ pub async fn send_event(
     State(state): State<Arc<AppState>>,
     Query(filter): Query<EventFilter>,
 ) -> Result<impl IntoResponse, AppError> {
-    let destination = if let Some(f) = filter.channel {
-        SSEDestination::Channel(f)
-    } else {
-        SSEDestination::Everyone
-    };
-    state.events_sender.send(SSEvent {
-        destination,
+    let sent = state.events.publish(SSEvent {
+        id: None,
+        destination: filter.destination()?,
         event: SSEventType::Test,
         data: "data".to_string(),
-    })?;
+    });
 
-    Ok("Event sent")
+    Ok(format!("Event sent to {} subscribers", sent))
+}
+
+#[axum::debug_handler]
+pub async fn events_metrics(
+    State(state): State<Arc<AppState>>,
+    _auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Json(state.events.metrics()))
 }
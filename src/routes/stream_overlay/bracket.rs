@@ -0,0 +1,324 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use axum::Form;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::overlay::Overlay;
+use crate::database::DB;
+use crate::routes::error::AppError;
+use crate::routes::sse::{SSEvent, SSEventType};
+use crate::routes::views::filters;
+use crate::routes::AppState;
+use crate::startgg::auth::AuthSession;
+use crate::startgg::sets::{
+    StartGGBracketType, StartGGPhaseGroup, StartGGPhaseGroupInfo, StartGGSetState,
+};
+use crate::startgg::tournaments::StartGGTeam;
+
+#[derive(Debug, Clone, Default)]
+pub struct BracketSlot {
+    /// Only present when the entrant's team was already loaded into the `team` table
+    pub team: Option<StartGGTeam>,
+    pub entrant_name: Option<String>,
+    pub score: Option<i32>,
+    pub winner: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BracketSet {
+    pub in_progress: bool,
+    pub slots: Vec<BracketSlot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BracketRound {
+    pub name: String,
+    pub sets: Vec<BracketSet>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BracketStanding {
+    pub team: Option<StartGGTeam>,
+    pub entrant_name: Option<String>,
+    pub wins: i32,
+    pub losses: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Bracket {
+    pub name: String,
+    pub winners: Vec<BracketRound>,
+    /// Only filled for double elimination brackets
+    pub losers: Vec<BracketRound>,
+    /// Only filled for round robin groups
+    pub standings: Vec<BracketStanding>,
+}
+
+async fn build_bracket(db: &DB, phase_group: StartGGPhaseGroup) -> Bracket {
+    let mut teams: HashMap<String, Option<StartGGTeam>> = HashMap::new();
+    let team_ids = phase_group
+        .sets
+        .iter()
+        .flat_map(|set| &set.slots)
+        .filter_map(|slot| slot.team_id.clone())
+        .collect::<Vec<_>>();
+    for team_id in team_ids {
+        if let Entry::Vacant(entry) = teams.entry(team_id) {
+            let team = db.get_team(entry.key()).await.ok();
+            entry.insert(team);
+        }
+    }
+    let team_for = |team_id: &Option<String>| {
+        team_id
+            .as_ref()
+            .and_then(|id| teams.get(id).cloned().flatten())
+    };
+
+    let mut rounds: BTreeMap<i64, BracketRound> = BTreeMap::new();
+    let mut standings: Vec<BracketStanding> = vec![];
+    let mut standing_entrants: Vec<String> = vec![];
+
+    for set in &phase_group.sets {
+        let mut slots = set
+            .slots
+            .iter()
+            .map(|slot| BracketSlot {
+                team: team_for(&slot.team_id),
+                entrant_name: slot.entrant_name.clone(),
+                score: slot.score,
+                winner: set.winner_entrant_id.as_deref() == Some(slot.entrant_id.as_str()),
+            })
+            .collect::<Vec<_>>();
+        slots.resize_with(2, BracketSlot::default);
+
+        let round = rounds
+            .entry(set.round.unwrap_or_default())
+            .or_insert_with(|| BracketRound {
+                name: set.round_name.clone().unwrap_or_default(),
+                sets: vec![],
+            });
+        round.sets.push(BracketSet {
+            in_progress: set.state == StartGGSetState::InProgress,
+            slots,
+        });
+
+        if phase_group.info.bracket_type == StartGGBracketType::RoundRobin {
+            for slot in &set.slots {
+                let position = match standing_entrants.iter().position(|e| *e == slot.entrant_id) {
+                    Some(position) => position,
+                    None => {
+                        standing_entrants.push(slot.entrant_id.clone());
+                        standings.push(BracketStanding {
+                            team: team_for(&slot.team_id),
+                            entrant_name: slot.entrant_name.clone(),
+                            wins: 0,
+                            losses: 0,
+                        });
+                        standings.len() - 1
+                    }
+                };
+                match set.winner_entrant_id.as_deref() {
+                    Some(winner) if winner == slot.entrant_id => standings[position].wins += 1,
+                    Some(_) => standings[position].losses += 1,
+                    None => {}
+                }
+            }
+        }
+    }
+    standings.sort_by(|a, b| b.wins.cmp(&a.wins).then(a.losses.cmp(&b.losses)));
+
+    // Losers rounds come with negative numbers, -1 being the first one
+    let (losers, winners): (Vec<_>, Vec<_>) = rounds.into_iter().partition(|(round, _)| {
+        *round < 0 && phase_group.info.bracket_type == StartGGBracketType::DoubleElimination
+    });
+
+    Bracket {
+        name: phase_group.info.display_name(),
+        winners: winners.into_iter().map(|(_, round)| round).collect(),
+        losers: losers.into_iter().rev().map(|(_, round)| round).collect(),
+        standings,
+    }
+}
+
+/// Fetches the overlay's selected phase group and renders its bracket.
+/// Returns `None` when no phase group was selected for the overlay.
+pub async fn render_bracket(
+    state: &AppState,
+    overlay: &Overlay,
+    startgg_token: &str,
+) -> anyhow::Result<Option<String>> {
+    let Some(phase_group_id) = &overlay.bracket_phase_group_id else {
+        return Ok(None);
+    };
+
+    let startgg_client = state.startgg_client(startgg_token);
+    let phase_group = startgg_client.fetch_phase_group(phase_group_id).await?;
+
+    Ok(Some(
+        BracketContentTemplate {
+            bracket: Some(build_bracket(&state.db, phase_group).await),
+        }
+        .render()?,
+    ))
+}
+
+/// Last bracket rendered for each overlay, so subscribers that (re)connect get it without
+/// fetching the phase group from start.gg again
+#[derive(Debug, Default)]
+pub struct BracketCache {
+    brackets: Mutex<HashMap<Uuid, String>>,
+}
+
+impl BracketCache {
+    pub fn get(&self, overlay_id: Uuid) -> Option<String> {
+        self.brackets.lock().unwrap().get(&overlay_id).cloned()
+    }
+
+    pub fn insert(&self, overlay_id: Uuid, bracket_html: String) {
+        self.brackets
+            .lock()
+            .unwrap()
+            .insert(overlay_id, bracket_html);
+    }
+
+    /// Forgets the overlays that no longer show a bracket
+    pub fn retain(&self, overlay_ids: &[Uuid]) {
+        self.brackets
+            .lock()
+            .unwrap()
+            .retain(|id, _| overlay_ids.contains(id));
+    }
+}
+
+pub fn broadcast_bracket(state: &AppState, overlay_id: Uuid, bracket_html: String) {
+    state.brackets.insert(overlay_id, bracket_html.clone());
+    state.events.publish(SSEvent::overlay(
+        overlay_id,
+        SSEventType::BracketUpdate,
+        bracket_html,
+    ));
+}
+
+#[derive(Template)]
+#[template(path = "stream_overlays/bracket.html", block = "bracket_content")]
+pub struct BracketContentTemplate {
+    pub bracket: Option<Bracket>,
+}
+
+#[derive(Template)]
+#[template(path = "stream_overlays/bracket.html")]
+pub struct BracketOverlayTemplate {
+    pub overlay_id: Uuid,
+    pub bracket: Option<Bracket>,
+}
+
+#[axum::debug_handler]
+pub async fn bracket_overlay(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let overlay = state.db.get_overlay(overlay_id).await?;
+
+    let bracket = match (&overlay.bracket_phase_group_id, &state.startgg_token) {
+        (Some(phase_group_id), Some(token)) => {
+            let startgg_client = state.startgg_client(token);
+            match startgg_client.fetch_phase_group(phase_group_id).await {
+                Ok(phase_group) => Some(build_bracket(&state.db, phase_group).await),
+                Err(e) => {
+                    tracing::error!("Failed to fetch bracket for overlay {}: {}", overlay_id, e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    Ok(Html(
+        BracketOverlayTemplate {
+            overlay_id,
+            bracket,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "bracket_setup.html")]
+pub struct BracketSetupTemplate {
+    tournament_slug: String,
+    overlay: Overlay,
+    phase_groups: Vec<StartGGPhaseGroupInfo>,
+}
+
+#[axum::debug_handler]
+pub async fn bracket_setup(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let overlay = state.db.get_overlay(overlay_id).await?;
+
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let phase_groups = startgg_client
+        .fetch_tournament_phase_groups(&tournament_slug)
+        .await?;
+
+    Ok(Html(
+        BracketSetupTemplate {
+            tournament_slug,
+            overlay,
+            phase_groups,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BracketUpdateForm {
+    phase_group_id: String,
+}
+
+#[axum::debug_handler]
+pub async fn bracket_update(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
+    Form(form): Form<BracketUpdateForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let phase_groups = startgg_client
+        .fetch_tournament_phase_groups(&tournament_slug)
+        .await?;
+
+    let phase_group_id = match form.phase_group_id.as_str() {
+        "" => None,
+        id if phase_groups.iter().any(|pg| pg.id == id) => Some(id),
+        _ => return Err("Chave não pertence a este campeonato".into()),
+    };
+
+    let overlay = state
+        .db
+        .set_overlay_bracket_phase_group(overlay_id, phase_group_id)
+        .await?;
+
+    let bracket_html = match render_bracket(&state, &overlay, &auth_session.access_token).await? {
+        Some(html) => html,
+        None => BracketContentTemplate { bracket: None }.render()?,
+    };
+    broadcast_bracket(&state, overlay_id, bracket_html);
+
+    Ok(Html(
+        BracketSetupTemplate {
+            tournament_slug,
+            overlay,
+            phase_groups,
+        }
+        .render()?,
+    ))
+}
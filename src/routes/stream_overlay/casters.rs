@@ -1,87 +1,282 @@
 use crate::database::casters::Caster;
+use crate::database::overlay::Overlay;
+use crate::database::overlay_events::OverlayState;
+use crate::database::vdo::VdoRoom;
 use crate::routes::error::AppError;
-use crate::routes::sse::{SSEDestination, SSEvent, SSEventType};
-use crate::startgg::StartGGClient;
+use crate::routes::sse::{SSEvent, SSEventType};
+use crate::routes::stream_overlay::history;
+use crate::routes::stream_overlay::websocket::OverlayUpdate;
 use askama::Template;
 use axum::extract::{Path, State};
 use axum::response::{Html, IntoResponse};
-use axum::Form;
+use axum::Extension;
+use axum_extra::extract::Form;
+use rand::{rngs::OsRng, TryRngCore};
+use reqwest::StatusCode;
 use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::routes::tournament::CastersSetup;
 use crate::routes::AppState;
 use crate::startgg::auth::AuthSession;
 
+/// Most casters an overlay can have, more than this doesn't fit the screen
+pub const MAX_CASTERS: usize = 6;
+
+const VDO_PASSWORD_LEN: usize = 12;
+const VDO_PUSH_ID_LEN: usize = 8;
+
+/// Random lowercase letters and digits, so it can go in a VDO.ninja link as is
+fn vdo_secret(len: usize) -> anyhow::Result<String> {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut bytes = vec![0u8; len];
+    OsRng.try_fill_bytes(&mut bytes)?;
+    Ok(bytes
+        .iter()
+        .map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char)
+        .collect())
+}
+
+/// VDO.ninja room of the overlay, created with a new password the first time
+pub async fn vdo_room(state: &AppState, overlay_id: Uuid) -> anyhow::Result<VdoRoom> {
+    if let Some(room) = state.db.get_vdo_room(overlay_id).await? {
+        return Ok(room);
+    }
+    // Another request may create the room at the same time, the first one to get in is kept
+    state
+        .db
+        .insert_vdo_room(&VdoRoom {
+            overlay_id,
+            password: vdo_secret(VDO_PASSWORD_LEN)?,
+        })
+        .await?;
+    state
+        .db
+        .get_vdo_room(overlay_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("vdo room of overlay {} not found", overlay_id))
+}
+
+pub fn new_push_id() -> anyhow::Result<String> {
+    vdo_secret(VDO_PUSH_ID_LEN)
+}
+
+/// Gives a push ID to the casters that don't have one yet
+fn assign_push_ids(casters: &mut [Caster]) -> anyhow::Result<()> {
+    for caster in casters.iter_mut().filter(|c| c.push_id.is_none()) {
+        caster.push_id = Some(new_push_id()?);
+    }
+    Ok(())
+}
+
+/// Casters as the overlay shows them, embedding their feed from the room unless they have
+/// another video
+pub fn with_videos(room: &VdoRoom, mut casters: Vec<Caster>) -> Vec<Caster> {
+    for caster in casters.iter_mut().filter(|c| c.stream_video.is_empty()) {
+        if let Some(push_id) = &caster.push_id {
+            caster.stream_video = room.view_link(push_id);
+        }
+    }
+    casters
+}
+
+pub async fn overlay_casters(state: &AppState, overlay_id: Uuid) -> anyhow::Result<Vec<Caster>> {
+    let room = vdo_room(state, overlay_id).await?;
+    Ok(with_videos(&room, state.db.get_casters(&overlay_id).await?))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateCastersForm {
-    narrator: String,
-    narrator_video: String,
-    commenter: String,
-    commenter_video: String,
+    #[serde(default)]
+    name: Vec<String>,
+    #[serde(default)]
+    role: Vec<String>,
+    #[serde(default)]
+    handle: Vec<String>,
+    #[serde(default)]
+    pronouns: Vec<String>,
+    #[serde(default)]
+    image: Vec<String>,
+    #[serde(default)]
+    stream_video: Vec<String>,
+    #[serde(default)]
+    push_id: Vec<String>,
+    #[serde(default)]
+    talent_id: Vec<String>,
 }
+
+impl UpdateCastersForm {
+    /// Casters in the order of the form, rows without a name are left out
+    fn casters(self, overlay_id: Uuid) -> Vec<Caster> {
+        let field = |values: &[String], i: usize| {
+            values
+                .get(i)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        self.name
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let name = name.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                Some(Caster {
+                    overlay_id,
+                    name: name.to_string(),
+                    role: field(&self.role, i).unwrap_or_default(),
+                    stream_video: field(&self.stream_video, i).unwrap_or_default(),
+                    image: field(&self.image, i),
+                    handle: field(&self.handle, i),
+                    pronouns: field(&self.pronouns, i),
+                    push_id: field(&self.push_id, i),
+                    talent_id: field(&self.talent_id, i).and_then(|id| id.parse().ok()),
+                })
+            })
+            .collect()
+    }
+}
+
 #[axum::debug_handler]
 pub async fn update_casters(
     state: State<Arc<AppState>>,
     auth_session: AuthSession,
-    Path((_tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    Extension(overlay): Extension<Overlay>,
     Form(form): Form<UpdateCastersForm>,
 ) -> Result<impl IntoResponse, AppError> {
     tracing::info!("Updating casters for overlay {}: {:?}", overlay_id, form);
 
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
-    let tournaments = startgg_client.fetch_tournaments_organized_by_user().await?;
-
-    let overlay = state.db.get_overlay(overlay_id).await?;
-
-    if !tournaments
-        .iter()
-        .any(|t| t.slug == overlay.tournament_slug)
-    {
-        return Err("You are not allowed to update this overlay".into());
-    };
+    let casters = form.casters(overlay_id);
+    if casters.len() > MAX_CASTERS {
+        return Err(AppError::from(format!("No máximo {} casters", MAX_CASTERS))
+            .with_status(StatusCode::UNPROCESSABLE_ENTITY));
+    }
 
-    let caster_narrator = Caster {
+    let previous = state.db.get_casters(&overlay_id).await?;
+    let casters = set_casters(&state, overlay_id, casters).await?;
+    history::record_change(
+        &state,
         overlay_id,
-        name: form.narrator,
-        kind: crate::database::casters::CasterKind::Narrator,
-        stream_video: form.narrator_video,
-    };
+        &auth_session.actor(),
+        OverlayState::Casters(previous),
+        OverlayState::Casters(casters.clone()),
+    )
+    .await;
 
-    state.db.upsert_caster(&caster_narrator).await?;
+    Ok(Html(
+        CastersSetup::new(&state, tournament_slug, overlay, casters)
+            .await?
+            .render()?,
+    ))
+}
 
-    let caster_commenter = Caster {
+/// Changes the password of the overlay's room, the casters need their new invite links
+#[axum::debug_handler]
+pub async fn regenerate_vdo_password(
+    state: State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    Extension(overlay): Extension<Overlay>,
+) -> Result<impl IntoResponse, AppError> {
+    let room = VdoRoom {
         overlay_id,
-        name: form.commenter,
-        kind: crate::database::casters::CasterKind::Commenter,
-        stream_video: form.commenter_video,
+        password: vdo_secret(VDO_PASSWORD_LEN)?,
     };
+    state.db.upsert_vdo_room(&room).await?;
+
+    let casters = state.db.get_casters(&overlay_id).await?;
+    for event in casters_events(overlay_id, with_videos(&room, casters.clone()))? {
+        state.events.publish(event);
+    }
 
-    state.db.upsert_caster(&caster_commenter).await?;
+    Ok(Html(
+        CastersSetup::new(&state, tournament_slug, overlay, casters)
+            .await?
+            .render()?,
+    ))
+}
 
-    state.events_sender.send(SSEvent {
-        destination: SSEDestination::Channel(format!("overlay_{}", overlay.id)),
-        event: SSEventType::CasterOverlayUpdate,
-        data: CastersContentTemplate {
-            casters: Some((caster_narrator, caster_commenter)),
+/// Saves the casters of the overlay and sends them to it, returning them with their push IDs
+pub async fn set_casters(
+    state: &AppState,
+    overlay_id: Uuid,
+    mut casters: Vec<Caster>,
+) -> anyhow::Result<Vec<Caster>> {
+    let overlay = state.db.get_overlay(overlay_id).await?;
+    // Only talent from the directory of the tournament's organization
+    let talent = state
+        .db
+        .get_tournament_talent(&overlay.tournament_slug)
+        .await?;
+    for caster in casters.iter_mut() {
+        if caster
+            .talent_id
+            .is_some_and(|id| !talent.iter().any(|t| t.id == id))
+        {
+            caster.talent_id = None;
         }
-        .render()?,
-    })?;
+        // Only images of the tournament, or the photo of the talent from the directory
+        let talent_image = talent
+            .iter()
+            .find(|t| Some(t.id) == caster.talent_id)
+            .and_then(|t| t.image.as_ref());
+        if let Some(image) = &caster.image {
+            if talent_image != Some(image)
+                && !state
+                    .db
+                    .is_tournament_image(&overlay.tournament_slug, image)
+                    .await?
+            {
+                caster.image = None;
+            }
+        }
+    }
+
+    assign_push_ids(&mut casters)?;
+    state.db.replace_casters(&overlay_id, &casters).await?;
+
+    let room = vdo_room(state, overlay_id).await?;
+    let mut on_air = false;
+    for event in casters_events(overlay_id, with_videos(&room, casters.clone()))? {
+        on_air |= state.events.publish(event) > 0;
+    }
+    // Only casters shown by an overlay on the air worked the broadcast
+    if on_air {
+        state
+            .db
+            .record_talent_appearances(&overlay, &casters)
+            .await?;
+    }
 
-    Ok("Casters atualizados!")
+    Ok(casters)
+}
+
+pub fn casters_events(overlay_id: Uuid, casters: Vec<Caster>) -> anyhow::Result<Vec<SSEvent>> {
+    Ok(vec![
+        OverlayUpdate::Casters(casters.as_slice().into()).event(overlay_id)?,
+        SSEvent::overlay(
+            overlay_id,
+            SSEventType::CasterOverlayUpdate,
+            CastersContentTemplate { casters }.render()?,
+        ),
+    ])
 }
 
 #[derive(Template)]
 #[template(path = "stream_overlays/casters.html", block = "casters_content")]
 pub struct CastersContentTemplate {
-    pub casters: Option<(Caster, Caster)>,
+    pub casters: Vec<Caster>,
 }
 
 #[derive(Template)]
 #[template(path = "stream_overlays/casters.html")]
 pub struct CastersOverlayTemplate {
     pub overlay_id: Uuid,
-    pub casters: Option<(Caster, Caster)>,
+    pub sponsor_token: Uuid,
+    pub casters: Vec<Caster>,
 }
 
 #[axum::debug_handler]
@@ -89,21 +284,12 @@ pub async fn casters_overlay(
     s: State<Arc<AppState>>,
     Path(overlay_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, AppError> {
-    if let Ok(casters) = s.db.get_casters(&overlay_id).await {
-        if casters.len() == 2 {
-            return Ok(Html(
-                CastersOverlayTemplate {
-                    overlay_id,
-                    casters: Some((casters[0].clone(), casters[1].clone())),
-                }
-                .render()?,
-            ));
-        }
-    }
+    let casters = overlay_casters(&s, overlay_id).await.unwrap_or_default();
     Ok(Html(
         CastersOverlayTemplate {
             overlay_id,
-            casters: None,
+            sponsor_token: s.db.get_sponsor_token(overlay_id).await?,
+            casters,
         }
         .render()?,
     ))
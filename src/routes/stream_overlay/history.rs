@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use uuid::Uuid;
+
+use crate::database::matches::Match;
+use crate::database::overlay_events::{OverlayEvent, OverlayState};
+use crate::routes::error::AppError;
+use crate::routes::AppState;
+use crate::startgg::auth::AuthSession;
+
+use super::casters::set_casters;
+use super::standings::broadcast_standings;
+use super::waiting::{broadcast_todays_matches, get_wait_timer, set_wait_timer};
+use super::{broadcast_scoreboard, lineups};
+
+/// Actor of the changes made by the start.gg sync
+pub const STARTGG_SYNC_ACTOR: &str = "start.gg";
+
+const HISTORY_SIZE: i64 = 50;
+
+/// Adds the change to the overlay history.
+/// The change was already made, so failing to record it is only logged.
+pub async fn record_change(
+    state: &AppState,
+    overlay_id: Uuid,
+    actor: &str,
+    before: OverlayState,
+    after: OverlayState,
+) {
+    let _ = state
+        .db
+        .insert_overlay_event(overlay_id, actor, &before, &after)
+        .await;
+}
+
+/// Saves the state and sends it to the overlays, without recording it in the history
+async fn restore(
+    state: &Arc<AppState>,
+    overlay_id: Uuid,
+    overlay_state: OverlayState,
+) -> Result<(), AppError> {
+    let label = overlay_state.label();
+    match overlay_state {
+        OverlayState::Scoreboard(Some(scoreboard)) => {
+            let scoreboard = state.db.upsert_scoreboard(scoreboard).await?;
+            let team_a = state.db.get_team(&scoreboard.team_a).await?;
+            let team_b = state.db.get_team(&scoreboard.team_b).await?;
+            broadcast_scoreboard(state, overlay_id, &scoreboard, &team_a, &team_b).await?;
+            lineups::broadcast_lineups(state, overlay_id).await?;
+        }
+        OverlayState::Casters(casters) => {
+            set_casters(state, overlay_id, casters).await?;
+        }
+        OverlayState::WaitTimer(Some(wait_timer)) => {
+            set_wait_timer(state.clone(), wait_timer).await?;
+        }
+        OverlayState::Matches(matches) => {
+            // Matches that didn't exist yet are only removed from the overlay, like in the setup
+            for current in state.db.get_overlay_matches(overlay_id).await? {
+                if !matches.iter().any(|m| m.id == current.id) {
+                    state
+                        .db
+                        .upsert_match(Match {
+                            overlay_id: None,
+                            ..current
+                        })
+                        .await?;
+                }
+            }
+            for m in matches {
+                let games = m.games.clone();
+                let m = state.db.upsert_match(m).await?;
+                state.db.set_match_games(m.id, &games).await?;
+            }
+
+            let overlay = state.db.get_overlay(overlay_id).await?;
+            let matches = state.db.get_overlay_matches(overlay_id).await?;
+            broadcast_todays_matches(state, overlay_id, &matches)?;
+            broadcast_standings(state, &overlay.tournament_slug).await?;
+        }
+        OverlayState::Scoreboard(None) | OverlayState::WaitTimer(None) => {
+            return Err(format!("{} não tinha um estado anterior para restaurar", label).into());
+        }
+    }
+    Ok(())
+}
+
+/// Current state of the part of the overlay the given state belongs to
+async fn current_state(
+    state: &Arc<AppState>,
+    overlay_id: Uuid,
+    overlay_state: &OverlayState,
+) -> Result<OverlayState, AppError> {
+    Ok(match overlay_state {
+        OverlayState::Scoreboard(_) => {
+            OverlayState::Scoreboard(state.db.get_scoreboard(overlay_id).await.ok())
+        }
+        OverlayState::Casters(_) => OverlayState::Casters(state.db.get_casters(&overlay_id).await?),
+        OverlayState::WaitTimer(_) => {
+            OverlayState::WaitTimer(get_wait_timer(state.clone(), &overlay_id).await)
+        }
+        OverlayState::Matches(_) => {
+            OverlayState::Matches(state.db.get_overlay_matches(overlay_id).await?)
+        }
+    })
+}
+
+#[derive(Template)]
+#[template(path = "overlay_history.html")]
+pub struct OverlayHistoryTemplate {
+    pub tournament_slug: String,
+    pub overlay_id: Uuid,
+    pub events: Vec<OverlayEvent>,
+    pub can_redo: bool,
+    pub message: Option<String>,
+}
+
+async fn render_history(
+    state: &AppState,
+    tournament_slug: String,
+    overlay_id: Uuid,
+    message: Option<String>,
+) -> Result<Html<String>, AppError> {
+    let events = state
+        .db
+        .get_overlay_events(overlay_id, HISTORY_SIZE)
+        .await?;
+    let can_redo = state
+        .db
+        .get_redoable_overlay_event(overlay_id)
+        .await?
+        .is_some();
+
+    Ok(Html(
+        OverlayHistoryTemplate {
+            tournament_slug,
+            overlay_id,
+            events,
+            can_redo,
+            message,
+        }
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn history_handler(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    _auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    render_history(&state, tournament_slug, overlay_id, None).await
+}
+
+#[axum::debug_handler]
+pub async fn undo(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let message = match state.db.get_undoable_overlay_event(overlay_id).await? {
+        Some(event)
+            if !current_state(&state, overlay_id, &event.after)
+                .await?
+                .same_as(&event.after) =>
+        {
+            format!(
+                "{} mudou depois desta alteração, desfaça as mais recentes antes",
+                event.after.label()
+            )
+        }
+        Some(event) => {
+            let label = event.before.label();
+            restore(&state, overlay_id, event.before).await?;
+            state
+                .db
+                .set_overlay_event_undone(event.id, Some(&auth_session.actor()))
+                .await?;
+            tracing::info!(
+                "{} undid change {} of overlay {}",
+                auth_session.actor(),
+                event.id,
+                overlay_id
+            );
+            format!("Alteração de {} feita por {} desfeita", label, event.actor)
+        }
+        None => "Nenhuma alteração para desfazer".to_string(),
+    };
+
+    render_history(&state, tournament_slug, overlay_id, Some(message)).await
+}
+
+#[axum::debug_handler]
+pub async fn redo(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let message = match state.db.get_redoable_overlay_event(overlay_id).await? {
+        Some(event)
+            if !current_state(&state, overlay_id, &event.before)
+                .await?
+                .same_as(&event.before) =>
+        {
+            format!(
+                "{} mudou depois que a alteração foi desfeita, não é possível refazê-la",
+                event.before.label()
+            )
+        }
+        Some(event) => {
+            let label = event.after.label();
+            restore(&state, overlay_id, event.after).await?;
+            state.db.set_overlay_event_undone(event.id, None).await?;
+            tracing::info!(
+                "{} redid change {} of overlay {}",
+                auth_session.actor(),
+                event.id,
+                overlay_id
+            );
+            format!("Alteração de {} refeita", label)
+        }
+        None => "Nenhuma alteração para refazer".to_string(),
+    };
+
+    render_history(&state, tournament_slug, overlay_id, Some(message)).await
+}
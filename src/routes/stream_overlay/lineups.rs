@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use axum_extra::extract::Form;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::routes::error::AppError;
+use crate::routes::sse::{SSEvent, SSEventType};
+use crate::routes::views::filters;
+use crate::routes::AppState;
+use crate::startgg::auth::AuthSession;
+use crate::startgg::tournaments::{StartGGTeam, StartGGTeamMember};
+
+const STARTERS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct TeamLineup {
+    pub team: StartGGTeam,
+    pub starters: Vec<StartGGTeamMember>,
+    pub substitutes: Vec<StartGGTeamMember>,
+}
+
+impl TeamLineup {
+    pub fn is_starter(&self, member: &StartGGTeamMember) -> bool {
+        self.starters
+            .iter()
+            .any(|s| s.gamer_tag == member.gamer_tag)
+    }
+}
+
+/// Splits the team members into starters and substitutes.
+/// Without a lineup picked for the overlay, the first non-alternate members start, captain first.
+async fn team_lineup(
+    state: &AppState,
+    overlay_id: Uuid,
+    mut team: StartGGTeam,
+) -> anyhow::Result<TeamLineup> {
+    team.team_members = state.db.get_team_members(&team.id).await?;
+    let picked = state
+        .db
+        .get_lineup(overlay_id, &team.id)
+        .await?
+        .filter(|tags| !tags.is_empty());
+
+    let (starters, substitutes) = match picked {
+        Some(tags) => team
+            .team_members
+            .iter()
+            .cloned()
+            .partition(|m| tags.contains(&m.gamer_tag)),
+        None => {
+            let (mut starters, mut substitutes): (Vec<_>, Vec<_>) = team
+                .team_members
+                .iter()
+                .cloned()
+                .partition(|m| !m.alternate);
+            if starters.len() > STARTERS {
+                substitutes.splice(0..0, starters.split_off(STARTERS));
+            }
+            (starters, substitutes)
+        }
+    };
+
+    Ok(TeamLineup {
+        team,
+        starters,
+        substitutes,
+    })
+}
+
+/// Lineups of the two teams currently on the overlay's scoreboard
+async fn get_lineups(
+    state: &AppState,
+    overlay_id: Uuid,
+) -> anyhow::Result<Option<(TeamLineup, TeamLineup)>> {
+    let Ok(scoreboard) = state.db.get_scoreboard(overlay_id).await else {
+        return Ok(None);
+    };
+
+    let team_a = state.db.get_team(&scoreboard.team_a).await?;
+    let team_b = state.db.get_team(&scoreboard.team_b).await?;
+
+    Ok(Some((
+        team_lineup(state, overlay_id, team_a).await?,
+        team_lineup(state, overlay_id, team_b).await?,
+    )))
+}
+
+pub async fn broadcast_lineups(state: &AppState, overlay_id: Uuid) -> anyhow::Result<()> {
+    state
+        .events
+        .publish(lineups_event(state, overlay_id).await?);
+    Ok(())
+}
+
+pub async fn lineups_event(state: &AppState, overlay_id: Uuid) -> anyhow::Result<SSEvent> {
+    Ok(SSEvent::overlay(
+        overlay_id,
+        SSEventType::LineupsUpdate,
+        LineupsContentTemplate {
+            lineups: get_lineups(state, overlay_id).await?,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "stream_overlays/lineups.html", block = "lineups_content")]
+pub struct LineupsContentTemplate {
+    pub lineups: Option<(TeamLineup, TeamLineup)>,
+}
+
+#[derive(Template)]
+#[template(path = "stream_overlays/lineups.html")]
+pub struct LineupsOverlayTemplate {
+    pub overlay_id: Uuid,
+    pub lineups: Option<(TeamLineup, TeamLineup)>,
+}
+
+#[axum::debug_handler]
+pub async fn lineups_overlay(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Html(
+        LineupsOverlayTemplate {
+            overlay_id,
+            lineups: get_lineups(&state, overlay_id).await?,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "lineups_setup.html")]
+pub struct LineupsSetupTemplate {
+    tournament_slug: String,
+    overlay_id: Uuid,
+    lineups: Option<(TeamLineup, TeamLineup)>,
+}
+
+#[axum::debug_handler]
+pub async fn lineups_setup(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    _auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Html(
+        LineupsSetupTemplate {
+            tournament_slug,
+            overlay_id,
+            lineups: get_lineups(&state, overlay_id).await?,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LineupsUpdateForm {
+    #[serde(default)]
+    team_a_lineup: Vec<String>,
+    #[serde(default)]
+    team_b_lineup: Vec<String>,
+}
+
+#[axum::debug_handler]
+pub async fn lineups_update(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    _auth_session: AuthSession,
+    Form(form): Form<LineupsUpdateForm>,
+) -> Result<impl IntoResponse, AppError> {
+    if form.team_a_lineup.len() > STARTERS || form.team_b_lineup.len() > STARTERS {
+        return Err(format!("Escolha no máximo {STARTERS} titulares por time").into());
+    }
+
+    let scoreboard = state.db.get_scoreboard(overlay_id).await?;
+    state
+        .db
+        .upsert_lineup(overlay_id, &scoreboard.team_a, &form.team_a_lineup)
+        .await?;
+    state
+        .db
+        .upsert_lineup(overlay_id, &scoreboard.team_b, &form.team_b_lineup)
+        .await?;
+
+    broadcast_lineups(&state, overlay_id).await?;
+
+    Ok(Html(
+        LineupsSetupTemplate {
+            tournament_slug,
+            overlay_id,
+            lineups: get_lineups(&state, overlay_id).await?,
+        }
+        .render()?,
+    ))
+}
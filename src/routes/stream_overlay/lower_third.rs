@@ -0,0 +1,372 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use axum::Form;
+use chrono::Utc;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::lower_third::{LowerThird, LowerThirdMessage, MessageKind};
+use crate::routes::error::AppError;
+use crate::routes::sse::{SSEvent, SSEventType};
+use crate::routes::AppState;
+use crate::startgg::auth::AuthSession;
+
+const MIN_DURATION_SECONDS: i32 = 3;
+const MAX_DURATION_SECONDS: i32 = 120;
+const DEFAULT_DURATION_SECONDS: i32 = 10;
+
+/// Lower third and ticker of the overlay with their messages
+#[derive(Debug, Clone)]
+pub struct LowerThirdContent {
+    pub lower_third: LowerThird,
+    pub messages: Vec<LowerThirdMessage>,
+}
+
+impl LowerThirdContent {
+    pub fn lower_thirds(&self) -> Vec<&LowerThirdMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.kind == MessageKind::LowerThird)
+            .collect()
+    }
+
+    pub fn ticker(&self) -> Vec<&LowerThirdMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.kind == MessageKind::Ticker)
+            .collect()
+    }
+
+    pub fn is_pinned(&self, message_id: &Uuid) -> bool {
+        self.lower_third.pinned_message_id.as_ref() == Some(message_id)
+    }
+
+    pub fn started_at_millis(&self) -> i64 {
+        self.lower_third.rotation_started_at.timestamp_millis()
+    }
+
+    /// Time for the ticker to scroll through all its messages, longer the more there is to read
+    pub fn ticker_seconds(&self) -> usize {
+        let chars: usize = self.ticker().iter().map(|m| m.title.chars().count()).sum();
+        (chars / 4).max(15)
+    }
+}
+
+async fn get_lower_third_content(
+    state: &AppState,
+    overlay_id: Uuid,
+) -> anyhow::Result<LowerThirdContent> {
+    Ok(LowerThirdContent {
+        lower_third: state.db.get_lower_third(overlay_id).await?,
+        messages: state.db.get_lower_third_messages(overlay_id).await?,
+    })
+}
+
+pub async fn lower_third_event(state: &AppState, overlay_id: Uuid) -> anyhow::Result<SSEvent> {
+    Ok(SSEvent::overlay(
+        overlay_id,
+        SSEventType::LowerThirdUpdate,
+        LowerThirdContentTemplate {
+            content: get_lower_third_content(state, overlay_id).await?,
+        }
+        .render()?,
+    ))
+}
+
+async fn broadcast_lower_third(state: &AppState, overlay_id: Uuid) -> anyhow::Result<()> {
+    state
+        .events
+        .publish(lower_third_event(state, overlay_id).await?);
+    Ok(())
+}
+
+#[derive(Template)]
+#[template(
+    path = "stream_overlays/lower_third.html",
+    block = "lower_third_content"
+)]
+pub struct LowerThirdContentTemplate {
+    pub content: LowerThirdContent,
+}
+
+#[derive(Template)]
+#[template(path = "stream_overlays/lower_third.html")]
+pub struct LowerThirdOverlayTemplate {
+    pub overlay_id: Uuid,
+    pub content: LowerThirdContent,
+}
+
+#[axum::debug_handler]
+pub async fn lower_third_overlay(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Html(
+        LowerThirdOverlayTemplate {
+            overlay_id,
+            content: get_lower_third_content(&state, overlay_id).await?,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "lower_third_setup.html")]
+pub struct LowerThirdSetupTemplate {
+    tournament_slug: String,
+    overlay_id: Uuid,
+    content: LowerThirdContent,
+}
+
+impl LowerThirdSetupTemplate {
+    async fn new(
+        state: &AppState,
+        tournament_slug: String,
+        overlay_id: Uuid,
+    ) -> Result<Self, AppError> {
+        Ok(Self {
+            tournament_slug,
+            overlay_id,
+            content: get_lower_third_content(state, overlay_id).await?,
+        })
+    }
+}
+
+#[axum::debug_handler]
+pub async fn lower_third_setup(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    _auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Html(
+        LowerThirdSetupTemplate::new(&state, tournament_slug, overlay_id)
+            .await?
+            .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LowerThirdForm {
+    #[serde(default)]
+    lower_third_on_air: Option<String>,
+    #[serde(default)]
+    ticker_on_air: Option<String>,
+}
+
+#[axum::debug_handler]
+pub async fn update_lower_third(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    Form(form): Form<LowerThirdForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let template = LowerThirdSetupTemplate::new(&state, tournament_slug, overlay_id).await?;
+    let mut lower_third = template.content.lower_third.clone();
+
+    let lower_third_on_air = form.lower_third_on_air.is_some();
+    // Going on air starts the rotation from the first message
+    if lower_third_on_air && !lower_third.lower_third_on_air {
+        lower_third.rotation_started_at = Utc::now();
+    }
+    lower_third.lower_third_on_air = lower_third_on_air;
+    lower_third.ticker_on_air = form.ticker_on_air.is_some();
+    state.db.upsert_lower_third(&lower_third).await?;
+
+    broadcast_lower_third(&state, overlay_id).await?;
+    Ok(Html(
+        LowerThirdSetupTemplate {
+            content: get_lower_third_content(&state, overlay_id).await?,
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinForm {
+    /// Without one the lower third goes back to the rotation
+    message_id: Option<Uuid>,
+}
+
+#[axum::debug_handler]
+pub async fn pin_lower_third_message(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    Form(form): Form<PinForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let template = LowerThirdSetupTemplate::new(&state, tournament_slug, overlay_id).await?;
+    if form
+        .message_id
+        .is_some_and(|id| !template.content.lower_thirds().iter().any(|m| m.id == id))
+    {
+        return Err("Mensagem não encontrada".into());
+    }
+
+    let lower_third = LowerThird {
+        pinned_message_id: form.message_id,
+        rotation_started_at: Utc::now(),
+        ..template.content.lower_third.clone()
+    };
+    state.db.upsert_lower_third(&lower_third).await?;
+
+    broadcast_lower_third(&state, overlay_id).await?;
+    Ok(Html(
+        LowerThirdSetupTemplate {
+            content: get_lower_third_content(&state, overlay_id).await?,
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessageForm {
+    /// Only needed when adding the message
+    kind: Option<MessageKind>,
+    title: String,
+    #[serde(default)]
+    subtitle: String,
+    duration_seconds: Option<i32>,
+}
+
+impl MessageForm {
+    fn message(self, overlay_id: Uuid, id: Uuid) -> Result<LowerThirdMessage, AppError> {
+        let title = self.title.trim().to_string();
+        if title.is_empty() {
+            return Err("A mensagem precisa de um texto".into());
+        }
+        let subtitle = Some(self.subtitle.trim().to_string()).filter(|s| !s.is_empty());
+
+        Ok(LowerThirdMessage {
+            id,
+            overlay_id,
+            kind: self.kind.unwrap_or(MessageKind::LowerThird),
+            title,
+            subtitle,
+            duration_seconds: self
+                .duration_seconds
+                .unwrap_or(DEFAULT_DURATION_SECONDS)
+                .clamp(MIN_DURATION_SECONDS, MAX_DURATION_SECONDS),
+        })
+    }
+}
+
+#[axum::debug_handler]
+pub async fn add_lower_third_message(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    Form(form): Form<MessageForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let template = LowerThirdSetupTemplate::new(&state, tournament_slug, overlay_id).await?;
+    state
+        .db
+        .insert_lower_third_message(&form.message(overlay_id, Uuid::new_v4())?)
+        .await?;
+
+    broadcast_lower_third(&state, overlay_id).await?;
+    Ok(Html(
+        LowerThirdSetupTemplate {
+            content: get_lower_third_content(&state, overlay_id).await?,
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn update_lower_third_message(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id, message_id)): Path<(String, Uuid, Uuid)>,
+    Form(form): Form<MessageForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let template = LowerThirdSetupTemplate::new(&state, tournament_slug, overlay_id).await?;
+    state
+        .db
+        .update_lower_third_message(&form.message(overlay_id, message_id)?)
+        .await?;
+
+    broadcast_lower_third(&state, overlay_id).await?;
+    Ok(Html(
+        LowerThirdSetupTemplate {
+            content: get_lower_third_content(&state, overlay_id).await?,
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn delete_lower_third_message(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id, message_id)): Path<(String, Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let template = LowerThirdSetupTemplate::new(&state, tournament_slug, overlay_id).await?;
+    state
+        .db
+        .delete_lower_third_message(overlay_id, message_id)
+        .await?;
+
+    broadcast_lower_third(&state, overlay_id).await?;
+    Ok(Html(
+        LowerThirdSetupTemplate {
+            content: get_lower_third_content(&state, overlay_id).await?,
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MoveForm {
+    direction: Direction,
+}
+
+/// Swaps the message with the previous or next one of the same kind
+#[axum::debug_handler]
+pub async fn move_lower_third_message(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id, message_id)): Path<(String, Uuid, Uuid)>,
+    Form(form): Form<MoveForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let template = LowerThirdSetupTemplate::new(&state, tournament_slug, overlay_id).await?;
+    let messages = &template.content.messages;
+    let index = messages
+        .iter()
+        .position(|m| m.id == message_id)
+        .ok_or_else(|| AppError::from("Mensagem não encontrada"))?;
+    let kind = messages[index].kind;
+    let other = match form.direction {
+        Direction::Up => messages[..index].iter().rposition(|m| m.kind == kind),
+        Direction::Down => messages[index + 1..]
+            .iter()
+            .position(|m| m.kind == kind)
+            .map(|i| index + 1 + i),
+    };
+
+    if let Some(other) = other {
+        let mut ids = messages.iter().map(|m| m.id).collect::<Vec<_>>();
+        ids.swap(index, other);
+        state
+            .db
+            .reorder_lower_third_messages(overlay_id, &ids)
+            .await?;
+        broadcast_lower_third(&state, overlay_id).await?;
+    }
+
+    Ok(Html(
+        LowerThirdSetupTemplate {
+            content: get_lower_third_content(&state, overlay_id).await?,
+            ..template
+        }
+        .render()?,
+    ))
+}
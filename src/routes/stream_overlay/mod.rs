@@ -1,34 +1,54 @@
 pub mod background;
+pub mod bracket;
 pub mod casters;
+pub mod history;
+pub mod lineups;
+pub mod lower_third;
 pub mod partial;
+pub mod snapshot;
+pub mod sponsors;
+pub mod standings;
+pub mod startgg_sync;
+pub mod theme;
 pub mod waiting;
+pub mod websocket;
 
+use super::assets::FOREIGN_IMAGE;
 use super::error::AppError;
 use super::sse::{SSEDestination, SSEvent, SSEventType};
 use super::tournament::{get_tournament_teams, TeamsSetup};
 use super::AppState;
+use crate::database::matches::Match;
+use crate::database::overlay_events::OverlayState;
 use crate::database::scoreboard::Scoreboard;
 use crate::routes::views::filters;
 use crate::startgg::auth::AuthSession;
 use crate::startgg::tournaments::StartGGTeam;
 use askama::Template;
 use axum::extract::ws::{self, WebSocket};
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::response::{Html, IntoResponse};
 use axum::Form;
 use futures_util::{SinkExt, StreamExt};
+use reqwest::StatusCode;
 use serde::Deserialize;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tokio_stream::wrappers::BroadcastStream;
 use uuid::Uuid;
+use websocket::{OverlayUpdate, ScoreboardState};
 
 #[axum::debug_handler]
 pub async fn ingame_overlay(
-    _s: State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
     Path(overlay_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, AppError> {
-    Ok(Html(IngameOverlayTemplate { overlay_id }.render()?))
+    Ok(Html(
+        IngameOverlayTemplate {
+            overlay_id,
+            sponsor_token: state.db.get_sponsor_token(overlay_id).await?,
+        }
+        .render()?,
+    ))
 }
 
 pub async fn get_scoreboard(
@@ -37,6 +57,8 @@ pub async fn get_scoreboard(
 ) -> anyhow::Result<ScoreboardTemplate> {
     let team_a = state.db.get_team(&scoreboard.team_a).await?;
     let team_b = state.db.get_team(&scoreboard.team_b).await?;
+    let theme = state.db.get_overlay_theme(scoreboard.overlay_id).await?;
+    let swap_sides = scoreboard.swap_sides(theme.swap_scoreboard_sides);
 
     Ok(ScoreboardTemplate {
         team_a,
@@ -46,6 +68,8 @@ pub async fn get_scoreboard(
         team_b_score: scoreboard.team_b_score,
         team_b_standing: scoreboard.team_b_standing,
         logo: scoreboard.logo,
+        best_of: scoreboard.best_of,
+        swap_sides,
     })
 }
 
@@ -59,22 +83,51 @@ pub async fn get_championhip_phase(
     })
 }
 
+#[derive(Debug, Deserialize)]
+pub struct OverlayWsQuery {
+    /// Protocol version, see [`websocket`]. Without it only the legacy scoreboard messages are sent
+    v: Option<u32>,
+    last_event_id: Option<u64>,
+    /// Session from the `hello` of a previous connection, resumed from its last acknowledged update
+    session: Option<Uuid>,
+}
+
 #[axum::debug_handler]
 pub async fn overlay_ws(
     State(state): State<Arc<AppState>>,
     ws: ws::WebSocketUpgrade,
     Path(overlay_id): Path<Uuid>,
-) -> Result<impl IntoResponse, String> {
+    Query(query): Query<OverlayWsQuery>,
+) -> Result<impl IntoResponse, AppError> {
     let conn_id = Uuid::new_v4();
     tracing::debug!(
         "Incoming websocket connection for overlay {}: {}",
         overlay_id,
         conn_id
     );
-    Ok(ws.on_upgrade(move |socket| handle_websocket(socket, state, overlay_id, conn_id)))
+
+    match query.v {
+        None => Ok(ws
+            .on_upgrade(move |socket| handle_legacy_websocket(socket, state, overlay_id, conn_id))),
+        Some(websocket::PROTOCOL_VERSION) => Ok(ws.on_upgrade(move |socket| {
+            websocket::handle_websocket(
+                socket,
+                state,
+                overlay_id,
+                conn_id,
+                query.session,
+                query.last_event_id,
+            )
+        })),
+        Some(v) => Err(
+            AppError::from(format!("Unsupported protocol version: {}", v))
+                .with_status(StatusCode::BAD_REQUEST),
+        ),
+    }
 }
 
-async fn handle_websocket(
+/// Sends `{"overlay_id", "team_a", "team_b"}` with the team IDs whenever the scoreboard changes
+async fn handle_legacy_websocket(
     socket: WebSocket,
     state: Arc<AppState>,
     overlay_id: Uuid,
@@ -83,29 +136,29 @@ async fn handle_websocket(
     let (sender, _receiver) = socket.split();
     let sender_arc = Arc::new(Mutex::new(sender));
 
-    let events = state.events_sender.subscribe();
-
-    let channel_id = format!("overlay_{}", overlay_id);
-    let event_stream = BroadcastStream::new(events)
-        .filter_map(|res| async { res.ok() })
-        .filter_map(|event: SSEvent| async {
-            match &event.destination {
-                SSEDestination::Everyone => Some(event),
-                SSEDestination::Channel(c) => {
-                    if c == &channel_id {
-                        Some(event)
-                    } else {
-                        None
-                    }
-                }
-            }
-        })
-        .filter_map(|ev| async move {
-            match ev.event {
-                SSEventType::WebsocketEvent => Some(ev.data),
-                _ => None,
-            }
-        });
+    let event_stream = snapshot::subscribe_with_resync(
+        state,
+        SSEDestination::Overlay(overlay_id),
+        None,
+        Some(SSEventType::WebsocketEvent),
+    )
+    .filter_map(|ev| async move {
+        if ev.event != SSEventType::WebsocketEvent {
+            return None;
+        }
+        let update: serde_json::Value = serde_json::from_str(&ev.data).ok()?;
+        if update["type"] != "scoreboard" {
+            return None;
+        }
+        Some(
+            serde_json::json!({
+                "overlay_id": overlay_id,
+                "team_a": update["data"]["team_a"]["id"],
+                "team_b": update["data"]["team_b"]["id"],
+            })
+            .to_string(),
+        )
+    });
 
     event_stream
         .for_each_concurrent(None, move |data| {
@@ -136,6 +189,12 @@ pub struct UpdateTeamForm {
     championship_phase: Option<String>,
     #[serde(rename = "tournament_logo")]
     logo: String,
+    #[serde(default = "default_best_of")]
+    best_of: i32,
+}
+
+fn default_best_of() -> i32 {
+    3
 }
 #[axum::debug_handler]
 pub async fn update_ingame_scoreboard(
@@ -145,6 +204,33 @@ pub async fn update_ingame_scoreboard(
     Form(form): Form<UpdateTeamForm>,
 ) -> Result<impl IntoResponse, AppError> {
     let overlay = state.db.get_overlay(overlay_id).await?;
+    if !state
+        .db
+        .is_tournament_image(&tournament_slug, &form.logo)
+        .await?
+    {
+        return Err(AppError::from(FOREIGN_IMAGE).with_status(StatusCode::BAD_REQUEST));
+    }
+
+    let previous = state.db.get_scoreboard(overlay_id).await.ok();
+
+    // Keep the start.gg set link and the sides only while the same teams are on the scoreboard
+    let same_teams = previous
+        .as_ref()
+        .filter(|s| s.team_a == form.team_a && s.team_b == form.team_b);
+    let startgg_set_id = same_teams.and_then(|s| s.startgg_set_id.clone());
+    let sides_swapped = same_teams.is_some_and(|s| s.sides_swapped);
+
+    // Standings left empty are filled with the records computed from the completed matches
+    let tournament_standings = state.db.get_tournament_standings(&tournament_slug).await?;
+    let team_a_standing = form
+        .team_a_standing
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| standings::team_record(&tournament_standings, &form.team_a));
+    let team_b_standing = form
+        .team_b_standing
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| standings::team_record(&tournament_standings, &form.team_b));
 
     let scoreboard = Scoreboard {
         overlay_id,
@@ -152,51 +238,30 @@ pub async fn update_ingame_scoreboard(
         team_b: form.team_b,
         team_a_score: form.team_a_score,
         team_b_score: form.team_b_score,
-        team_a_standing: form.team_a_standing.unwrap_or("0-0".to_string()),
-        team_b_standing: form.team_b_standing.unwrap_or("0-0".to_string()),
+        team_a_standing,
+        team_b_standing,
         championship_phase: form.championship_phase,
         logo: form.logo,
+        startgg_set_id,
+        best_of: form.best_of,
+        sides_swapped,
     };
 
     let scoreboard = state.db.upsert_scoreboard(scoreboard).await?;
+    history::record_change(
+        &state,
+        overlay_id,
+        &auth_session.actor(),
+        OverlayState::Scoreboard(previous),
+        OverlayState::Scoreboard(Some(scoreboard.clone())),
+    )
+    .await;
 
     let team_a = state.db.get_team(&scoreboard.team_a).await?;
     let team_b = state.db.get_team(&scoreboard.team_b).await?;
 
-    state.events_sender.send(SSEvent {
-        destination: SSEDestination::Channel(format!("overlay_{}", overlay.id)),
-        event: SSEventType::IngameOverlayUpdate,
-        data: ScoreboardTemplate {
-            team_a: team_a.clone(),
-            team_b: team_b.clone(),
-            team_a_score: scoreboard.team_a_score,
-            team_a_standing: scoreboard.team_a_standing.clone(),
-            team_b_score: scoreboard.team_b_score,
-            team_b_standing: scoreboard.team_b_standing.clone(),
-            logo: scoreboard.logo.clone(),
-        }
-        .render()?,
-    })?;
-
-    state.events_sender.send(SSEvent {
-        destination: SSEDestination::Channel(format!("overlay_{}", overlay.id)),
-        event: SSEventType::ChampionshipPhaseUpdate,
-        data: ChampionshipPhaseTemplate {
-            overlay_id,
-            championship_phase: scoreboard.championship_phase.clone(),
-        }
-        .as_phase()
-        .render()?,
-    })?;
-
-    state.events_sender.send(SSEvent {
-        destination: SSEDestination::Channel(format!("overlay_{}", overlay.id)),
-        event: SSEventType::WebsocketEvent,
-        data: format!(
-            r#"{{"overlay_id": "{}", "team_a": "{}", "team_b": "{}"}}"#,
-            overlay_id, scoreboard.team_a, scoreboard.team_b
-        ),
-    })?;
+    broadcast_scoreboard(&state, overlay.id, &scoreboard, &team_a, &team_b).await?;
+    lineups::broadcast_lineups(&state, overlay.id).await?;
 
     let teams = get_tournament_teams(state, &auth_session, &tournament_slug).await?;
 
@@ -205,10 +270,214 @@ pub async fn update_ingame_scoreboard(
         overlay_id,
         tournament_slug,
         selected_teams: Some((team_a, team_b, scoreboard)),
+        startgg_report: None,
     }
     .render()?)
 }
 
+/// Puts the teams on the other side of the scoreboard, keeping who is team A and B
+#[axum::debug_handler]
+pub async fn swap_ingame_sides(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let previous = state.db.get_scoreboard(overlay_id).await?;
+
+    let scoreboard = state
+        .db
+        .upsert_scoreboard(Scoreboard {
+            sides_swapped: !previous.sides_swapped,
+            ..previous.clone()
+        })
+        .await?;
+    history::record_change(
+        &state,
+        overlay_id,
+        &auth_session.actor(),
+        OverlayState::Scoreboard(Some(previous)),
+        OverlayState::Scoreboard(Some(scoreboard.clone())),
+    )
+    .await;
+
+    let team_a = state.db.get_team(&scoreboard.team_a).await?;
+    let team_b = state.db.get_team(&scoreboard.team_b).await?;
+
+    broadcast_scoreboard(&state, overlay_id, &scoreboard, &team_a, &team_b).await?;
+
+    let teams = get_tournament_teams(state, &auth_session, &tournament_slug).await?;
+
+    Ok(Html(
+        TeamsSetup {
+            teams,
+            overlay_id,
+            tournament_slug,
+            selected_teams: Some((team_a, team_b, scoreboard)),
+            startgg_report: None,
+        }
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn report_ingame_scoreboard(
+    State(state): State<Arc<AppState>>,
+    auth_session: AuthSession,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let scoreboard = state.db.get_scoreboard(overlay_id).await?;
+    let matches = state.db.get_overlay_matches(overlay_id).await?;
+    let linked_match = matches
+        .iter()
+        .find(|m| m.startgg_set_id.is_some() && m.startgg_set_id == scoreboard.startgg_set_id)
+        .cloned();
+    // Scores in the team order of the linked match, the order its games were recorded in
+    let (team_a_score, team_b_score) = match &linked_match {
+        Some(m) if m.team_a.id != scoreboard.team_a => {
+            (scoreboard.team_b_score, scoreboard.team_a_score)
+        }
+        _ => (scoreboard.team_a_score, scoreboard.team_b_score),
+    };
+
+    let startgg_report = match &scoreboard.startgg_set_id {
+        Some(set_id) => {
+            let (team_a, team_b, games) = match &linked_match {
+                Some(m) => (
+                    m.team_a.id.as_str(),
+                    m.team_b.id.as_str(),
+                    waiting::startgg_set_games(m),
+                ),
+                None => (
+                    scoreboard.team_a.as_str(),
+                    scoreboard.team_b.as_str(),
+                    vec![],
+                ),
+            };
+            state
+                .startgg_client(&auth_session.access_token)
+                .report_set_score(
+                    set_id,
+                    (team_a, team_a_score),
+                    (team_b, team_b_score),
+                    &games,
+                )
+                .await
+                .inspect_err(|e| tracing::error!("Failed to report scoreboard to start.gg: {}", e))
+                .map_err(|e| e.to_string())
+        }
+        None => Err("O placar não está vinculado a nenhuma partida do start.gg".to_string()),
+    };
+
+    if startgg_report.is_ok() {
+        if let Some(m) = linked_match {
+            state
+                .db
+                .upsert_match(Match {
+                    team_a_score,
+                    team_b_score,
+                    completed: true,
+                    in_progress: false,
+                    ..m
+                })
+                .await?;
+            let previous = matches;
+            let matches = state.db.get_overlay_matches(overlay_id).await?;
+            history::record_change(
+                &state,
+                overlay_id,
+                &auth_session.actor(),
+                OverlayState::Matches(previous),
+                OverlayState::Matches(matches.clone()),
+            )
+            .await;
+            waiting::broadcast_todays_matches(&state, overlay_id, &matches)?;
+            standings::broadcast_standings(&state, &tournament_slug).await?;
+        }
+    }
+
+    let team_a = state.db.get_team(&scoreboard.team_a).await?;
+    let team_b = state.db.get_team(&scoreboard.team_b).await?;
+
+    let teams = get_tournament_teams(state, &auth_session, &tournament_slug).await?;
+
+    Ok(Html(
+        TeamsSetup {
+            teams,
+            overlay_id,
+            tournament_slug,
+            selected_teams: Some((team_a, team_b, scoreboard)),
+            startgg_report: Some(startgg_report),
+        }
+        .render()?,
+    ))
+}
+
+pub async fn broadcast_scoreboard(
+    state: &AppState,
+    overlay_id: Uuid,
+    scoreboard: &Scoreboard,
+    team_a: &StartGGTeam,
+    team_b: &StartGGTeam,
+) -> anyhow::Result<()> {
+    let theme = state.db.get_overlay_theme(overlay_id).await?;
+
+    for event in scoreboard_events(
+        overlay_id,
+        scoreboard,
+        team_a,
+        team_b,
+        theme.swap_scoreboard_sides,
+    )? {
+        state.events.publish(event);
+    }
+
+    Ok(())
+}
+
+pub fn scoreboard_events(
+    overlay_id: Uuid,
+    scoreboard: &Scoreboard,
+    team_a: &StartGGTeam,
+    team_b: &StartGGTeam,
+    theme_swap_sides: bool,
+) -> anyhow::Result<Vec<SSEvent>> {
+    Ok(vec![
+        SSEvent::overlay(
+            overlay_id,
+            SSEventType::IngameOverlayUpdate,
+            ScoreboardTemplate {
+                team_a: team_a.clone(),
+                team_b: team_b.clone(),
+                team_a_score: scoreboard.team_a_score,
+                team_a_standing: scoreboard.team_a_standing.clone(),
+                team_b_score: scoreboard.team_b_score,
+                team_b_standing: scoreboard.team_b_standing.clone(),
+                logo: scoreboard.logo.clone(),
+                best_of: scoreboard.best_of,
+                swap_sides: scoreboard.swap_sides(theme_swap_sides),
+            }
+            .render()?,
+        ),
+        SSEvent::overlay(
+            overlay_id,
+            SSEventType::ChampionshipPhaseUpdate,
+            ChampionshipPhaseTemplate {
+                overlay_id,
+                championship_phase: scoreboard.championship_phase.clone(),
+            }
+            .as_phase()
+            .render()?,
+        ),
+        OverlayUpdate::Scoreboard(ScoreboardState::new(
+            scoreboard,
+            team_a,
+            team_b,
+            theme_swap_sides,
+        ))
+        .event(overlay_id)?,
+    ])
+}
+
 #[axum::debug_handler]
 pub async fn ingame_scoreboard(
     State(state): State<Arc<AppState>>,
@@ -237,6 +506,7 @@ pub async fn ingame_championship_phase(
 #[template(path = "stream_overlays/ingame.html")]
 pub struct IngameOverlayTemplate {
     pub overlay_id: Uuid,
+    pub sponsor_token: Uuid,
 }
 
 #[derive(Template)]
@@ -249,6 +519,9 @@ pub struct ScoreboardTemplate {
     pub team_b_score: i32,
     pub team_b_standing: String,
     pub logo: String,
+    pub best_of: i32,
+    /// Team B on the left, from the tournament theme
+    pub swap_sides: bool,
 }
 
 #[derive(Template)]
@@ -12,6 +12,7 @@ use crate::routes::error::AppError;
 use crate::routes::sse::SSEventType;
 use crate::routes::AppState;
 
+use super::casters::{overlay_casters, CastersContentTemplate};
 use super::waiting::{TodaysMatchesTemplate, WaitInfoTemplate};
 
 #[derive(Debug, Clone, Deserialize)]
@@ -28,8 +29,20 @@ pub async fn partial(
 ) -> Result<impl IntoResponse, AppError> {
     if hx_request {
         match query.name {
-            SSEventType::IngameOverlayUpdate => todo!(),
-            SSEventType::CasterOverlayUpdate => todo!(),
+            SSEventType::IngameOverlayUpdate => {
+                let scoreboard = state.db.get_scoreboard(overlay_id).await?;
+                Ok(Html(
+                    super::get_scoreboard(state.clone(), scoreboard)
+                        .await?
+                        .render()?,
+                ))
+            }
+            SSEventType::CasterOverlayUpdate => Ok(Html(
+                CastersContentTemplate {
+                    casters: overlay_casters(&state, overlay_id).await?,
+                }
+                .render()?,
+            )),
             SSEventType::TodaysMatchesUpdate => {
                 return Ok(Html(
                     TodaysMatchesTemplate {
@@ -48,7 +61,6 @@ pub async fn partial(
                     .render()?,
                 ))
             }
-            SSEventType::WebsocketEvent => todo!(),
             _ => Err(
                 AppError::from(format!("Invalid SSEventType: {}", query.name))
                     .with_status(StatusCode::NOT_FOUND),
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use askama::Template;
+use futures_util::stream::{self, Stream};
+use futures_util::StreamExt;
+use uuid::Uuid;
+
+use crate::routes::hub::HubMessage;
+use crate::routes::sse::{SSEDestination, SSEvent, SSEventType};
+use crate::routes::AppState;
+
+use super::bracket::{render_bracket, BracketContentTemplate};
+use super::casters::{casters_events, overlay_casters};
+use super::lineups::lineups_event;
+use super::lower_third::lower_third_event;
+use super::scoreboard_events;
+use super::sponsors::sponsors_event;
+use super::standings::render_standings;
+use super::waiting::{todays_matches_events, wait_timer_events};
+use super::websocket::OverlayUpdate;
+
+/// Subscribes to the destination events, starting with the current state of the overlay.
+/// A subscriber reconnecting with the ID of the last event it got only receives what it missed,
+/// if still available. When it lags behind the whole state is sent again.
+/// With an `event` type, only the state of that type is sent.
+pub fn subscribe_with_resync(
+    state: Arc<AppState>,
+    destination: SSEDestination,
+    last_event_id: Option<u64>,
+    event: Option<SSEventType>,
+) -> impl Stream<Item = SSEvent> + Send + 'static {
+    let overlay_id = match destination {
+        SSEDestination::Overlay(overlay_id) => Some(overlay_id),
+        SSEDestination::Everyone => None,
+    };
+
+    state
+        .events
+        .subscribe(destination, last_event_id)
+        .then(move |message| {
+            let state = state.clone();
+            let event = event.clone();
+            async move {
+                match (message, overlay_id) {
+                    (HubMessage::Event(event), _) => vec![event],
+                    (HubMessage::Resync(id), Some(overlay_id)) => {
+                        tracing::debug!("Sending overlay {} state", overlay_id);
+                        overlay_snapshot(&state, overlay_id, event.as_ref())
+                            .await
+                            .into_iter()
+                            .map(|event| SSEvent {
+                                id: Some(id),
+                                ..event
+                            })
+                            .collect()
+                    }
+                    (HubMessage::Resync(_), None) => vec![],
+                }
+            }
+        })
+        .flat_map(stream::iter)
+}
+
+/// Renders every event of the overlay from its current state, or only those of the `event` type.
+/// Parts that fail to load are skipped, the rest of the overlay is still resynced.
+pub async fn overlay_snapshot(
+    state: &AppState,
+    overlay_id: Uuid,
+    event: Option<&SSEventType>,
+) -> Vec<SSEvent> {
+    use SSEventType::*;
+
+    let wants = |types: &[SSEventType]| event.is_none_or(|event| types.contains(event));
+    let mut parts = vec![];
+
+    if wants(&[IngameOverlayUpdate, ChampionshipPhaseUpdate, WebsocketEvent]) {
+        parts.push(scoreboard_snapshot(state, overlay_id).await);
+    }
+    if wants(&[CasterOverlayUpdate, WebsocketEvent]) {
+        parts.push(casters_snapshot(state, overlay_id).await);
+    }
+    if wants(&[
+        WaitInfoUpdate,
+        WaitInfoStandaloneUpdate,
+        NextMatchInfoUpdate,
+        TodaysMatchesUpdate,
+        WebsocketEvent,
+    ]) {
+        parts.push(waiting_snapshot(state, overlay_id).await);
+    }
+    if wants(&[BracketUpdate]) {
+        parts.push(bracket_snapshot(state, overlay_id).await);
+    }
+    if wants(&[StandingsUpdate]) {
+        parts.push(standings_snapshot(state, overlay_id).await);
+    }
+    if wants(&[LineupsUpdate]) {
+        parts.push(lineups_event(state, overlay_id).await.map(|e| vec![e]));
+    }
+    if wants(&[LowerThirdUpdate]) {
+        parts.push(lower_third_event(state, overlay_id).await.map(|e| vec![e]));
+    }
+    if wants(&[SponsorsUpdate]) {
+        parts.push(sponsors_event(state, overlay_id).await.map(|e| vec![e]));
+    }
+    if wants(&[WebsocketEvent]) {
+        parts.push(theme_snapshot(state, overlay_id).await);
+    }
+
+    collect_parts(overlay_id, parts)
+        .into_iter()
+        .filter(|e| event.is_none_or(|event| *event == e.event))
+        .collect()
+}
+
+/// Only the events sent to websocket clients, without rendering the bracket and standings
+pub async fn websocket_snapshot(state: &AppState, overlay_id: Uuid) -> Vec<SSEvent> {
+    overlay_snapshot(state, overlay_id, Some(&SSEventType::WebsocketEvent)).await
+}
+
+fn collect_parts(
+    overlay_id: Uuid,
+    parts: impl IntoIterator<Item = anyhow::Result<Vec<SSEvent>>>,
+) -> Vec<SSEvent> {
+    parts
+        .into_iter()
+        .filter_map(|part| {
+            part.inspect_err(|e| {
+                tracing::warn!("Failed to snapshot overlay {}: {}", overlay_id, e);
+            })
+            .ok()
+        })
+        .flatten()
+        .collect()
+}
+
+async fn scoreboard_snapshot(state: &AppState, overlay_id: Uuid) -> anyhow::Result<Vec<SSEvent>> {
+    let scoreboard = state.db.get_scoreboard(overlay_id).await?;
+    let team_a = state.db.get_team(&scoreboard.team_a).await?;
+    let team_b = state.db.get_team(&scoreboard.team_b).await?;
+    let theme = state.db.get_overlay_theme(overlay_id).await?;
+
+    scoreboard_events(
+        overlay_id,
+        &scoreboard,
+        &team_a,
+        &team_b,
+        theme.swap_scoreboard_sides,
+    )
+}
+
+async fn casters_snapshot(state: &AppState, overlay_id: Uuid) -> anyhow::Result<Vec<SSEvent>> {
+    let casters = overlay_casters(state, overlay_id).await?;
+    casters_events(overlay_id, casters)
+}
+
+async fn waiting_snapshot(state: &AppState, overlay_id: Uuid) -> anyhow::Result<Vec<SSEvent>> {
+    let wait_timer = state.db.get_wait_timer(&overlay_id).await.ok();
+    let matches = state.db.get_overlay_matches(overlay_id).await?;
+
+    let mut events = wait_timer_events(overlay_id, &wait_timer)?;
+    events.extend(todays_matches_events(overlay_id, &matches)?);
+    Ok(events)
+}
+
+async fn bracket_snapshot(state: &AppState, overlay_id: Uuid) -> anyhow::Result<Vec<SSEvent>> {
+    let bracket_html = match state.brackets.get(overlay_id) {
+        Some(html) => html,
+        None => {
+            // Not rendered since the server started. The bracket comes from start.gg,
+            // it can only be fetched with the server token
+            let Some(token) = &state.startgg_token else {
+                return Ok(vec![]);
+            };
+
+            let overlay = state.db.get_overlay(overlay_id).await?;
+            let html = match render_bracket(state, &overlay, token).await? {
+                Some(html) => html,
+                None => BracketContentTemplate { bracket: None }.render()?,
+            };
+            state.brackets.insert(overlay_id, html.clone());
+            html
+        }
+    };
+
+    Ok(vec![SSEvent::overlay(
+        overlay_id,
+        SSEventType::BracketUpdate,
+        bracket_html,
+    )])
+}
+
+async fn theme_snapshot(state: &AppState, overlay_id: Uuid) -> anyhow::Result<Vec<SSEvent>> {
+    let theme = state.db.get_overlay_theme(overlay_id).await?;
+    Ok(vec![OverlayUpdate::Theme(theme).event(overlay_id)?])
+}
+
+async fn standings_snapshot(state: &AppState, overlay_id: Uuid) -> anyhow::Result<Vec<SSEvent>> {
+    let overlay = state.db.get_overlay(overlay_id).await?;
+
+    Ok(vec![SSEvent::overlay(
+        overlay_id,
+        SSEventType::StandingsUpdate,
+        render_standings(state, &overlay.tournament_slug).await?,
+    )])
+}
@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use axum::Form;
+use chrono::Utc;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::sponsors::{Sponsor, SponsorSlot};
+use crate::routes::error::AppError;
+use crate::routes::sse::{SSEvent, SSEventType};
+use crate::routes::AppState;
+
+/// How long each sponsor of the rotation stays on screen
+pub const SLOT_SECONDS: i32 = 10;
+
+/// Order the sponsors are shown in each turn of the rotation, each as many times as its weight.
+/// Showings of the same sponsor are spread out instead of shown in a row.
+fn rotation(sponsors: &[Sponsor]) -> Vec<Sponsor> {
+    let total: i32 = sponsors.iter().map(|s| s.weight).sum();
+    let mut current = vec![0; sponsors.len()];
+
+    (0..total)
+        .filter_map(|_| {
+            for (c, sponsor) in current.iter_mut().zip(sponsors) {
+                *c += sponsor.weight;
+            }
+            let next = current
+                .iter()
+                .enumerate()
+                .max_by_key(|(i, c)| (**c, Reverse(*i)))
+                .map(|(i, _)| i)?;
+            current[next] -= total;
+            Some(sponsors[next].clone())
+        })
+        .collect()
+}
+
+#[derive(Template)]
+#[template(path = "stream_overlays/sponsors.html")]
+pub struct SponsorRotationTemplate {
+    pub rotation: Vec<Sponsor>,
+}
+
+impl SponsorRotationTemplate {
+    fn slot_seconds(&self) -> i32 {
+        SLOT_SECONDS
+    }
+}
+
+/// Sponsor rotation of the tournament. Sponsors whose window hasn't started yet are included,
+/// the overlays show them once it does.
+async fn render_sponsors(state: &AppState, tournament_slug: &str) -> anyhow::Result<String> {
+    let now = Utc::now();
+    let sponsors = state
+        .db
+        .get_tournament_sponsors(tournament_slug)
+        .await?
+        .into_iter()
+        .filter(|s| s.ends_at.is_none_or(|ends_at| now < ends_at))
+        .collect::<Vec<_>>();
+
+    Ok(SponsorRotationTemplate {
+        rotation: rotation(&sponsors),
+    }
+    .render()?)
+}
+
+pub async fn sponsors_event(state: &AppState, overlay_id: Uuid) -> anyhow::Result<SSEvent> {
+    let overlay = state.db.get_overlay(overlay_id).await?;
+    Ok(SSEvent::overlay(
+        overlay_id,
+        SSEventType::SponsorsUpdate,
+        render_sponsors(state, &overlay.tournament_slug).await?,
+    ))
+}
+
+/// Sends the new rotation to every overlay of the tournament
+pub async fn broadcast_sponsors(state: &AppState, tournament_slug: &str) -> anyhow::Result<()> {
+    let html = render_sponsors(state, tournament_slug).await?;
+    for overlay in state.db.get_tournament_overlays(tournament_slug).await? {
+        state.events.publish(SSEvent::overlay(
+            overlay.id,
+            SSEventType::SponsorsUpdate,
+            html.clone(),
+        ));
+    }
+    Ok(())
+}
+
+#[axum::debug_handler]
+pub async fn sponsors_overlay(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let overlay = state.db.get_overlay(overlay_id).await?;
+    Ok(Html(
+        render_sponsors(&state, &overlay.tournament_slug).await?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImpressionForm {
+    token: Uuid,
+    sponsor_id: Uuid,
+    slot: SponsorSlot,
+    seconds: i32,
+}
+
+/// Sent by the overlays each time a sponsor leaves the screen, with the sponsor token of the overlay.
+/// Each overlay can report a sponsor in a slot once per turn of the slot.
+#[axum::debug_handler]
+pub async fn record_impression(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+    Form(form): Form<ImpressionForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let not_found = |_: anyhow::Error| {
+        AppError::from("Patrocinador não encontrado").with_status(StatusCode::NOT_FOUND)
+    };
+
+    let overlay = state
+        .db
+        .get_overlay(overlay_id)
+        .await
+        .map_err(|e| not_found(e.into()))?;
+    if state.db.get_sponsor_token(overlay_id).await? != form.token {
+        return Err(AppError::from("Token inválido").with_status(StatusCode::FORBIDDEN));
+    }
+    let sponsor = state
+        .db
+        .get_sponsor(&overlay.tournament_slug, form.sponsor_id)
+        .await
+        .map_err(not_found)?;
+
+    if form.seconds <= 0 {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+    // A showing never lasts longer than the slot. The interval is a second shorter than the
+    // slot, as the timers of the overlays drift a little.
+    let recorded = state
+        .db
+        .record_sponsor_impression(
+            sponsor.id,
+            overlay_id,
+            form.slot,
+            form.seconds.min(SLOT_SECONDS),
+            SLOT_SECONDS - 1,
+        )
+        .await?;
+
+    Ok(if recorded {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::TOO_MANY_REQUESTS
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sponsor(name: &str, weight: i32) -> Sponsor {
+        Sponsor {
+            id: Uuid::new_v4(),
+            tournament_slug: "liga".to_string(),
+            name: name.to_string(),
+            logo: format!("/uploads/{}", name),
+            weight,
+            starts_at: None,
+            ends_at: None,
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        let names = |sponsors: &[Sponsor]| {
+            rotation(sponsors)
+                .into_iter()
+                .map(|s| s.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(&[sponsor("a", 1), sponsor("b", 1), sponsor("c", 1)]),
+            ["a", "b", "c"]
+        );
+        assert_eq!(
+            names(&[sponsor("a", 3), sponsor("b", 1)]),
+            ["a", "a", "b", "a"]
+        );
+        assert_eq!(
+            names(&[sponsor("a", 2), sponsor("b", 2), sponsor("c", 1)]),
+            ["a", "b", "c", "a", "b"]
+        );
+        assert!(rotation(&[]).is_empty());
+    }
+}
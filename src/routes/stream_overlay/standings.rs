@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use uuid::Uuid;
+
+use crate::database::standings::TeamStanding;
+use crate::routes::error::AppError;
+use crate::routes::sse::{SSEvent, SSEventType};
+use crate::routes::views::filters;
+use crate::routes::AppState;
+
+#[derive(Template)]
+#[template(path = "stream_overlays/standings.html", block = "standings_content")]
+pub struct StandingsContentTemplate {
+    pub standings: Vec<TeamStanding>,
+}
+
+#[derive(Template)]
+#[template(path = "stream_overlays/standings.html")]
+pub struct StandingsOverlayTemplate {
+    pub overlay_id: Uuid,
+    pub standings: Vec<TeamStanding>,
+}
+
+#[axum::debug_handler]
+pub async fn standings_overlay(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let overlay = state.db.get_overlay(overlay_id).await?;
+    let standings = state
+        .db
+        .get_tournament_standings(&overlay.tournament_slug)
+        .await?;
+
+    Ok(Html(
+        StandingsOverlayTemplate {
+            overlay_id,
+            standings,
+        }
+        .render()?,
+    ))
+}
+
+/// Record of the team in the "W-L" format, "0-0" if it didn't complete any match yet
+pub fn team_record(standings: &[TeamStanding], team_id: &str) -> String {
+    standings
+        .iter()
+        .find(|s| s.team.id == team_id)
+        .map(|s| s.record())
+        .unwrap_or("0-0".to_string())
+}
+
+/// Sends the recomputed standings to every overlay of the tournament
+pub async fn broadcast_standings(state: &AppState, tournament_slug: &str) -> anyhow::Result<()> {
+    let data = render_standings(state, tournament_slug).await?;
+
+    for overlay in state.db.get_tournament_overlays(tournament_slug).await? {
+        state.events.publish(SSEvent::overlay(
+            overlay.id,
+            SSEventType::StandingsUpdate,
+            data.clone(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn render_standings(state: &AppState, tournament_slug: &str) -> anyhow::Result<String> {
+    let standings = state.db.get_tournament_standings(tournament_slug).await?;
+    Ok(StandingsContentTemplate { standings }.render()?)
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::database::matches::Match;
+use crate::database::overlay::Overlay;
+use crate::database::overlay_events::OverlayState;
+use crate::database::scoreboard::Scoreboard;
+use crate::routes::AppState;
+use crate::startgg::sets::{StartGGSet, StartGGSetState};
+
+use super::bracket::{broadcast_bracket, render_bracket};
+use super::broadcast_scoreboard;
+use super::history;
+use super::standings::broadcast_standings;
+use super::waiting::broadcast_todays_matches;
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Periodically pulls the sets of every tournament that has an overlay with start.gg sync enabled,
+/// and mirrors their state into today's matches and the in-game scoreboard.
+/// Bracket overlays are refreshed on the same interval.
+pub async fn startgg_sync_task(state: Arc<AppState>) {
+    let Some(startgg_token) = state.startgg_token.clone() else {
+        return;
+    };
+
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = sync_overlays(state.clone(), &startgg_token).await {
+            tracing::error!("Failed to sync overlays with start.gg: {}", e);
+        }
+        if let Err(e) = sync_brackets(&state, &startgg_token).await {
+            tracing::error!("Failed to sync brackets with start.gg: {}", e);
+        }
+    }
+}
+
+async fn sync_overlays(state: Arc<AppState>, startgg_token: &str) -> anyhow::Result<()> {
+    let overlays = state.db.get_startgg_synced_overlays().await?;
+    if overlays.is_empty() {
+        return Ok(());
+    }
+
+    let startgg_client = state.startgg_client(startgg_token);
+
+    let mut tournament_sets: HashMap<String, Vec<StartGGSet>> = HashMap::new();
+    for overlay in overlays {
+        if !tournament_sets.contains_key(&overlay.tournament_slug) {
+            let sets = startgg_client
+                .fetch_tournament_sets(&overlay.tournament_slug)
+                .await
+                .inspect_err(|e| {
+                    tracing::error!(
+                        "Failed to fetch start.gg sets for {}: {}",
+                        overlay.tournament_slug,
+                        e
+                    );
+                })
+                .unwrap_or_default();
+            tournament_sets.insert(overlay.tournament_slug.clone(), sets);
+        }
+        let sets = &tournament_sets[&overlay.tournament_slug];
+
+        let _ = sync_overlay_matches(&state, &overlay, sets)
+            .await
+            .inspect_err(|e| {
+                tracing::error!("Failed to sync matches for overlay {}: {}", overlay.id, e);
+            });
+        let _ = sync_overlay_scoreboard(&state, overlay.id, sets)
+            .await
+            .inspect_err(|e| {
+                tracing::error!(
+                    "Failed to sync scoreboard for overlay {}: {}",
+                    overlay.id,
+                    e
+                );
+            });
+    }
+
+    Ok(())
+}
+
+/// Updates the overlay's matches that are linked to a start.gg set, linking matches typed by hand
+/// to their sets when the teams match, and adding sets that were just called or started.
+async fn sync_overlay_matches(
+    state: &AppState,
+    overlay: &Overlay,
+    sets: &[StartGGSet],
+) -> anyhow::Result<()> {
+    let matches = state.db.get_overlay_matches(overlay.id).await?;
+    let mut changed = false;
+
+    for set in sets {
+        let linked = matches
+            .iter()
+            .find(|m| m.startgg_set_id.as_deref() == Some(set.id.as_str()))
+            .or_else(|| {
+                matches.iter().find(|m| {
+                    set.state != StartGGSetState::Completed
+                        && m.startgg_set_id.is_none()
+                        && !m.completed
+                        && set.is_between(&m.team_a.id, &m.team_b.id)
+                })
+            });
+
+        let current = match linked {
+            Some(m) => m.clone(),
+            None if matches!(
+                set.state,
+                StartGGSetState::Called | StartGGSetState::InProgress
+            ) =>
+            {
+                let team_ids = set
+                    .slots
+                    .iter()
+                    .filter_map(|s| s.team_id.clone())
+                    .collect::<Vec<_>>();
+                let [team_a, team_b, ..] = team_ids.as_slice() else {
+                    continue;
+                };
+                // Teams are only known once someone opened the setup pages for this tournament
+                let (Ok(team_a), Ok(team_b)) = (
+                    state.db.get_team(team_a).await,
+                    state.db.get_team(team_b).await,
+                ) else {
+                    continue;
+                };
+                Match {
+                    id: Uuid::nil(),
+                    overlay_id: Some(overlay.id),
+                    tournament_slug: overlay.tournament_slug.clone(),
+                    team_a,
+                    team_b,
+                    team_a_score: 0,
+                    team_b_score: 0,
+                    completed: false,
+                    in_progress: false,
+                    featured: false,
+                    startgg_set_id: None,
+                    best_of: 3,
+                    games: vec![],
+                }
+            }
+            None => continue,
+        };
+
+        let Some((team_a_score, team_b_score)) =
+            set.scores_for(&current.team_a.id, &current.team_b.id)
+        else {
+            continue;
+        };
+
+        let updated = Match {
+            team_a_score,
+            team_b_score,
+            completed: set.state == StartGGSetState::Completed,
+            in_progress: set.state == StartGGSetState::InProgress,
+            startgg_set_id: Some(set.id.clone()),
+            ..current.clone()
+        };
+
+        if current.id.is_nil()
+            || current.team_a_score != updated.team_a_score
+            || current.team_b_score != updated.team_b_score
+            || current.completed != updated.completed
+            || current.in_progress != updated.in_progress
+            || current.startgg_set_id != updated.startgg_set_id
+        {
+            tracing::debug!("Syncing match {} from start.gg set {}", current.id, set.id);
+            if current.id.is_nil() {
+                state.db.upsert_set_match(updated).await?;
+            } else {
+                state.db.upsert_match(updated).await?;
+            }
+            changed = true;
+        }
+    }
+
+    if changed {
+        let previous = matches;
+        let matches = state.db.get_overlay_matches(overlay.id).await?;
+        history::record_change(
+            state,
+            overlay.id,
+            history::STARTGG_SYNC_ACTOR,
+            OverlayState::Matches(previous),
+            OverlayState::Matches(matches.clone()),
+        )
+        .await;
+        broadcast_todays_matches(state, overlay.id, &matches)?;
+        broadcast_standings(state, &overlay.tournament_slug).await?;
+    }
+
+    Ok(())
+}
+
+/// Mirrors the scores of the set being played by the scoreboard teams into the scoreboard
+async fn sync_overlay_scoreboard(
+    state: &AppState,
+    overlay_id: Uuid,
+    sets: &[StartGGSet],
+) -> anyhow::Result<()> {
+    let Ok(scoreboard) = state.db.get_scoreboard(overlay_id).await else {
+        return Ok(());
+    };
+
+    let set = sets
+        .iter()
+        .find(|s| scoreboard.startgg_set_id.as_deref() == Some(s.id.as_str()))
+        .or_else(|| {
+            sets.iter().find(|s| {
+                s.state == StartGGSetState::InProgress
+                    && s.is_between(&scoreboard.team_a, &scoreboard.team_b)
+            })
+        });
+    let Some(set) = set else {
+        return Ok(());
+    };
+    let Some((team_a_score, team_b_score)) = set.scores_for(&scoreboard.team_a, &scoreboard.team_b)
+    else {
+        return Ok(());
+    };
+
+    let championship_phase = match scoreboard.championship_phase.as_deref() {
+        None | Some("") => set.round_name.clone(),
+        Some(_) => scoreboard.championship_phase.clone(),
+    };
+
+    if scoreboard.team_a_score == team_a_score
+        && scoreboard.team_b_score == team_b_score
+        && scoreboard.championship_phase == championship_phase
+        && scoreboard.startgg_set_id.as_deref() == Some(set.id.as_str())
+    {
+        return Ok(());
+    }
+
+    let previous = scoreboard.clone();
+    let scoreboard = state
+        .db
+        .upsert_scoreboard(Scoreboard {
+            team_a_score,
+            team_b_score,
+            championship_phase,
+            startgg_set_id: Some(set.id.clone()),
+            ..scoreboard
+        })
+        .await?;
+    history::record_change(
+        state,
+        overlay_id,
+        history::STARTGG_SYNC_ACTOR,
+        OverlayState::Scoreboard(Some(previous)),
+        OverlayState::Scoreboard(Some(scoreboard.clone())),
+    )
+    .await;
+
+    let team_a = state.db.get_team(&scoreboard.team_a).await?;
+    let team_b = state.db.get_team(&scoreboard.team_b).await?;
+
+    broadcast_scoreboard(state, overlay_id, &scoreboard, &team_a, &team_b).await
+}
+
+/// Unchanged brackets aren't broadcast again
+async fn sync_brackets(state: &AppState, startgg_token: &str) -> anyhow::Result<()> {
+    let overlays = state.db.get_bracket_overlays().await?;
+    state
+        .brackets
+        .retain(&overlays.iter().map(|o| o.id).collect::<Vec<_>>());
+
+    for overlay in overlays {
+        let bracket_html = match render_bracket(state, &overlay, startgg_token).await {
+            Ok(Some(html)) => html,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::error!("Failed to render bracket for overlay {}: {}", overlay.id, e);
+                continue;
+            }
+        };
+
+        if state.brackets.get(overlay.id).as_ref() != Some(&bracket_html) {
+            tracing::debug!("Syncing bracket of overlay {} from start.gg", overlay.id);
+            broadcast_bracket(state, overlay.id, bracket_html);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use uuid::Uuid;
+
+use crate::database::theme::Theme;
+use crate::routes::error::AppError;
+use crate::routes::AppState;
+
+#[derive(Template)]
+#[template(path = "stream_overlays/theme.css", escape = "none")]
+pub struct ThemeStylesheet {
+    pub theme: Theme,
+}
+
+/// CSS custom properties of the overlay's tournament theme, loaded by every overlay page
+#[axum::debug_handler]
+pub async fn theme_css(
+    State(state): State<Arc<AppState>>,
+    Path(overlay_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let theme = state.db.get_overlay_theme(overlay_id).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/css"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        ThemeStylesheet { theme }.render()?,
+    ))
+}
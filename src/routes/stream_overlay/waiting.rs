@@ -1,13 +1,19 @@
 use std::sync::Arc;
 
+use crate::database::games::MatchGame;
 use crate::database::matches::Match;
+use crate::database::overlay_events::OverlayState;
 use crate::database::wait_timer::{WaitTimer, WaitType};
 use crate::routes::error::AppError;
-use crate::routes::sse::{SSEDestination, SSEvent, SSEventType};
+use crate::routes::obs;
+use crate::routes::sse::{SSEvent, SSEventType};
+use crate::routes::stream_overlay::history;
+use crate::routes::stream_overlay::websocket::OverlayUpdate;
 use crate::routes::tournament::get_tournament_teams;
 use crate::routes::views::filters;
 use crate::routes::AppState;
 use crate::startgg::auth::AuthSession;
+use crate::startgg::sets::StartGGSetGame;
 use crate::startgg::tournaments::StartGGTeam;
 use askama::Template;
 use axum::extract::{Path, State};
@@ -15,13 +21,17 @@ use axum::response::{Html, IntoResponse};
 use axum_extra::extract::Form;
 use axum_htmx::HxRequest;
 use futures_util::future::join_all;
+use reqwest::StatusCode;
 use serde::Deserialize;
 use uuid::Uuid;
 
+use super::standings::broadcast_standings;
+
 #[derive(Template)]
 #[template(path = "stream_overlays/waiting/page.html")]
 pub struct WaitingOverlayTemplate {
     pub overlay_id: Uuid,
+    pub sponsor_token: Uuid,
     pub wait_timer: Option<WaitTimer>,
 }
 
@@ -40,6 +50,7 @@ pub async fn waiting_overlay(
     Ok(Html(
         WaitingOverlayTemplate {
             overlay_id,
+            sponsor_token: state.db.get_sponsor_token(overlay_id).await?,
             wait_timer: get_wait_timer(state, &overlay_id).await,
         }
         .render()?,
@@ -118,6 +129,7 @@ pub struct TimerUpdateForm {
 pub async fn timer_update(
     State(state): State<Arc<AppState>>,
     Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
     Form(form): Form<TimerUpdateForm>,
 ) -> Result<impl IntoResponse, AppError> {
     let naive_time = chrono::NaiveDateTime::parse_from_str(&form.waiting_until, "%Y-%m-%dT%H:%M")?;
@@ -129,45 +141,24 @@ pub async fn timer_update(
         chrono::offset::LocalResult::None => return Err("Invalid time".into()),
     };
 
-    let wait_timer = WaitTimer {
+    let previous = get_wait_timer(state.clone(), &overlay_id).await;
+    let wait_timer = set_wait_timer(
+        state.clone(),
+        WaitTimer {
+            overlay_id,
+            wait_until: time,
+            wait_type: form.wait_type,
+        },
+    )
+    .await?;
+    history::record_change(
+        &state,
         overlay_id,
-        wait_until: time,
-        wait_type: form.wait_type,
-    };
-
-    state.db.upsert_wait_timer(&wait_timer).await?;
-
-    let wait_timer = get_wait_timer(state.clone(), &overlay_id).await;
-
-    let _ = state
-        .events_sender
-        .send(SSEvent {
-            destination: SSEDestination::Channel(format!("overlay_{}", overlay_id)),
-            event: SSEventType::WaitInfoUpdate,
-            data: WaitInfoTemplate {
-                overlay_id,
-                wait_timer: wait_timer.clone(),
-            }
-            .render()?,
-        })
-        .inspect_err(|e| {
-            tracing::error!("Failed to send wait timer update: {}", e);
-        });
-
-    let _ = state
-        .events_sender
-        .send(SSEvent {
-            destination: SSEDestination::Channel(format!("overlay_{}", overlay_id)),
-            event: SSEventType::WaitInfoStandaloneUpdate,
-            data: StandaloneTimerTemplate {
-                overlay_id,
-                wait_timer: wait_timer.clone(),
-            }
-            .render()?,
-        })
-        .inspect_err(|e| {
-            tracing::error!("Failed to send wait timer update: {}", e);
-        });
+        &auth_session.actor(),
+        OverlayState::WaitTimer(previous),
+        OverlayState::WaitTimer(wait_timer.clone()),
+    )
+    .await;
 
     Ok(Html(
         WaitTimerSetupTemplate {
@@ -179,6 +170,31 @@ pub async fn timer_update(
     ))
 }
 
+/// Saves the timer and sends it to the overlay, switching the OBS scene when the wait type changed
+pub async fn set_wait_timer(
+    state: Arc<AppState>,
+    wait_timer: WaitTimer,
+) -> Result<Option<WaitTimer>, AppError> {
+    let overlay_id = wait_timer.overlay_id;
+    let previous_wait_type = get_wait_timer(state.clone(), &overlay_id)
+        .await
+        .map(|t| t.wait_type);
+
+    state.db.upsert_wait_timer(&wait_timer).await?;
+
+    if previous_wait_type.as_ref() != Some(&wait_timer.wait_type) {
+        obs::wait_type_changed(state.clone(), overlay_id, wait_timer.wait_type.clone());
+    }
+
+    let wait_timer = get_wait_timer(state.clone(), &overlay_id).await;
+
+    for event in wait_timer_events(overlay_id, &wait_timer)? {
+        state.events.publish(event);
+    }
+
+    Ok(wait_timer)
+}
+
 pub async fn get_wait_timer(state: Arc<AppState>, overlay_id: &Uuid) -> Option<WaitTimer> {
     state
         .db
@@ -251,6 +267,8 @@ pub struct TodaysMatchesUpdateForm {
     #[serde(default)]
     team_b_score: Vec<i32>,
     #[serde(default)]
+    best_of: Vec<i32>,
+    #[serde(default)]
     completed: Vec<Uuid>,
     #[serde(default)]
     in_progress: Vec<Uuid>,
@@ -265,6 +283,8 @@ pub async fn todays_matches_update(
     auth_session: AuthSession,
     Form(todays_matches_form): Form<TodaysMatchesUpdateForm>,
 ) -> Result<impl IntoResponse, AppError> {
+    let previous = state.db.get_overlay_matches(overlay_id).await?;
+
     let delete_matches = todays_matches_form
         .existing_match_ids
         .into_iter()
@@ -305,30 +325,42 @@ pub async fn todays_matches_update(
                 .into_iter()
                 .zip(todays_matches_form.team_b_score),
         )
+        .zip(todays_matches_form.best_of)
         .zip(todays_matches_form.match_ids)
-        .map(|(((team_a, score_a), (team_b, score_b)), match_id)| {
-            let st = state.clone();
-            let tournament_slug = tournament_slug.clone();
-            let completed = todays_matches_form.completed.contains(&match_id);
-            let in_progress = todays_matches_form.in_progress.contains(&match_id);
-            let featured = todays_matches_form.featured.contains(&match_id);
-            async move {
-                let m = Match {
-                    id: match_id,
-                    overlay_id: Some(overlay_id),
-                    tournament_slug,
-                    team_a: st.db.get_team(&team_a).await?,
-                    team_b: st.db.get_team(&team_b).await?,
-                    team_a_score: score_a,
-                    team_b_score: score_b,
-                    completed,
-                    in_progress,
-                    featured,
-                };
-
-                st.db.upsert_match(m).await
-            }
-        });
+        .map(
+            |((((team_a, score_a), (team_b, score_b)), best_of), match_id)| {
+                let st = state.clone();
+                let tournament_slug = tournament_slug.clone();
+                let completed = todays_matches_form.completed.contains(&match_id);
+                let in_progress = todays_matches_form.in_progress.contains(&match_id);
+                let featured = todays_matches_form.featured.contains(&match_id);
+                async move {
+                    let existing_match = st.db.get_match(match_id).await.ok();
+                    let was_completed = existing_match.as_ref().is_some_and(|m| m.completed);
+                    let (startgg_set_id, games) = existing_match
+                        .map(|m| (m.startgg_set_id, m.games))
+                        .unwrap_or_default();
+                    let m = Match {
+                        id: match_id,
+                        overlay_id: Some(overlay_id),
+                        tournament_slug,
+                        team_a: st.db.get_team(&team_a).await?,
+                        team_b: st.db.get_team(&team_b).await?,
+                        team_a_score: score_a,
+                        team_b_score: score_b,
+                        completed,
+                        in_progress,
+                        featured,
+                        startgg_set_id,
+                        best_of,
+                        games,
+                    };
+
+                    let m = st.db.upsert_match(m).await?;
+                    Ok((m, completed && !was_completed)) as anyhow::Result<_>
+                }
+            },
+        );
 
     // Need to guarantee that the order that the matches are created is the same as in the UI.
     // Because of the creation timestamp that is used for ordering.
@@ -344,45 +376,51 @@ pub async fn todays_matches_update(
     //     .for_each(|e| {
     //         tracing::error!("Failed to update match: {}", e);
     //     });
+    let mut newly_completed: Vec<Match> = vec![];
     for fut in update_matches {
-        let _ = fut.await.inspect_err(|e| {
-            tracing::error!("Failed to update match: {}", e);
-        });
+        match fut.await {
+            Ok((m, true)) => newly_completed.push(m),
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to update match: {}", e),
+        }
+    }
+
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let mut startgg_report_errors = vec![];
+    for m in newly_completed {
+        let Some(set_id) = &m.startgg_set_id else {
+            continue;
+        };
+        if let Err(e) = startgg_client
+            .report_set_score(
+                set_id,
+                (&m.team_a.id, m.team_a_score),
+                (&m.team_b.id, m.team_b_score),
+                &startgg_set_games(&m),
+            )
+            .await
+        {
+            tracing::error!("Failed to report match {} to start.gg: {}", m.id, e);
+            startgg_report_errors.push(format!("{} vs {}: {}", m.team_a.name, m.team_b.name, e));
+        }
     }
 
     let teams = get_tournament_teams(state.clone(), &auth_session, &tournament_slug).await?;
 
     let matches = state.db.get_overlay_matches(overlay_id).await?;
+    history::record_change(
+        &state,
+        overlay_id,
+        &auth_session.actor(),
+        OverlayState::Matches(previous),
+        OverlayState::Matches(matches.clone()),
+    )
+    .await;
 
-    let _ = state
-        .events_sender
-        .send(SSEvent {
-            destination: SSEDestination::Channel(format!("overlay_{}", overlay_id)),
-            event: SSEventType::TodaysMatchesUpdate,
-            data: TodaysMatchesTemplate {
-                todays_matches: matches.clone(),
-            }
-            .render()?,
-        })
-        .inspect_err(|e| {
-            tracing::error!("Failed to send todays matches update: {}", e);
-        });
+    broadcast_todays_matches(&state, overlay_id, &matches)?;
+    broadcast_standings(&state, &tournament_slug).await?;
 
-    let _ = state
-        .events_sender
-        .send(SSEvent {
-            destination: SSEDestination::Channel(format!("overlay_{}", overlay_id)),
-            event: SSEventType::NextMatchInfoUpdate,
-            data: NextUpMatchTemplate {
-                overlay_id,
-                todays_matches: matches.clone(),
-            }
-            .as_next_match_info()
-            .render()?,
-        })
-        .inspect_err(|e| {
-            tracing::error!("Failed to send todays matches update: {}", e);
-        });
+    let overlay = state.db.get_overlay(overlay_id).await?;
 
     Ok(Html(
         WaitingSetupTemplate {
@@ -391,11 +429,76 @@ pub async fn todays_matches_update(
             upcoming_matches: matches,
             teams,
             wait_timer: get_wait_timer(state, &overlay_id).await,
+            startgg_sync: overlay.startgg_sync,
+            startgg_report_errors,
         }
         .render()?,
     ))
 }
 
+pub fn broadcast_todays_matches(
+    state: &AppState,
+    overlay_id: Uuid,
+    matches: &[Match],
+) -> anyhow::Result<()> {
+    for event in todays_matches_events(overlay_id, matches)? {
+        state.events.publish(event);
+    }
+
+    Ok(())
+}
+
+pub fn todays_matches_events(overlay_id: Uuid, matches: &[Match]) -> anyhow::Result<Vec<SSEvent>> {
+    Ok(vec![
+        SSEvent::overlay(
+            overlay_id,
+            SSEventType::TodaysMatchesUpdate,
+            TodaysMatchesTemplate {
+                todays_matches: matches.to_vec(),
+            }
+            .render()?,
+        ),
+        SSEvent::overlay(
+            overlay_id,
+            SSEventType::NextMatchInfoUpdate,
+            NextUpMatchTemplate {
+                overlay_id,
+                todays_matches: matches.to_vec(),
+            }
+            .as_next_match_info()
+            .render()?,
+        ),
+        OverlayUpdate::Matches(matches.into()).event(overlay_id)?,
+    ])
+}
+
+pub fn wait_timer_events(
+    overlay_id: Uuid,
+    wait_timer: &Option<WaitTimer>,
+) -> anyhow::Result<Vec<SSEvent>> {
+    Ok(vec![
+        SSEvent::overlay(
+            overlay_id,
+            SSEventType::WaitInfoUpdate,
+            WaitInfoTemplate {
+                overlay_id,
+                wait_timer: wait_timer.clone(),
+            }
+            .render()?,
+        ),
+        SSEvent::overlay(
+            overlay_id,
+            SSEventType::WaitInfoStandaloneUpdate,
+            StandaloneTimerTemplate {
+                overlay_id,
+                wait_timer: wait_timer.clone(),
+            }
+            .render()?,
+        ),
+        OverlayUpdate::Timer(wait_timer.into()).event(overlay_id)?,
+    ])
+}
+
 #[derive(Template)]
 #[template(path = "waiting/setup.html")]
 pub struct WaitingSetupTemplate {
@@ -404,6 +507,8 @@ pub struct WaitingSetupTemplate {
     upcoming_matches: Vec<Match>,
     teams: Vec<StartGGTeam>,
     wait_timer: Option<WaitTimer>,
+    startgg_sync: bool,
+    startgg_report_errors: Vec<String>,
 }
 
 #[axum::debug_handler]
@@ -415,6 +520,7 @@ pub async fn waiting_setup(
     let teams = get_tournament_teams(state.clone(), &auth_session, &tournament_slug).await?;
 
     let upcoming_matches = state.db.get_overlay_matches(overlay_id).await?;
+    let overlay = state.db.get_overlay(overlay_id).await?;
 
     Ok(Html(
         WaitingSetupTemplate {
@@ -423,7 +529,239 @@ pub async fn waiting_setup(
             tournament_slug,
             overlay_id,
             wait_timer: get_wait_timer(state, &overlay_id).await,
+            startgg_sync: overlay.startgg_sync,
+            startgg_report_errors: vec![],
         }
         .render()?,
     ))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct StartggSyncForm {
+    #[serde(default)]
+    startgg_sync: bool,
+}
+
+#[axum::debug_handler]
+pub async fn startgg_sync_update(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
+    auth_session: AuthSession,
+    Form(form): Form<StartggSyncForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let overlay = state
+        .db
+        .set_overlay_startgg_sync(overlay_id, form.startgg_sync)
+        .await?;
+
+    let teams = get_tournament_teams(state.clone(), &auth_session, &tournament_slug).await?;
+    let upcoming_matches = state.db.get_overlay_matches(overlay_id).await?;
+
+    Ok(Html(
+        WaitingSetupTemplate {
+            upcoming_matches,
+            teams,
+            tournament_slug,
+            overlay_id,
+            wait_timer: get_wait_timer(state, &overlay_id).await,
+            startgg_sync: overlay.startgg_sync,
+            startgg_report_errors: vec![],
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchGameForm {
+    winner: String,
+    #[serde(default)]
+    team_a_goals: i32,
+    #[serde(default)]
+    team_b_goals: i32,
+    arena: Option<String>,
+    duration: Option<String>,
+}
+
+/// Parses durations in the "m:ss" format shown by the game
+fn parse_game_duration(duration: &str) -> Option<chrono::Duration> {
+    let (minutes, seconds) = duration.trim().split_once(':')?;
+    let minutes = minutes.parse::<i64>().ok()?;
+    let seconds = seconds.parse::<i64>().ok().filter(|s| *s < 60)?;
+    Some(chrono::Duration::seconds(minutes * 60 + seconds))
+}
+
+/// The recorded games of the match to report to start.gg, in the team order of the match
+pub fn startgg_set_games(match_: &Match) -> Vec<StartGGSetGame> {
+    match_
+        .games
+        .iter()
+        .map(|game| StartGGSetGame {
+            game_number: game.game_number,
+            winner: game.winner.clone(),
+            team_a_goals: game.team_a_goals,
+            team_b_goals: game.team_b_goals,
+        })
+        .collect()
+}
+
+/// Updates the score of the match from its games, recording the change in the overlay history.
+/// `previous` are the matches of the overlay before the games changed.
+async fn update_match_from_games(
+    state: &AppState,
+    tournament_slug: &str,
+    actor: &str,
+    previous: Vec<Match>,
+    match_: Match,
+) -> anyhow::Result<()> {
+    let games = state.db.get_match_games(match_.id).await?;
+    let team_a_score = games
+        .iter()
+        .filter(|g| g.winner == match_.team_a.id)
+        .count() as i32;
+    let team_b_score = games
+        .iter()
+        .filter(|g| g.winner == match_.team_b.id)
+        .count() as i32;
+
+    let overlay_id = match_.overlay_id;
+    state
+        .db
+        .upsert_match(Match {
+            team_a_score,
+            team_b_score,
+            games,
+            ..match_
+        })
+        .await?;
+
+    if let Some(overlay_id) = overlay_id {
+        let matches = state.db.get_overlay_matches(overlay_id).await?;
+        history::record_change(
+            state,
+            overlay_id,
+            actor,
+            OverlayState::Matches(previous),
+            OverlayState::Matches(matches.clone()),
+        )
+        .await;
+        broadcast_todays_matches(state, overlay_id, &matches)?;
+    }
+    broadcast_standings(state, tournament_slug).await
+}
+
+async fn get_overlay_match(
+    state: &AppState,
+    overlay_id: Uuid,
+    match_id: Uuid,
+) -> Result<Match, AppError> {
+    let match_ = state.db.get_match(match_id).await?;
+    if match_.overlay_id != Some(overlay_id) {
+        return Err("Partida não pertence a este overlay".into());
+    }
+    Ok(match_)
+}
+
+async fn render_waiting_setup(
+    state: Arc<AppState>,
+    auth_session: &AuthSession,
+    tournament_slug: String,
+    overlay_id: Uuid,
+) -> Result<Html<String>, AppError> {
+    let teams = get_tournament_teams(state.clone(), auth_session, &tournament_slug).await?;
+    let upcoming_matches = state.db.get_overlay_matches(overlay_id).await?;
+    let overlay = state.db.get_overlay(overlay_id).await?;
+
+    Ok(Html(
+        WaitingSetupTemplate {
+            upcoming_matches,
+            teams,
+            tournament_slug,
+            overlay_id,
+            wait_timer: get_wait_timer(state, &overlay_id).await,
+            startgg_sync: overlay.startgg_sync,
+            startgg_report_errors: vec![],
+        }
+        .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn add_match_game(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id, match_id)): Path<(String, Uuid, Uuid)>,
+    auth_session: AuthSession,
+    Form(form): Form<MatchGameForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let match_ = get_overlay_match(&state, overlay_id, match_id).await?;
+
+    if form.winner != match_.team_a.id && form.winner != match_.team_b.id {
+        return Err("O vencedor precisa ser um dos times da partida".into());
+    }
+    let wins = |team_id: &str| match_.games.iter().filter(|g| g.winner == team_id).count() as i32;
+    if match_.games.len() as i32 >= match_.best_of
+        || wins(&match_.team_a.id) >= match_.wins_needed()
+        || wins(&match_.team_b.id) >= match_.wins_needed()
+    {
+        return Err(AppError::from(format!(
+            "A partida já foi decidida, é melhor de {}",
+            match_.best_of
+        ))
+        .with_status(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+    let duration = match form.duration.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(d) => Some(parse_game_duration(d).ok_or("Duração inválida, use o formato m:ss")?),
+    };
+
+    let game = MatchGame {
+        match_id,
+        game_number: match_
+            .games
+            .iter()
+            .map(|g| g.game_number)
+            .max()
+            .unwrap_or_default()
+            + 1,
+        winner: form.winner,
+        team_a_goals: form.team_a_goals,
+        team_b_goals: form.team_b_goals,
+        arena: form.arena.filter(|a| !a.trim().is_empty()),
+        duration,
+    };
+    let previous = state.db.get_overlay_matches(overlay_id).await?;
+    state.db.upsert_match_game(&game).await?;
+
+    update_match_from_games(
+        &state,
+        &tournament_slug,
+        &auth_session.actor(),
+        previous,
+        match_,
+    )
+    .await?;
+
+    render_waiting_setup(state, &auth_session, tournament_slug, overlay_id).await
+}
+
+#[axum::debug_handler]
+pub async fn delete_match_game(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_slug, overlay_id, match_id, game_number)): Path<(String, Uuid, Uuid, i32)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let match_ = get_overlay_match(&state, overlay_id, match_id).await?;
+
+    let previous = state.db.get_overlay_matches(overlay_id).await?;
+    state.db.delete_match_game(match_id, game_number).await?;
+
+    update_match_from_games(
+        &state,
+        &tournament_slug,
+        &auth_session.actor(),
+        previous,
+        match_,
+    )
+    .await?;
+
+    render_waiting_setup(state, &auth_session, tournament_slug, overlay_id).await
+}
@@ -0,0 +1,545 @@
+//! JSON websocket protocol for custom overlays, served at `/stream_overlay/{id}/overlay-ws?v=1`.
+//!
+//! Every server message is `{"v": 1, "id": <event id>, "type": <type>, "data": <payload>}`.
+//! After connecting the client gets `hello`, the current state of every topic and then each
+//! update as it happens. Updates are typed by their topic: `scoreboard`, `casters`, `timer`,
+//! `matches` and `theme`.
+//!
+//! Client messages:
+//! - `{"type": "subscribe", "topics": ["scoreboard", "timer"]}` only receives these topics from
+//!   now on, their current state is sent again
+//! - `{"type": "ping", "nonce": "abc"}` is answered with a `pong` with the same nonce
+//! - `{"type": "ack", "id": 123}` acknowledges the last applied update
+//!
+//! A client reconnecting with `&last_event_id=<id>` only receives the updates it missed.
+//! Reconnecting with `&session=<session>` from the `hello` does the same from the last
+//! acknowledged update.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::ws::{self, WebSocket};
+use chrono::{DateTime, FixedOffset};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::database::casters::Caster;
+use crate::database::matches::Match;
+use crate::database::scoreboard::Scoreboard;
+use crate::database::theme::Theme;
+use crate::database::wait_timer::{WaitTimer, WaitType};
+use crate::routes::hub::HubMessage;
+use crate::routes::sse::{SSEDestination, SSEvent, SSEventType};
+use crate::routes::AppState;
+use crate::startgg::tournaments::StartGGTeam;
+
+use super::snapshot::websocket_snapshot;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How long a closed session can be resumed
+const SESSION_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Last update acknowledged in each closed session of an overlay
+#[derive(Debug, Default)]
+pub struct WebsocketSessions {
+    acked: Mutex<HashMap<(Uuid, Uuid), (u64, Instant)>>,
+}
+
+impl WebsocketSessions {
+    fn close(&self, overlay_id: Uuid, session: Uuid, last_acked: u64) {
+        let mut acked = self.acked.lock().unwrap();
+        acked.retain(|_, (_, closed_at)| closed_at.elapsed() < SESSION_TTL);
+        acked.insert((overlay_id, session), (last_acked, Instant::now()));
+    }
+
+    fn resume(&self, overlay_id: Uuid, session: Uuid) -> Option<u64> {
+        self.acked
+            .lock()
+            .unwrap()
+            .remove(&(overlay_id, session))
+            .filter(|(_, closed_at)| closed_at.elapsed() < SESSION_TTL)
+            .map(|(id, _)| id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    Scoreboard,
+    Casters,
+    Timer,
+    Matches,
+    Theme,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 5] = [
+        Topic::Scoreboard,
+        Topic::Casters,
+        Topic::Timer,
+        Topic::Matches,
+        Topic::Theme,
+    ];
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TeamState {
+    pub id: String,
+    pub name: String,
+    pub nickname: Option<String>,
+    pub image: Option<String>,
+}
+
+impl From<&StartGGTeam> for TeamState {
+    fn from(team: &StartGGTeam) -> Self {
+        Self {
+            id: team.id.clone(),
+            name: team.name.clone(),
+            nickname: team.nickname.clone(),
+            image: team.image.as_ref().map(|i| i.url.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreboardState {
+    pub team_a: TeamState,
+    pub team_b: TeamState,
+    pub team_a_score: i32,
+    pub team_b_score: i32,
+    pub team_a_standing: String,
+    pub team_b_standing: String,
+    pub best_of: i32,
+    pub championship_phase: Option<String>,
+    pub logo: String,
+    /// Team A is shown on the right
+    pub swap_sides: bool,
+    pub sides_swapped: bool,
+}
+
+impl ScoreboardState {
+    pub fn new(
+        scoreboard: &Scoreboard,
+        team_a: &StartGGTeam,
+        team_b: &StartGGTeam,
+        theme_swap_sides: bool,
+    ) -> Self {
+        Self {
+            team_a: team_a.into(),
+            team_b: team_b.into(),
+            team_a_score: scoreboard.team_a_score,
+            team_b_score: scoreboard.team_b_score,
+            team_a_standing: scoreboard.team_a_standing.clone(),
+            team_b_standing: scoreboard.team_b_standing.clone(),
+            best_of: scoreboard.best_of,
+            championship_phase: scoreboard.championship_phase.clone(),
+            logo: scoreboard.logo.clone(),
+            swap_sides: scoreboard.swap_sides(theme_swap_sides),
+            sides_swapped: scoreboard.sides_swapped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CasterState {
+    pub name: String,
+    #[serde(default)]
+    pub role: String,
+    pub stream_video: String,
+    pub image: Option<String>,
+    pub handle: Option<String>,
+    pub pronouns: Option<String>,
+    #[serde(default)]
+    pub push_id: Option<String>,
+    #[serde(default)]
+    pub talent_id: Option<Uuid>,
+}
+
+/// Casters in the order they are shown
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CastersState {
+    pub casters: Vec<CasterState>,
+    /// First caster, kept for clients of the two caster version
+    pub narrator: Option<CasterState>,
+    /// Second caster, kept for clients of the two caster version
+    pub commenter: Option<CasterState>,
+}
+
+impl From<&Caster> for CasterState {
+    fn from(caster: &Caster) -> Self {
+        Self {
+            name: caster.name.clone(),
+            role: caster.role.clone(),
+            stream_video: caster.stream_video.clone(),
+            image: caster.image.clone(),
+            handle: caster.handle.clone(),
+            pronouns: caster.pronouns.clone(),
+            push_id: caster.push_id.clone(),
+            talent_id: caster.talent_id,
+        }
+    }
+}
+
+impl From<&[Caster]> for CastersState {
+    fn from(casters: &[Caster]) -> Self {
+        let casters = casters.iter().map(CasterState::from).collect::<Vec<_>>();
+        Self {
+            narrator: casters.first().cloned(),
+            commenter: casters.get(1).cloned(),
+            casters,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimerState {
+    pub wait_until: Option<DateTime<FixedOffset>>,
+    pub wait_type: Option<WaitType>,
+}
+
+impl From<&Option<WaitTimer>> for TimerState {
+    fn from(wait_timer: &Option<WaitTimer>) -> Self {
+        Self {
+            wait_until: wait_timer.as_ref().map(|t| t.wait_until),
+            wait_type: wait_timer.as_ref().map(|t| t.wait_type.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchState {
+    pub id: Uuid,
+    pub team_a: TeamState,
+    pub team_b: TeamState,
+    pub team_a_score: i32,
+    pub team_b_score: i32,
+    pub best_of: i32,
+    pub completed: bool,
+    pub in_progress: bool,
+    pub featured: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchesState {
+    pub matches: Vec<MatchState>,
+}
+
+impl From<&[Match]> for MatchesState {
+    fn from(matches: &[Match]) -> Self {
+        Self {
+            matches: matches
+                .iter()
+                .map(|m| MatchState {
+                    id: m.id,
+                    team_a: (&m.team_a).into(),
+                    team_b: (&m.team_b).into(),
+                    team_a_score: m.team_a_score,
+                    team_b_score: m.team_b_score,
+                    best_of: m.best_of,
+                    completed: m.completed,
+                    in_progress: m.in_progress,
+                    featured: m.featured,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// State updates, published to the hub as [`SSEventType::WebsocketEvent`]s
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum OverlayUpdate {
+    Scoreboard(ScoreboardState),
+    Casters(CastersState),
+    Timer(TimerState),
+    Matches(MatchesState),
+    Theme(Theme),
+}
+
+impl OverlayUpdate {
+    pub fn event(&self, overlay_id: Uuid) -> anyhow::Result<SSEvent> {
+        Ok(SSEvent::overlay(
+            overlay_id,
+            SSEventType::WebsocketEvent,
+            serde_json::to_string(self)?,
+        ))
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+enum ControlMessage {
+    Hello {
+        overlay_id: Uuid,
+        session: Uuid,
+        topics: Vec<Topic>,
+    },
+    Subscribed {
+        topics: Vec<Topic>,
+    },
+    Pong {
+        nonce: Option<String>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct Envelope<T: Serialize> {
+    v: u32,
+    id: Option<u64>,
+    #[serde(flatten)]
+    message: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe { topics: Vec<Topic> },
+    Ping { nonce: Option<String> },
+    Ack { id: u64 },
+}
+
+fn envelope<T: Serialize>(id: Option<u64>, message: T) -> Option<ws::Message> {
+    let envelope = Envelope {
+        v: PROTOCOL_VERSION,
+        id,
+        message,
+    };
+    serde_json::to_string(&envelope)
+        .inspect_err(|e| tracing::error!("Failed to serialize websocket message: {}", e))
+        .ok()
+        .map(|text| ws::Message::Text(text.into()))
+}
+
+/// Topic of a hub event, `None` for the events that aren't websocket updates
+fn update_topic(event: &SSEvent) -> Option<(Topic, Value)> {
+    if event.event != SSEventType::WebsocketEvent {
+        return None;
+    }
+    let update: Value = serde_json::from_str(&event.data).ok()?;
+    let topic = serde_json::from_value(update["type"].clone()).ok()?;
+    Some((topic, update))
+}
+
+struct Connection {
+    overlay_id: Uuid,
+    topics: HashSet<Topic>,
+    last_acked: Option<u64>,
+}
+
+impl Connection {
+    fn sorted_topics(&self) -> Vec<Topic> {
+        Topic::ALL
+            .into_iter()
+            .filter(|t| self.topics.contains(t))
+            .collect()
+    }
+
+    fn update_message(&self, event: &SSEvent) -> Option<ws::Message> {
+        let (topic, update) = update_topic(event)?;
+        if !self.topics.contains(&topic) {
+            return None;
+        }
+        envelope(event.id, update)
+    }
+
+    /// Handles a client message, returning the replies
+    async fn handle(&mut self, state: &AppState, text: &str) -> Vec<ws::Message> {
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(e) => {
+                return envelope(
+                    None,
+                    ControlMessage::Error {
+                        message: format!("invalid message: {}", e),
+                    },
+                )
+                .into_iter()
+                .collect();
+            }
+        };
+
+        match message {
+            ClientMessage::Subscribe { topics } => {
+                self.topics = topics.into_iter().collect();
+                let subscribed = ControlMessage::Subscribed {
+                    topics: self.sorted_topics(),
+                };
+
+                let id = state.events.last_id();
+                envelope(None, subscribed)
+                    .into_iter()
+                    .chain(
+                        websocket_snapshot(state, self.overlay_id)
+                            .await
+                            .iter()
+                            .filter_map(|event| {
+                                self.update_message(&SSEvent {
+                                    id: Some(id),
+                                    ..event.clone()
+                                })
+                            }),
+                    )
+                    .collect()
+            }
+            ClientMessage::Ping { nonce } => envelope(None, ControlMessage::Pong { nonce })
+                .into_iter()
+                .collect(),
+            ClientMessage::Ack { id } => {
+                self.last_acked = Some(id);
+                vec![]
+            }
+        }
+    }
+}
+
+pub async fn handle_websocket(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    overlay_id: Uuid,
+    conn_id: Uuid,
+    session: Option<Uuid>,
+    last_event_id: Option<u64>,
+) {
+    let session = session.unwrap_or(conn_id);
+    let last_event_id =
+        last_event_id.or_else(|| state.websocket_sessions.resume(overlay_id, session));
+
+    let (mut sender, mut receiver) = socket.split();
+    let mut connection = Connection {
+        overlay_id,
+        topics: Topic::ALL.into_iter().collect(),
+        last_acked: None,
+    };
+    let mut events = state
+        .events
+        .subscribe(SSEDestination::Overlay(overlay_id), last_event_id);
+
+    let hello = ControlMessage::Hello {
+        overlay_id,
+        session,
+        topics: connection.sorted_topics(),
+    };
+    if let Some(hello) = envelope(None, hello) {
+        if sender.send(hello).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        let messages = tokio::select! {
+            message = events.next() => match message {
+                Some(HubMessage::Event(event)) => connection.update_message(&event).into_iter().collect(),
+                Some(HubMessage::Resync(id)) => websocket_snapshot(&state, overlay_id)
+                    .await
+                    .iter()
+                    .filter_map(|event| connection.update_message(&SSEvent { id: Some(id), ..event.clone() }))
+                    .collect(),
+                None => break,
+            },
+            message = receiver.next() => match message {
+                Some(Ok(ws::Message::Text(text))) => connection.handle(&state, text.as_str()).await,
+                Some(Ok(ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum
+                Some(Ok(_)) => vec![],
+            },
+        };
+
+        for message in messages {
+            if let Err(e) = sender.send(message).await {
+                tracing::error!("Failed to send websocket message for {}: {}", conn_id, e);
+                return;
+            }
+        }
+    }
+
+    tracing::debug!(
+        "Websocket {} closed, last acknowledged event: {:?}",
+        conn_id,
+        connection.last_acked
+    );
+    if let Some(last_acked) = connection.last_acked {
+        state
+            .websocket_sessions
+            .close(overlay_id, session, last_acked);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_client_messages() {
+        let message: ClientMessage =
+            serde_json::from_str(r#"{"type": "subscribe", "topics": ["scoreboard", "timer"]}"#)
+                .unwrap();
+        assert!(matches!(
+            message,
+            ClientMessage::Subscribe { topics } if topics == vec![Topic::Scoreboard, Topic::Timer]
+        ));
+
+        let message: ClientMessage = serde_json::from_str(r#"{"type": "ping"}"#).unwrap();
+        assert!(matches!(message, ClientMessage::Ping { nonce: None }));
+
+        let message: ClientMessage = serde_json::from_str(r#"{"type": "ack", "id": 42}"#).unwrap();
+        assert!(matches!(message, ClientMessage::Ack { id: 42 }));
+
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"type": "unknown"}"#).is_err());
+    }
+
+    #[test]
+    fn test_resume_session() {
+        let sessions = WebsocketSessions::default();
+        let (overlay_id, session) = (Uuid::new_v4(), Uuid::new_v4());
+        sessions.close(overlay_id, session, 42);
+
+        assert_eq!(sessions.resume(Uuid::new_v4(), session), None);
+        assert_eq!(sessions.resume(overlay_id, session), Some(42));
+        assert_eq!(sessions.resume(overlay_id, session), None);
+    }
+
+    #[test]
+    fn test_update_envelope() {
+        let overlay_id = Uuid::new_v4();
+        let update = OverlayUpdate::Timer(TimerState {
+            wait_until: None,
+            wait_type: Some(WaitType::Break),
+        });
+        let event = SSEvent {
+            id: Some(7),
+            ..update.event(overlay_id).unwrap()
+        };
+
+        let connection = Connection {
+            overlay_id,
+            topics: [Topic::Timer].into_iter().collect(),
+            last_acked: None,
+        };
+        let Some(ws::Message::Text(text)) = connection.update_message(&event) else {
+            panic!("timer update not sent");
+        };
+        let message: Value = serde_json::from_str(text.as_str()).unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({
+                "v": 1,
+                "id": 7,
+                "type": "timer",
+                "data": { "wait_until": null, "wait_type": "break" },
+            })
+        );
+
+        let connection = Connection {
+            topics: [Topic::Scoreboard].into_iter().collect(),
+            ..connection
+        };
+        assert!(connection.update_message(&event).is_none());
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse};
+use axum::Form;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::organizations::Organization;
+use crate::database::talent::{Talent, TalentAppearance};
+use crate::startgg::auth::AuthSession;
+use crate::startgg::oauth::StartggUser;
+
+use super::assets::FOREIGN_IMAGE;
+use super::error::AppError;
+use super::stream_overlay::casters::new_push_id;
+use super::views::filters;
+use super::AppState;
+
+#[derive(Template)]
+#[template(path = "talent.html", blocks = ["talent_list"])]
+pub struct TalentTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub organization: Organization,
+    pub talent: Vec<Talent>,
+    pub error: Option<String>,
+}
+
+impl TalentTemplate {
+    async fn new(state: &AppState, organization_id: Uuid) -> Result<Self, AppError> {
+        Ok(TalentTemplate {
+            maybe_user: None,
+            organization: state.db.get_organization(organization_id).await?,
+            talent: state.db.get_organization_talent(organization_id).await?,
+            error: None,
+        })
+    }
+}
+
+#[axum::debug_handler]
+pub async fn talent_handler(
+    State(state): State<Arc<AppState>>,
+    Path(organization_id): Path<Uuid>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let template = TalentTemplate::new(&state, organization_id).await?;
+
+    Ok(Html(
+        TalentTemplate {
+            maybe_user: Some(auth_session.user),
+            ..template
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TalentForm {
+    name: String,
+    #[serde(default)]
+    handle: String,
+    #[serde(default)]
+    pronouns: String,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    push_id: String,
+}
+
+impl TalentForm {
+    /// Talent with the fields of the form, `None` without a name
+    fn talent(self, organization_id: Uuid, id: Uuid) -> anyhow::Result<Option<Talent>> {
+        let optional = |value: String| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+
+        let name = self.name.trim().to_string();
+        if name.is_empty() {
+            return Ok(None);
+        }
+        // VDO.ninja only keeps letters, digits and underscores of the push ID
+        let push_id = self
+            .push_id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect::<String>();
+        let push_id = if push_id.is_empty() {
+            new_push_id()?
+        } else {
+            push_id
+        };
+
+        Ok(Some(Talent {
+            id,
+            organization_id,
+            name,
+            handle: optional(self.handle),
+            pronouns: optional(self.pronouns),
+            image: optional(self.image),
+            push_id,
+        }))
+    }
+}
+
+/// Photos can be uploaded to any tournament of the organization
+async fn is_organization_image(state: &AppState, talent: &Talent) -> anyhow::Result<bool> {
+    match &talent.image {
+        Some(image) => {
+            state
+                .db
+                .is_organization_image(talent.organization_id, image)
+                .await
+        }
+        None => Ok(true),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_talent(
+    State(state): State<Arc<AppState>>,
+    Path(organization_id): Path<Uuid>,
+    auth_session: AuthSession,
+    Form(form): Form<TalentForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut error = None;
+
+    match form.talent(organization_id, Uuid::new_v4())? {
+        Some(talent) if !is_organization_image(&state, &talent).await? => {
+            error = Some(FOREIGN_IMAGE.to_string())
+        }
+        Some(talent) => {
+            state.db.insert_talent(&talent).await?;
+            tracing::info!(
+                "{} added {} to the talent of organization {}",
+                auth_session.actor(),
+                talent.name,
+                organization_id
+            );
+        }
+        None => error = Some("O nome é obrigatório".to_string()),
+    }
+
+    let template = TalentTemplate::new(&state, organization_id).await?;
+    Ok(Html(
+        TalentTemplate { error, ..template }
+            .as_talent_list()
+            .render()?,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn update_talent(
+    State(state): State<Arc<AppState>>,
+    Path((organization_id, talent_id)): Path<(Uuid, Uuid)>,
+    Form(form): Form<TalentForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut error = None;
+
+    match form.talent(organization_id, talent_id)? {
+        Some(talent) if !is_organization_image(&state, &talent).await? => {
+            error = Some(FOREIGN_IMAGE.to_string())
+        }
+        Some(talent) => state.db.update_talent(&talent).await?,
+        None => error = Some("O nome é obrigatório".to_string()),
+    }
+
+    let template = TalentTemplate::new(&state, organization_id).await?;
+    Ok(Html(
+        TalentTemplate { error, ..template }
+            .as_talent_list()
+            .render()?,
+    ))
+}
+
+/// Casters picked from the directory stay in the overlays, only without their history
+#[axum::debug_handler]
+pub async fn delete_talent(
+    State(state): State<Arc<AppState>>,
+    Path((organization_id, talent_id)): Path<(Uuid, Uuid)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    state.db.delete_talent(organization_id, talent_id).await?;
+    tracing::info!(
+        "{} removed talent {} from organization {}",
+        auth_session.actor(),
+        talent_id,
+        organization_id
+    );
+
+    let template = TalentTemplate::new(&state, organization_id).await?;
+    let html = template.as_talent_list().render()?;
+    Ok(Html(html))
+}
+
+#[derive(Template)]
+#[template(path = "talent_history.html")]
+pub struct TalentHistoryTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub organization: Organization,
+    pub talent: Talent,
+    pub appearances: Vec<TalentAppearance>,
+}
+
+impl TalentHistoryTemplate {
+    /// Days with at least one broadcast, what freelancers are usually paid by
+    fn days_worked(&self) -> usize {
+        let mut days = self.appearances.iter().map(|a| a.day).collect::<Vec<_>>();
+        days.dedup();
+        days.len()
+    }
+}
+
+#[axum::debug_handler]
+pub async fn talent_history_handler(
+    State(state): State<Arc<AppState>>,
+    Path((organization_id, talent_id)): Path<(Uuid, Uuid)>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let talent = state.db.get_talent(organization_id, talent_id).await?;
+
+    Ok(Html(
+        TalentHistoryTemplate {
+            maybe_user: Some(auth_session.user),
+            organization: state.db.get_organization(organization_id).await?,
+            appearances: state.db.get_talent_appearances(talent.id).await?,
+            talent,
+        }
+        .render()?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn form(name: &str, push_id: &str) -> TalentForm {
+        TalentForm {
+            name: name.to_string(),
+            handle: " ".to_string(),
+            pronouns: "ela/dela".to_string(),
+            image: String::new(),
+            push_id: push_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_talent_form() {
+        let organization_id = Uuid::new_v4();
+
+        let talent = form(" Ana ", "ana-casts!")
+            .talent(organization_id, Uuid::new_v4())
+            .unwrap()
+            .unwrap();
+        assert_eq!(talent.name, "Ana");
+        assert_eq!(talent.handle, None);
+        assert_eq!(talent.pronouns.as_deref(), Some("ela/dela"));
+        assert_eq!(talent.push_id, "anacasts");
+
+        let talent = form("Bia", "")
+            .talent(organization_id, Uuid::new_v4())
+            .unwrap()
+            .unwrap();
+        assert!(!talent.push_id.is_empty());
+
+        assert!(form("  ", "bia")
+            .talent(organization_id, Uuid::new_v4())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -7,16 +7,24 @@ use axum::response::{Html, IntoResponse, Response};
 use axum::Form;
 use axum_htmx::HxRequest;
 use futures_util::future::join_all;
+use reqwest::StatusCode;
 use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::database::casters::Caster;
+use crate::database::casters::{Caster, CASTER_ROLES};
 use crate::database::overlay::Overlay;
 use crate::database::scoreboard::Scoreboard;
+use crate::database::standings::TeamStanding;
+use crate::database::talent::Talent;
+use crate::database::theme::Theme;
+use crate::database::vdo::VdoRoom;
 use crate::startgg::auth::AuthSession;
-use crate::startgg::StartGGClient;
 
+use super::assets::FOREIGN_IMAGE;
 use super::error::AppError;
+use super::organizations;
+use super::stream_overlay::casters::{vdo_room, MAX_CASTERS};
+use super::stream_overlay::websocket::OverlayUpdate;
 use super::AppState;
 
 use crate::startgg::oauth::StartggUser;
@@ -37,9 +45,25 @@ pub async fn tournaments_handler(
     auth_session: AuthSession,
 ) -> Result<impl IntoResponse, AppError> {
     // If AuthSession exists, try to get user data for display
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
+    let startgg_client = state.startgg_client(&auth_session.access_token);
     let user = startgg_client.fetch_startgg_user().await?;
-    let tournaments = startgg_client.fetch_tournaments_organized_by_user().await?;
+    let mut tournaments = startgg_client.fetch_tournaments_organized_by_user().await?;
+    state.tournament_access.insert(&user.slug, &tournaments);
+
+    // Tournaments the user helps with through an organization, without being a start.gg admin
+    let organization_slugs = state.db.get_user_tournaments(&user.slug).await?;
+    let organization_tournaments = join_all(
+        organization_slugs
+            .into_iter()
+            .filter(|slug| !tournaments.iter().any(|t| t.slug == *slug))
+            .map(|slug| startgg_client.fetch_tournament(slug)),
+    )
+    .await;
+    tournaments.extend(
+        organization_tournaments
+            .into_iter()
+            .filter_map(|t| t.inspect_err(|e| tracing::warn!("{}", e)).ok()),
+    );
 
     Ok(Html(
         TournamentsTemplate {
@@ -78,7 +102,7 @@ pub async fn tournament_setup(
     HxRequest(is_hx_request): HxRequest,
     auth_session: AuthSession,
 ) -> Result<impl IntoResponse, AppError> {
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
+    let startgg_client = state.startgg_client(&auth_session.access_token);
     let user = startgg_client.fetch_startgg_user().await?;
     let tournament = startgg_client
         .fetch_tournament(tournament_slug.to_string())
@@ -134,7 +158,7 @@ pub async fn create_overlay(
 ) -> Result<impl IntoResponse, AppError> {
     tracing::info!("Handling create_overlay");
 
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
+    let startgg_client = state.startgg_client(&auth_session.access_token);
     let tournament = startgg_client
         .fetch_tournament(tournament_slug.to_string())
         .await?;
@@ -144,7 +168,8 @@ pub async fn create_overlay(
     let overlays = state.db.get_tournament_overlays(&tournament.slug).await?;
 
     let selected_overlay = match query.overlay {
-        Some(id) => Some(state.db.get_overlay(id).await?),
+        Some(id) => Some(state.db.get_overlay(id).await?)
+            .filter(|overlay| overlay.tournament_slug == tournament.slug),
         None => None,
     };
 
@@ -170,7 +195,7 @@ pub async fn update_overlay(
     auth_session: AuthSession,
     Form(data): Form<UpdateOverlayForm>,
 ) -> Result<impl IntoResponse, AppError> {
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
+    let startgg_client = state.startgg_client(&auth_session.access_token);
     let tournament = startgg_client
         .fetch_tournament(tournament_slug.to_string())
         .await?;
@@ -203,7 +228,7 @@ pub async fn delete_overlay(
     Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
     auth_session: AuthSession,
 ) -> Result<impl IntoResponse, AppError> {
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
+    let startgg_client = state.startgg_client(&auth_session.access_token);
     let tournament = startgg_client
         .fetch_tournament(tournament_slug.to_string())
         .await?;
@@ -229,6 +254,7 @@ pub struct TeamsSetup {
     pub overlay_id: Uuid,
     pub teams: Vec<StartGGTeam>,
     pub selected_teams: Option<(StartGGTeam, StartGGTeam, Scoreboard)>,
+    pub startgg_report: Option<Result<(), String>>,
 }
 
 #[axum::debug_handler]
@@ -255,6 +281,7 @@ pub async fn team_setup_handler(
             overlay_id,
             tournament_slug,
             selected_teams,
+            startgg_report: None,
         }
         .render()?,
     ))
@@ -265,7 +292,7 @@ pub async fn get_tournament_teams(
     auth_session: &AuthSession,
     tournament_slug: &str,
 ) -> anyhow::Result<Vec<StartGGTeam>> {
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
+    let startgg_client = state.startgg_client(&auth_session.access_token);
 
     let teams = startgg_client
         .fetch_tournament_teams(tournament_slug.to_string())
@@ -328,6 +355,7 @@ pub async fn update_team_nickname(
             overlay_id,
             teams,
             selected_teams,
+            startgg_report: None,
         }
         .render()?,
     ))
@@ -345,6 +373,13 @@ pub async fn update_team_image(
     Path((tournament_slug, overlay_id)): Path<(String, Uuid)>,
     Form(form): Form<UpdateTeamImageForm>,
 ) -> Result<impl IntoResponse, AppError> {
+    if !state
+        .db
+        .is_tournament_image(&tournament_slug, &form.team_image)
+        .await?
+    {
+        return Err(FOREIGN_IMAGE.into());
+    }
     let team = state.db.get_team(&form.team).await?;
 
     let t = StartGGTeam {
@@ -376,7 +411,188 @@ pub async fn update_team_image(
             overlay_id,
             teams,
             selected_teams,
+            startgg_report: None,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "standings.html")]
+pub struct StandingsTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub tournament: StartGGTournament,
+    pub standings: Vec<TeamStanding>,
+}
+
+#[axum::debug_handler]
+pub async fn standings_handler(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let user = startgg_client.fetch_startgg_user().await?;
+    let tournament = startgg_client.fetch_tournament(tournament_slug).await?;
+
+    let standings = state.db.get_tournament_standings(&tournament.slug).await?;
+
+    Ok(Html(
+        StandingsTemplate {
+            maybe_user: Some(user),
+            tournament,
+            standings,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Template)]
+#[template(path = "theme_setup.html", blocks = ["theme_form"])]
+pub struct ThemeSetupTemplate {
+    pub maybe_user: Option<StartggUser>,
+    pub tournament: StartGGTournament,
+    pub theme: Theme,
+    pub saved: bool,
+}
+
+#[axum::debug_handler]
+pub async fn theme_handler(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    auth_session: AuthSession,
+) -> Result<impl IntoResponse, AppError> {
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let user = startgg_client.fetch_startgg_user().await?;
+    let tournament = startgg_client.fetch_tournament(tournament_slug).await?;
+
+    let theme = state.db.get_theme(&tournament.slug).await?;
+
+    Ok(Html(
+        ThemeSetupTemplate {
+            maybe_user: Some(user),
+            tournament,
+            theme,
+            saved: false,
+        }
+        .render()?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateThemeForm {
+    primary_color: String,
+    secondary_color: String,
+    background_color: String,
+    panel_color: String,
+    surface_color: String,
+    text_color: String,
+    team_a_color: String,
+    team_b_color: String,
+    font_family: String,
+    logo: String,
+    background_image: String,
+    #[serde(default)]
+    swap_scoreboard_sides: bool,
+}
+
+/// Theme values end up in the overlays' stylesheet, so only plain values are accepted
+fn validate_theme(theme: &Theme) -> Result<(), AppError> {
+    let is_color = |c: &str| {
+        c.len() == 7 && c.starts_with('#') && c[1..].chars().all(|c| c.is_ascii_hexdigit())
+    };
+    let is_url = |u: &str| {
+        !u.is_empty()
+            && !u
+                .chars()
+                .any(|c| c.is_whitespace() || "'\"()\\<>".contains(c))
+    };
+
+    if ![
+        &theme.primary_color,
+        &theme.secondary_color,
+        &theme.background_color,
+        &theme.panel_color,
+        &theme.surface_color,
+        &theme.text_color,
+        &theme.team_a_color,
+        &theme.team_b_color,
+    ]
+    .iter()
+    .all(|c| is_color(c))
+    {
+        return Err("Cor inválida, use o formato #RRGGBB".into());
+    }
+    if theme.font_family.trim().is_empty()
+        || !theme
+            .font_family
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == ' ')
+    {
+        return Err("Nome de fonte inválido".into());
+    }
+    if !is_url(&theme.logo) || !is_url(&theme.background_image) {
+        return Err("Endereço de imagem inválido".into());
+    }
+
+    Ok(())
+}
+
+#[axum::debug_handler]
+pub async fn update_theme(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_slug): Path<String>,
+    auth_session: AuthSession,
+    Form(form): Form<UpdateThemeForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let startgg_client = state.startgg_client(&auth_session.access_token);
+    let tournament = startgg_client.fetch_tournament(tournament_slug).await?;
+
+    let theme = Theme {
+        tournament_slug: tournament.slug.clone(),
+        primary_color: form.primary_color,
+        secondary_color: form.secondary_color,
+        background_color: form.background_color,
+        panel_color: form.panel_color,
+        surface_color: form.surface_color,
+        text_color: form.text_color,
+        team_a_color: form.team_a_color,
+        team_b_color: form.team_b_color,
+        font_family: form.font_family.trim().to_string(),
+        logo: form.logo,
+        background_image: form.background_image,
+        swap_scoreboard_sides: form.swap_scoreboard_sides,
+    };
+    validate_theme(&theme)?;
+    for image in [&theme.logo, &theme.background_image] {
+        if !state
+            .db
+            .is_tournament_image(&theme.tournament_slug, image)
+            .await?
+        {
+            return Err(FOREIGN_IMAGE.into());
         }
+    }
+
+    state.db.upsert_theme(&theme).await?;
+    for overlay in state
+        .db
+        .get_tournament_overlays(&theme.tournament_slug)
+        .await?
+    {
+        state
+            .events
+            .publish(OverlayUpdate::Theme(theme.clone()).event(overlay.id)?);
+    }
+
+    Ok(Html(
+        ThemeSetupTemplate {
+            maybe_user: None,
+            tournament,
+            theme,
+            saved: true,
+        }
+        .as_theme_form()
         .render()?,
     ))
 }
@@ -386,7 +602,40 @@ pub async fn update_team_image(
 pub struct CastersSetup {
     pub tournament_slug: String,
     pub overlay: Overlay,
-    pub casters: Option<(Caster, Caster)>,
+    pub casters: Vec<Caster>,
+    pub room: VdoRoom,
+    /// Directory of the organization that manages the tournament, to pick casters from
+    pub organization_id: Option<Uuid>,
+    pub talent: Vec<Talent>,
+}
+
+impl CastersSetup {
+    pub async fn new(
+        state: &AppState,
+        tournament_slug: String,
+        overlay: Overlay,
+        casters: Vec<Caster>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            room: vdo_room(state, overlay.id).await?,
+            organization_id: state
+                .db
+                .get_tournament_organization(&tournament_slug)
+                .await?,
+            talent: state.db.get_tournament_talent(&tournament_slug).await?,
+            tournament_slug,
+            overlay,
+            casters,
+        })
+    }
+
+    fn roles(&self) -> [&'static str; 5] {
+        CASTER_ROLES
+    }
+
+    fn max_casters(&self) -> usize {
+        MAX_CASTERS
+    }
 }
 
 #[axum::debug_handler]
@@ -397,20 +646,12 @@ pub async fn casters_handler(
 ) -> Result<impl IntoResponse, AppError> {
     let overlay = state.db.get_overlay(overlay_id).await?;
 
-    let db_casters = state.db.get_casters(&overlay_id).await?;
-    let casters = if db_casters.len() == 2 {
-        Some((db_casters[0].clone(), db_casters[1].clone()))
-    } else {
-        None
-    };
+    let casters = state.db.get_casters(&overlay_id).await?;
 
     Ok(Html(
-        CastersSetup {
-            overlay,
-            casters,
-            tournament_slug,
-        }
-        .render()?,
+        CastersSetup::new(&state, tournament_slug, overlay, casters)
+            .await?
+            .render()?,
     ))
 }
 
@@ -419,29 +660,55 @@ pub struct TournamentSlugPathExtractor {
     tournament_slug: String,
 }
 
+/// Lets members of the tournament through, with their role in the request extensions
+/// for the permission checks of each route
 pub async fn tournament_access_middleware(
     State(state): State<Arc<AppState>>,
     Path(path_extractor): Path<TournamentSlugPathExtractor>,
     auth_session: AuthSession,
-    req: Request<axum::body::Body>,
+    mut req: Request<axum::body::Body>,
     next: Next,
 ) -> Result<Response, AppError> {
-    let startgg_client = StartGGClient::new(&state.http_client, &auth_session.access_token);
+    let role =
+        organizations::tournament_role(&state, &auth_session, &path_extractor.tournament_slug)
+            .await?
+            .ok_or(
+                AppError::from(format!(
+                    "user is not authorized to manage tournament {}",
+                    path_extractor.tournament_slug
+                ))
+                .with_unauthorized(),
+            )?;
+
+    req.extensions_mut().insert(role);
+    let res = next.run(req).await;
+    Ok(res)
+}
 
-    let user_tournaments = startgg_client
-        .fetch_tournaments_organized_by_user()
-        .await?
-        .into_iter()
-        .find(|t| t.slug == path_extractor.tournament_slug);
+#[derive(Debug, Clone, Deserialize)]
+pub struct OverlayPathExtractor {
+    tournament_slug: String,
+    overlay_id: Uuid,
+}
 
-    user_tournaments.ok_or(
-        AppError::from(format!(
-            "user is not authorized to manage tournament {}",
-            path_extractor.tournament_slug
-        ))
-        .with_unauthorized(),
-    )?;
+/// Lets through only requests for overlays of the tournament in the path,
+/// with the overlay in the request extensions
+pub async fn overlay_access_middleware(
+    State(state): State<Arc<AppState>>,
+    Path(path_extractor): Path<OverlayPathExtractor>,
+    mut req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let overlay = state
+        .db
+        .get_overlay(path_extractor.overlay_id)
+        .await
+        .map_err(|_| AppError::from("Overlay não encontrado").with_status(StatusCode::NOT_FOUND))?;
+    if overlay.tournament_slug != path_extractor.tournament_slug {
+        return Err(AppError::from("Overlay não pertence a este campeonato")
+            .with_status(StatusCode::FORBIDDEN));
+    }
 
-    let res = next.run(req).await;
-    Ok(res)
+    req.extensions_mut().insert(overlay);
+    Ok(next.run(req).await)
 }
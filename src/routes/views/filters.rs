@@ -1,5 +1,3 @@
-use uuid::Uuid;
-
 use crate::database::matches::Match;
 use crate::startgg::oauth::StartggUser;
 use crate::startgg::tournaments::{StartGGImage, StartGGTeam};
@@ -55,40 +53,6 @@ pub fn team_full_name(team: &StartGGTeam, _values: &dyn askama::Values) -> askam
     })
 }
 
-fn uuid_to_vdo_id(uuid: &Uuid) -> askama::Result<String> {
-    let mut uuid_str = uuid.simple().to_string();
-    uuid_str.remove(12); // UUIDv4 version number
-    uuid_str.remove(15); // UUIDv4 variant
-    Ok(uuid_str)
-}
-
-pub fn vdo_invite_link(uuid: &Uuid, kind: &str) -> askama::Result<String> {
-    Ok(format!(
-        "https://vdo.ninja/?room={}&vd=0&avatar&push={}",
-        uuid_to_vdo_id(uuid)?,
-        kind
-    ))
-}
-
-pub fn vdo_view_link(
-    uuid: &Uuid,
-    _values: &dyn askama::Values,
-    kind: &str,
-) -> askama::Result<String> {
-    Ok(format!(
-        "https://vdo.ninja/?view={}&solo&room={}",
-        kind,
-        uuid_to_vdo_id(uuid)?
-    ))
-}
-
-pub fn vdo_director_link(uuid: &Uuid, _values: &dyn askama::Values) -> askama::Result<String> {
-    Ok(format!(
-        "https://vdo.ninja/?director={}",
-        uuid_to_vdo_id(uuid)?
-    ))
-}
-
 pub fn duration_text(
     duration: &chrono::Duration,
     _values: &dyn askama::Values,
@@ -118,6 +82,12 @@ pub fn duration_text(
     Ok(Some(result))
 }
 
+/// Time in seconds as hours, minutes and seconds, "0s" when there is none
+pub fn seconds_text(seconds: &i64, values: &dyn askama::Values) -> askama::Result<String> {
+    Ok(duration_text(&chrono::Duration::seconds(*seconds), values)?
+        .unwrap_or_else(|| "0s".to_string()))
+}
+
 pub fn datetime_format(
     dt: &chrono::DateTime<chrono::FixedOffset>,
     _values: &dyn askama::Values,
@@ -125,6 +95,17 @@ pub fn datetime_format(
     Ok(dt.format("%Y-%m-%dT%H:%M:%S").to_string())
 }
 
+/// Series progress of a team as pips, e.g. "●●○" for a team with 2 wins in a best of 5
+pub fn series_pips(
+    wins: &i32,
+    _values: &dyn askama::Values,
+    best_of: &i32,
+) -> askama::Result<String> {
+    let wins_needed = (best_of / 2 + 1).max(1) as usize;
+    let wins = (*wins).clamp(0, wins_needed as i32) as usize;
+    Ok("●".repeat(wins) + &"○".repeat(wins_needed - wins))
+}
+
 pub fn has_featured(matches: &[Match], _values: &dyn askama::Values) -> askama::Result<bool> {
     Ok(matches.iter().any(|m| m.featured))
 }
@@ -2,10 +2,7 @@ use std::sync::Arc;
 
 use crate::{
     routes::{views::ViewError, AppState},
-    startgg::{
-        oauth::{self, TokenResponse},
-        StartGGClient,
-    },
+    startgg::oauth::{self, TokenResponse},
 };
 use anyhow::Context;
 use axum::{
@@ -19,12 +16,23 @@ use chrono::Duration;
 use chrono::{DateTime, Utc};
 use tracing;
 
-use super::oauth::OAuthConfig;
+use super::oauth::{OAuthConfig, StartggUser};
 
 #[derive(Debug, Clone)]
 pub struct AuthSession {
     pub access_token: String,
     pub expires_at: DateTime<Utc>,
+    pub user: StartggUser,
+}
+
+impl AuthSession {
+    /// How the user is identified in the overlays history
+    pub fn actor(&self) -> String {
+        match &self.user.gamer_tag {
+            Some(gamer_tag) => format!("{} ({})", gamer_tag, self.user.slug),
+            None => self.user.slug.clone(),
+        }
+    }
 }
 
 // --- Middleware ---
@@ -112,14 +120,12 @@ pub async fn auth_middleware(
 
     // If after checks/refresh, we have valid auth data, fetch user and insert session
     if let Some((token, expiry)) = auth_data {
-        match StartGGClient::new(&state.http_client, &token)
-            .fetch_startgg_user()
-            .await
-        {
-            Ok(_user) => {
+        match state.startgg_client(&token).fetch_startgg_user().await {
+            Ok(user) => {
                 let session = AuthSession {
                     access_token: token.clone(), // Clone token for the session
                     expires_at: expiry,
+                    user,
                 };
                 // Add session to request extensions for handlers to use
                 req.extensions_mut().insert(session);
@@ -0,0 +1,106 @@
+//! Fake start.gg GraphQL API answering with recorded responses, so the client can be tested
+//! without reaching api.start.gg
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::json;
+
+/// Slug of the tournament in the recorded responses
+pub const TOURNAMENT_SLUG: &str = "copa-omega-brasil";
+
+const TOURNAMENT_RESPONSE: &str = include_str!("fixtures/tournament.json");
+const TOURNAMENT_TEAMS_RESPONSE: &str = include_str!("fixtures/tournament_teams.json");
+
+#[derive(Debug, Default)]
+struct MockState {
+    requests: AtomicUsize,
+    /// Statuses to answer with before going back to the recorded responses
+    failures: Mutex<VecDeque<StatusCode>>,
+}
+
+#[derive(Debug)]
+pub struct MockStartGG {
+    endpoint: String,
+    state: Arc<MockState>,
+}
+
+impl MockStartGG {
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState::default());
+        let router = Router::new()
+            .route("/gql/alpha", post(graphql_handler))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Self {
+            endpoint: format!("http://{address}/gql/alpha"),
+            state,
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Requests received so far, failed ones included
+    pub fn requests(&self) -> usize {
+        self.state.requests.load(Ordering::Relaxed)
+    }
+
+    /// Answers the next `times` requests with `status`
+    pub fn fail_next(&self, status: StatusCode, times: usize) {
+        let mut failures = self.state.failures.lock().unwrap();
+        for _ in 0..times {
+            failures.push_back(status);
+        }
+    }
+}
+
+async fn graphql_handler(
+    State(state): State<Arc<MockState>>,
+    Json(body): Json<serde_json::Value>,
+) -> Response {
+    state.requests.fetch_add(1, Ordering::Relaxed);
+
+    if let Some(status) = state.failures.lock().unwrap().pop_front() {
+        // No waiting around in tests
+        return (
+            status,
+            [(header::RETRY_AFTER, "0")],
+            Json(json!({ "success": false, "message": "mock failure" })),
+        )
+            .into_response();
+    }
+
+    let recorded = match body["operationName"].as_str() {
+        Some("Tournament") => TOURNAMENT_RESPONSE,
+        Some("TournamentTeams") => TOURNAMENT_TEAMS_RESPONSE,
+        operation => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("no recorded response for operation {operation:?}"),
+            )
+                .into_response()
+        }
+    };
+
+    if body["variables"]["tournament"].as_str() != Some(TOURNAMENT_SLUG) {
+        return Json(json!({ "data": { "tournament": null } })).into_response();
+    }
+
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        recorded.to_string(),
+    )
+        .into_response()
+}
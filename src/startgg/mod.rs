@@ -1,24 +1,64 @@
+use std::time::Duration;
+
+use reqwest::{header, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use self::rate_limit::RateLimiter;
+
 pub mod auth;
+#[cfg(test)]
+mod mock;
 pub mod oauth;
+mod rate_limit;
+pub mod sets;
 pub mod tournaments;
 pub mod user;
 
 pub const STARTGG_GRAPHQL_ENDPOINT: &str = "https://api.start.gg/gql/alpha";
 
+/// Attempts for each request before giving up
+const MAX_ATTEMPTS: u32 = 4;
+/// Wait before the first retry, doubled on each attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest wait between attempts, even if start.gg asks for more
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Where the start.gg API is and how much of it has been used, shared between every client
+#[derive(Debug)]
+pub struct StartGGApi {
+    endpoint: String,
+    rate_limiter: RateLimiter,
+}
+
+impl StartGGApi {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            rate_limiter: RateLimiter::default(),
+        }
+    }
+}
+
+impl Default for StartGGApi {
+    fn default() -> Self {
+        Self::new(STARTGG_GRAPHQL_ENDPOINT)
+    }
+}
+
 #[derive(Debug)]
 pub struct StartGGClient<'a> {
     client: &'a reqwest::Client,
+    api: &'a StartGGApi,
     token: &'a str,
 }
 
 impl<'a> StartGGClient<'a> {
-    pub fn new(client: &'a reqwest::Client, token: &'a str) -> Self {
-        Self { client, token }
+    pub fn new(client: &'a reqwest::Client, api: &'a StartGGApi, token: &'a str) -> Self {
+        Self { client, api, token }
     }
 
+    /// Sends a query, retrying when start.gg is rate limiting or unavailable
     async fn graphql_request<Query, ResponseData>(
         &self,
         query: &Query,
@@ -27,35 +67,198 @@ impl<'a> StartGGClient<'a> {
         Query: Serialize + ?Sized,
         ResponseData: DeserializeOwned,
     {
-        let response = self
-            .client
-            .post(STARTGG_GRAPHQL_ENDPOINT)
-            .bearer_auth(self.token)
-            .json(query)
-            .send()
-            .await?;
-        // .context("Failed to send tournament info request to start.gg GraphQL API")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Could not read error body".to_string());
-            tracing::error!("start.gg graphql fetch failed: {} - {}", status, body);
-            return Err(anyhow::anyhow!(
-                "Failed to fetch info. Status: {}. Body: {}",
-                status,
-                body
-            ));
+        self.send_graphql(query, true).await
+    }
+
+    /// Sends a mutation, only retrying when start.gg surely didn't run it
+    async fn graphql_mutation<Query, ResponseData>(
+        &self,
+        query: &Query,
+    ) -> anyhow::Result<graphql_client::Response<ResponseData>>
+    where
+        Query: Serialize + ?Sized,
+        ResponseData: DeserializeOwned,
+    {
+        self.send_graphql(query, false).await
+    }
+
+    async fn send_graphql<Query, ResponseData>(
+        &self,
+        query: &Query,
+        idempotent: bool,
+    ) -> anyhow::Result<graphql_client::Response<ResponseData>>
+    where
+        Query: Serialize + ?Sized,
+        ResponseData: DeserializeOwned,
+    {
+        let mut attempt = 1;
+        loop {
+            self.api.rate_limiter.acquire(self.token).await;
+
+            let response = self
+                .client
+                .post(&self.api.endpoint)
+                .bearer_auth(self.token)
+                .json(query)
+                .send()
+                .await;
+
+            let (error, retry_after) = match response {
+                Ok(response) if response.status().is_success() => {
+                    let resp_text = response.text().await?;
+                    tracing::trace!("Query response: '{resp_text}'");
+                    let response_body: graphql_client::Response<ResponseData> =
+                        serde_json::from_str(&resp_text)?;
+                    return Ok(response_body);
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = response
+                        .headers()
+                        .get(header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse().ok())
+                        .map(Duration::from_secs);
+                    let body = response
+                        .text()
+                        .await
+                        .unwrap_or_else(|_| "Could not read error body".to_string());
+                    let error =
+                        anyhow::anyhow!("Failed to fetch info. Status: {}. Body: {}", status, body);
+
+                    // Rate limited requests are rejected before running, so even mutations can
+                    // be sent again
+                    let retryable = status == StatusCode::TOO_MANY_REQUESTS
+                        || (idempotent && status.is_server_error());
+                    if !retryable {
+                        tracing::error!("start.gg graphql fetch failed: {} - {}", status, body);
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                // A request that couldn't connect never reached start.gg
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => (e.into(), None),
+                Err(e) => return Err(e.into()),
+            };
+
+            if attempt >= MAX_ATTEMPTS {
+                tracing::error!(
+                    "start.gg graphql fetch failed after {} attempts: {}",
+                    attempt,
+                    error
+                );
+                return Err(error);
+            }
+
+            let delay = retry_after
+                .unwrap_or(RETRY_BASE_DELAY * 2u32.pow(attempt - 1))
+                .min(MAX_RETRY_DELAY);
+            tracing::warn!(
+                "start.gg graphql fetch failed (attempt {}/{}), retrying in {:?}: {}",
+                attempt,
+                MAX_ATTEMPTS,
+                delay,
+                error
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::mock::MockStartGG;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recorded_tournament() {
+        let mock = MockStartGG::start().await;
+        let http_client = reqwest::Client::new();
+        let api = StartGGApi::new(mock.endpoint());
+        let client = StartGGClient::new(&http_client, &api, "token");
+
+        let tournament = client
+            .fetch_tournament(mock::TOURNAMENT_SLUG.to_string())
+            .await
+            .unwrap();
+        assert_eq!(tournament.slug, mock::TOURNAMENT_SLUG);
+        assert_eq!(tournament.name, "Copa Omega Brasil");
+        assert_eq!(tournament.images.len(), 2);
+
+        let teams = client
+            .fetch_tournament_teams(mock::TOURNAMENT_SLUG.to_string())
+            .await
+            .unwrap();
+        // The recorded response has a duplicated team, and one team that isn't an event team
+        assert_eq!(teams.len(), 2);
+        assert_eq!(teams[0].name, "Estrelas do Sul");
+        // Members without a discord connection are left out
+        assert_eq!(teams[0].team_members.len(), 3);
+        assert!(teams[0].team_members[0].capitain);
+        assert_eq!(teams[1].image.as_ref().map(|i| i.width), Some(200.0));
+
+        assert!(client
+            .fetch_tournament("nao-existe".to_string())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retries() {
+        let mock = MockStartGG::start().await;
+        let http_client = reqwest::Client::new();
+        let api = StartGGApi::new(mock.endpoint());
+        let client = StartGGClient::new(&http_client, &api, "token");
+
+        mock.fail_next(StatusCode::TOO_MANY_REQUESTS, 2);
+        client
+            .fetch_tournament(mock::TOURNAMENT_SLUG.to_string())
+            .await
+            .unwrap();
+        assert_eq!(mock.requests(), 3);
+
+        mock.fail_next(StatusCode::BAD_GATEWAY, MAX_ATTEMPTS as usize);
+        assert!(client
+            .fetch_tournament(mock::TOURNAMENT_SLUG.to_string())
+            .await
+            .is_err());
+        assert_eq!(mock.requests(), 3 + MAX_ATTEMPTS as usize);
+
+        mock.fail_next(StatusCode::UNAUTHORIZED, 1);
+        assert!(client
+            .fetch_tournament(mock::TOURNAMENT_SLUG.to_string())
+            .await
+            .is_err());
+        assert_eq!(mock.requests(), 4 + MAX_ATTEMPTS as usize);
+    }
+
+    #[tokio::test]
+    async fn test_mutations_not_retried_on_server_errors() {
+        let mock = MockStartGG::start().await;
+        let http_client = reqwest::Client::new();
+        let api = StartGGApi::new(mock.endpoint());
+        let client = StartGGClient::new(&http_client, &api, "token");
+        let query = json!({
+            "operationName": "Tournament",
+            "query": "",
+            "variables": { "tournament": mock::TOURNAMENT_SLUG },
+        });
 
-        // let response_body: graphql_client::Response<ResponseData> = response.json().await?;
-        let resp_text = response.text().await?;
-        tracing::trace!("Query response: '{resp_text}'");
-        let response_body: graphql_client::Response<ResponseData> =
-            serde_json::from_str(&resp_text)?;
+        mock.fail_next(StatusCode::INTERNAL_SERVER_ERROR, 1);
+        assert!(client
+            .graphql_mutation::<_, serde_json::Value>(&query)
+            .await
+            .is_err());
+        assert_eq!(mock.requests(), 1);
 
-        Ok(response_body)
+        mock.fail_next(StatusCode::TOO_MANY_REQUESTS, 1);
+        assert!(client
+            .graphql_mutation::<_, serde_json::Value>(&query)
+            .await
+            .is_ok());
+        assert_eq!(mock.requests(), 3);
     }
 }
@@ -0,0 +1,85 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// start.gg allows each token 80 requests every 60 seconds
+const MAX_REQUESTS: usize = 80;
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Sliding window of the requests made with each token, so we wait instead of getting 429s
+#[derive(Debug)]
+pub struct RateLimiter {
+    max_requests: usize,
+    window: Duration,
+    requests: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(MAX_REQUESTS, WINDOW)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: usize, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits until the token can make another request and counts it
+    pub async fn acquire(&self, token: &str) {
+        loop {
+            let wait = {
+                let mut requests = self.requests.lock().unwrap();
+                let now = Instant::now();
+                requests.retain(|_, sent| {
+                    while sent
+                        .front()
+                        .is_some_and(|t| now.duration_since(*t) >= self.window)
+                    {
+                        sent.pop_front();
+                    }
+                    !sent.is_empty()
+                });
+
+                let sent = requests.entry(token.to_string()).or_default();
+                match sent.front() {
+                    Some(oldest) if sent.len() >= self.max_requests => {
+                        self.window - now.duration_since(*oldest)
+                    }
+                    _ => {
+                        sent.push_back(now);
+                        return;
+                    }
+                }
+            };
+
+            tracing::warn!("start.gg rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let window = Duration::from_millis(200);
+        let limiter = RateLimiter::new(2, window);
+        let start = Instant::now();
+
+        limiter.acquire("a").await;
+        limiter.acquire("a").await;
+        // Other tokens have their own limit
+        limiter.acquire("b").await;
+        assert!(start.elapsed() < window);
+
+        limiter.acquire("a").await;
+        assert!(start.elapsed() >= window);
+    }
+}
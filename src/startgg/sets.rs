@@ -0,0 +1,542 @@
+use serde::{Deserialize, Serialize};
+
+use super::StartGGClient;
+
+use graphql_client::GraphQLQuery;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/startgg/graphql_schema.json",
+    query_path = "src/startgg/tournament.graphql",
+    response_derives = "Debug,Serialize"
+)]
+struct TournamentSets;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/startgg/graphql_schema.json",
+    query_path = "src/startgg/sets.graphql",
+    response_derives = "Debug,Serialize"
+)]
+struct TournamentSet;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/startgg/graphql_schema.json",
+    query_path = "src/startgg/sets.graphql",
+    response_derives = "Debug,Serialize"
+)]
+struct ReportBracketSet;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/startgg/graphql_schema.json",
+    query_path = "src/startgg/sets.graphql",
+    response_derives = "Debug,Serialize"
+)]
+struct PhaseGroupSets;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/startgg/graphql_schema.json",
+    query_path = "src/startgg/sets.graphql",
+    response_derives = "Debug,Serialize"
+)]
+struct TournamentPhaseGroups;
+
+use phase_group_sets::PhaseGroupSetsPhaseGroupSetsNodesSlotsEntrantTeam as PhaseGroupSetEntrantTeam;
+use tournament_set::TournamentSetSetSlotsEntrantTeam as SingleSetEntrantTeam;
+use tournament_sets::TournamentSetsTournamentEventsSetsNodesSlotsEntrantTeam as SetEntrantTeam;
+
+const SETS_PER_PAGE: i64 = 40;
+
+// Every query gets its own generated types, even when selecting the exact same set fields,
+// so the conversion into a `StartGGSet` is shared through a macro instead of a function.
+macro_rules! startgg_set_from_node {
+    ($node:expr, $entrant_team:ident, $event_id:expr, $event_name:expr) => {{
+        let s = $node;
+        Some(StartGGSet {
+            id: s.id?,
+            event_id: $event_id,
+            event_name: $event_name,
+            round: s.round,
+            round_name: s.full_round_text,
+            state: s.state.unwrap_or_default().into(),
+            winner_entrant_id: s.winner_id.map(|id| id.to_string()),
+            slots: s
+                .slots
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|slot| {
+                    let sl = slot?;
+                    let entrant = sl.entrant?;
+                    Some(StartGGSetSlot {
+                        entrant_id: entrant.id?,
+                        entrant_name: entrant.name,
+                        team_id: entrant.team.and_then(|team| match team {
+                            $entrant_team::GlobalTeam(t) => t.discriminator,
+                            $entrant_team::EventTeam(t) => t.global_team?.discriminator,
+                        }),
+                        score: sl
+                            .standing
+                            .and_then(|st| st.stats?.score?.value)
+                            .map(|v| v.max(0f64) as i32),
+                    })
+                })
+                .collect(),
+        })
+    }};
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartGGSetState {
+    NotStarted,
+    InProgress,
+    Completed,
+    Called,
+}
+
+impl From<i64> for StartGGSetState {
+    fn from(value: i64) -> Self {
+        match value {
+            2 => StartGGSetState::InProgress,
+            3 => StartGGSetState::Completed,
+            6 => StartGGSetState::Called,
+            _ => StartGGSetState::NotStarted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartGGSetSlot {
+    pub entrant_id: String,
+    pub entrant_name: Option<String>,
+    /// Same identifier used as `StartGGTeam::id`, so slots can be matched against the `team` table
+    pub team_id: Option<String>,
+    pub score: Option<i32>,
+}
+
+/// A played game of a set, with goals in the same team order as the set scores
+#[derive(Debug, Clone)]
+pub struct StartGGSetGame {
+    pub game_number: i32,
+    /// Same identifier as `StartGGSetSlot::team_id`
+    pub winner: String,
+    pub team_a_goals: i32,
+    pub team_b_goals: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartGGSet {
+    pub id: String,
+    pub event_id: Option<String>,
+    pub event_name: Option<String>,
+    /// Positive for the winners side of an elimination bracket, negative for the losers side
+    pub round: Option<i64>,
+    pub round_name: Option<String>,
+    pub state: StartGGSetState,
+    pub winner_entrant_id: Option<String>,
+    pub slots: Vec<StartGGSetSlot>,
+}
+
+impl StartGGSet {
+    pub fn slot_for_team(&self, team_id: &str) -> Option<&StartGGSetSlot> {
+        self.slots
+            .iter()
+            .find(|slot| slot.team_id.as_deref() == Some(team_id))
+    }
+
+    /// Whether this set is played between the two given teams, in any order
+    pub fn is_between(&self, team_a: &str, team_b: &str) -> bool {
+        team_a != team_b
+            && self.slot_for_team(team_a).is_some()
+            && self.slot_for_team(team_b).is_some()
+    }
+
+    /// Scores for the two given teams, in the same order they were passed
+    pub fn scores_for(&self, team_a: &str, team_b: &str) -> Option<(i32, i32)> {
+        let score_a = self.slot_for_team(team_a)?.score.unwrap_or_default();
+        let score_b = self.slot_for_team(team_b)?.score.unwrap_or_default();
+        Some((score_a, score_b))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartGGBracketType {
+    SingleElimination,
+    DoubleElimination,
+    RoundRobin,
+    Other,
+}
+
+macro_rules! impl_bracket_type_from {
+    ($bracket_type:ty) => {
+        impl From<$bracket_type> for StartGGBracketType {
+            fn from(value: $bracket_type) -> Self {
+                type B = $bracket_type;
+                match value {
+                    B::SINGLE_ELIMINATION => StartGGBracketType::SingleElimination,
+                    B::DOUBLE_ELIMINATION => StartGGBracketType::DoubleElimination,
+                    B::ROUND_ROBIN => StartGGBracketType::RoundRobin,
+                    _ => StartGGBracketType::Other,
+                }
+            }
+        }
+    };
+}
+
+impl_bracket_type_from!(phase_group_sets::BracketType);
+impl_bracket_type_from!(tournament_phase_groups::BracketType);
+
+/// A pool or bracket of a tournament phase, without its sets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartGGPhaseGroupInfo {
+    pub id: String,
+    pub event_name: Option<String>,
+    pub phase_name: Option<String>,
+    pub display_identifier: Option<String>,
+    pub bracket_type: StartGGBracketType,
+}
+
+impl StartGGPhaseGroupInfo {
+    pub fn display_name(&self) -> String {
+        [
+            self.event_name.as_deref(),
+            self.phase_name.as_deref(),
+            self.display_identifier.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" - ")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartGGPhaseGroup {
+    pub info: StartGGPhaseGroupInfo,
+    pub sets: Vec<StartGGSet>,
+}
+
+impl StartGGClient<'_> {
+    pub async fn fetch_tournament_sets(
+        &self,
+        tournament_slug: &str,
+    ) -> anyhow::Result<Vec<StartGGSet>> {
+        let mut sets = vec![];
+        let mut page = 1;
+
+        loop {
+            let var = tournament_sets::Variables {
+                tournament: Some(tournament_slug.to_string()),
+                page: Some(page),
+                per_page: Some(SETS_PER_PAGE),
+            };
+            let query = TournamentSets::build_query(var);
+
+            let response_body: graphql_client::Response<tournament_sets::ResponseData> =
+                self.graphql_request(&query).await?;
+
+            if let Some(err) = response_body.errors {
+                return Err(anyhow::anyhow!(
+                    "errors fetching tournament's sets: {err:?}"
+                ));
+            }
+
+            let events = response_body
+                .data
+                .and_then(|d| d.tournament)
+                .and_then(|t| t.events)
+                .ok_or(anyhow::anyhow!(
+                    "failed to get startgg sets for tournament '{tournament_slug}'"
+                ))?;
+
+            let mut total_pages = 0;
+            for event in events.into_iter().flatten() {
+                let Some(event_sets) = event.sets else {
+                    continue;
+                };
+                total_pages = total_pages.max(
+                    event_sets
+                        .page_info
+                        .and_then(|p| p.total_pages)
+                        .unwrap_or_default(),
+                );
+
+                sets.extend(
+                    event_sets
+                        .nodes
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|set| {
+                            startgg_set_from_node!(
+                                set?,
+                                SetEntrantTeam,
+                                event.id.clone(),
+                                event.name.clone()
+                            )
+                        }),
+                );
+            }
+
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(sets)
+    }
+
+    pub async fn fetch_set(&self, set_id: &str) -> anyhow::Result<StartGGSet> {
+        let var = tournament_set::Variables {
+            set_id: set_id.to_string(),
+        };
+        let query = TournamentSet::build_query(var);
+
+        let response_body: graphql_client::Response<tournament_set::ResponseData> =
+            self.graphql_request(&query).await?;
+
+        if let Some(err) = response_body.errors {
+            return Err(anyhow::anyhow!("errors fetching set info: {err:?}"));
+        }
+
+        let set: Option<StartGGSet> = (|| {
+            let s = response_body.data?.set?;
+            let event = s.event.as_ref();
+            let (event_id, event_name) = (
+                event.and_then(|e| e.id.clone()),
+                event.and_then(|e| e.name.clone()),
+            );
+            startgg_set_from_node!(s, SingleSetEntrantTeam, event_id, event_name)
+        })();
+
+        set.ok_or(anyhow::anyhow!(
+            "failed to get startgg set information for set '{set_id}'"
+        ))
+    }
+
+    pub async fn fetch_tournament_phase_groups(
+        &self,
+        tournament_slug: &str,
+    ) -> anyhow::Result<Vec<StartGGPhaseGroupInfo>> {
+        let var = tournament_phase_groups::Variables {
+            tournament: Some(tournament_slug.to_string()),
+        };
+        let query = TournamentPhaseGroups::build_query(var);
+
+        let response_body: graphql_client::Response<tournament_phase_groups::ResponseData> =
+            self.graphql_request(&query).await?;
+
+        if let Some(err) = response_body.errors {
+            return Err(anyhow::anyhow!(
+                "errors fetching tournament's phase groups: {err:?}"
+            ));
+        }
+
+        let events = response_body
+            .data
+            .and_then(|d| d.tournament)
+            .and_then(|t| t.events)
+            .ok_or(anyhow::anyhow!(
+                "failed to get startgg phase groups for tournament '{tournament_slug}'"
+            ))?;
+
+        Ok(events
+            .into_iter()
+            .flatten()
+            .flat_map(|event| {
+                let event_name = event.name;
+                event
+                    .phase_groups
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(move |pg| {
+                        Some(StartGGPhaseGroupInfo {
+                            id: pg.id?,
+                            event_name: event_name.clone(),
+                            phase_name: pg.phase.and_then(|p| p.name),
+                            display_identifier: pg.display_identifier,
+                            bracket_type: pg
+                                .bracket_type
+                                .map(Into::into)
+                                .unwrap_or(StartGGBracketType::Other),
+                        })
+                    })
+            })
+            .collect())
+    }
+
+    pub async fn fetch_phase_group(
+        &self,
+        phase_group_id: &str,
+    ) -> anyhow::Result<StartGGPhaseGroup> {
+        let mut info = None;
+        let mut sets = vec![];
+        let mut page = 1;
+
+        loop {
+            let var = phase_group_sets::Variables {
+                phase_group_id: Some(phase_group_id.to_string()),
+                page: Some(page),
+                per_page: Some(SETS_PER_PAGE),
+            };
+            let query = PhaseGroupSets::build_query(var);
+
+            let response_body: graphql_client::Response<phase_group_sets::ResponseData> =
+                self.graphql_request(&query).await?;
+
+            if let Some(err) = response_body.errors {
+                return Err(anyhow::anyhow!(
+                    "errors fetching phase group's sets: {err:?}"
+                ));
+            }
+
+            let phase_group =
+                response_body
+                    .data
+                    .and_then(|d| d.phase_group)
+                    .ok_or(anyhow::anyhow!(
+                        "failed to get startgg phase group '{phase_group_id}'"
+                    ))?;
+
+            if info.is_none() {
+                info = Some(StartGGPhaseGroupInfo {
+                    id: phase_group.id.unwrap_or(phase_group_id.to_string()),
+                    event_name: None,
+                    phase_name: phase_group.phase.and_then(|p| p.name),
+                    display_identifier: phase_group.display_identifier,
+                    bracket_type: phase_group
+                        .bracket_type
+                        .map(Into::into)
+                        .unwrap_or(StartGGBracketType::Other),
+                });
+            }
+
+            let Some(group_sets) = phase_group.sets else {
+                break;
+            };
+            let total_pages = group_sets
+                .page_info
+                .and_then(|p| p.total_pages)
+                .unwrap_or_default();
+            sets.extend(
+                group_sets
+                    .nodes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|set| {
+                        startgg_set_from_node!(set?, PhaseGroupSetEntrantTeam, None, None)
+                    }),
+            );
+
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(StartGGPhaseGroup {
+            info: info.ok_or(anyhow::anyhow!(
+                "failed to get startgg phase group '{phase_group_id}'"
+            ))?,
+            sets,
+        })
+    }
+
+    /// Reports the final score of a set to start.gg. The games, with goals in the same team order
+    /// as the scores, are reported as played when they add up to the score, otherwise only the
+    /// winner of the set is reported.
+    pub async fn report_set_score(
+        &self,
+        set_id: &str,
+        (team_a, team_a_score): (&str, i32),
+        (team_b, team_b_score): (&str, i32),
+        games: &[StartGGSetGame],
+    ) -> anyhow::Result<()> {
+        if team_a_score == team_b_score {
+            return Err(anyhow::anyhow!(
+                "cannot report a tied set ({team_a_score}-{team_b_score}) to start.gg"
+            ));
+        }
+
+        let set = self.fetch_set(set_id).await?;
+        let entrant_a = set
+            .slot_for_team(team_a)
+            .ok_or(anyhow::anyhow!("team {team_a} is not playing set {set_id}"))?
+            .entrant_id
+            .clone();
+        let entrant_b = set
+            .slot_for_team(team_b)
+            .ok_or(anyhow::anyhow!("team {team_b} is not playing set {set_id}"))?
+            .entrant_id
+            .clone();
+
+        let winner_id = if team_a_score > team_b_score {
+            entrant_a.clone()
+        } else {
+            entrant_b.clone()
+        };
+
+        let wins = |team: &str| games.iter().filter(|g| g.winner == team).count() as i32;
+        let games_match_score = wins(team_a) == team_a_score
+            && wins(team_b) == team_b_score
+            && games.len() as i32 == team_a_score + team_b_score;
+        // Entrant 1 is the first slot of the set, whichever team that is
+        let team_a_first = set
+            .slots
+            .first()
+            .is_some_and(|slot| slot.entrant_id == entrant_a);
+        let game_data = games_match_score.then(|| {
+            games
+                .iter()
+                .map(|game| {
+                    let winner = if game.winner == team_a {
+                        &entrant_a
+                    } else {
+                        &entrant_b
+                    };
+                    let (entrant1_goals, entrant2_goals) = if team_a_first {
+                        (game.team_a_goals, game.team_b_goals)
+                    } else {
+                        (game.team_b_goals, game.team_a_goals)
+                    };
+                    Some(report_bracket_set::BracketSetGameDataInput {
+                        winner_id: Some(winner.clone()),
+                        game_num: game.game_number.into(),
+                        entrant1_score: Some(entrant1_goals.into()),
+                        entrant2_score: Some(entrant2_goals.into()),
+                        stage_id: None,
+                        selections: None,
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let var = report_bracket_set::Variables {
+            set_id: set_id.to_string(),
+            winner_id: Some(winner_id),
+            game_data,
+        };
+        let query = ReportBracketSet::build_query(var);
+
+        let response_body: graphql_client::Response<report_bracket_set::ResponseData> =
+            self.graphql_mutation(&query).await?;
+
+        if let Some(err) = response_body.errors {
+            let messages = err
+                .into_iter()
+                .map(|e| e.message)
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(anyhow::anyhow!(
+                "start.gg refused to report set {set_id}: {messages}"
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -30,6 +30,11 @@ struct UserTournaments;
 )]
 struct Tournament;
 
+const TOURNAMENTS_PER_PAGE: i64 = 50;
+/// Upper bound on the pages requested, so a huge account can't stall every page load.
+/// Tournaments past it can't be managed by the user, which is logged when it happens.
+const MAX_TOURNAMENT_PAGES: i64 = 20;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StartGGImage {
     pub url: String,
@@ -64,62 +69,84 @@ pub struct StartGGTeam {
 }
 
 impl StartGGClient<'_> {
+    /// Every tournament the user is an admin of, going through all the pages
     pub async fn fetch_tournaments_organized_by_user(
         &self,
     ) -> anyhow::Result<Vec<StartGGTournament>> {
-        let var = user_tournaments::Variables {
-            page: Some(1),
-            per_page: Some(25),
-        };
-        let query = UserTournaments::build_query(var);
+        let mut tournaments = vec![];
+        let mut page = 1;
 
-        let response_body: graphql_client::Response<user_tournaments::ResponseData> =
-            self.graphql_request(&query).await?;
+        loop {
+            let var = user_tournaments::Variables {
+                page: Some(page),
+                per_page: Some(TOURNAMENTS_PER_PAGE),
+            };
+            let query = UserTournaments::build_query(var);
 
-        if let Some(err) = response_body.errors {
-            return Err(anyhow::anyhow!(
-                "errors fetching user's tournaments: {err:?}"
-            ));
-        }
+            let response_body: graphql_client::Response<user_tournaments::ResponseData> =
+                self.graphql_request(&query).await?;
 
-        let user: Option<Vec<StartGGTournament>> = (|| {
-            let cuser = response_body.data?.current_user?.tournaments?.nodes?;
-            let tournaments = cuser
-                .into_iter()
-                .filter(|t| {
-                    if let Some(tournaments) = t {
-                        tournaments.admins.is_some()
-                    } else {
-                        false
-                    }
-                })
-                .filter_map(|tournament| {
-                    let t = tournament?;
-                    Some(StartGGTournament {
-                        name: t.name?,
-                        images: t
-                            .images
-                            .unwrap_or_default()
-                            .into_iter()
-                            .flat_map(|image| {
-                                let i = image?;
-                                Some(StartGGImage {
-                                    url: i.url?,
-                                    height: i.height?,
-                                    width: i.width?,
+            if let Some(err) = response_body.errors {
+                return Err(anyhow::anyhow!(
+                    "errors fetching user's tournaments: {err:?}"
+                ));
+            }
+
+            let connection = response_body
+                .data
+                .and_then(|d| d.current_user)
+                .and_then(|u| u.tournaments)
+                .ok_or(anyhow::anyhow!("failed to get startgg user information"))?;
+
+            let total_pages = connection
+                .page_info
+                .and_then(|p| p.total_pages)
+                .unwrap_or_default();
+            tournaments.extend(
+                connection
+                    .nodes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flatten()
+                    .filter(|t| t.admins.is_some())
+                    .filter_map(|t| {
+                        Some(StartGGTournament {
+                            name: t.name?,
+                            images: t
+                                .images
+                                .unwrap_or_default()
+                                .into_iter()
+                                .flat_map(|image| {
+                                    let i = image?;
+                                    Some(StartGGImage {
+                                        url: i.url?,
+                                        height: i.height?,
+                                        width: i.width?,
+                                    })
                                 })
-                            })
-                            .collect(),
-                        slug: t.slug?.trim_start_matches("tournament/").to_string(),
-                        url: t.url?,
-                    })
-                })
-                .collect::<Vec<_>>();
+                                .collect(),
+                            slug: t.slug?.trim_start_matches("tournament/").to_string(),
+                            url: t.url?,
+                        })
+                    }),
+            );
 
-            Some(tournaments)
-        })();
+            if page >= MAX_TOURNAMENT_PAGES && total_pages > MAX_TOURNAMENT_PAGES {
+                tracing::warn!(
+                    "User admins more than {} tournaments, the ones past page {} of {} are left out",
+                    MAX_TOURNAMENT_PAGES * TOURNAMENTS_PER_PAGE,
+                    MAX_TOURNAMENT_PAGES,
+                    total_pages
+                );
+                break;
+            }
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+        }
 
-        user.ok_or(anyhow::anyhow!("failed to get startgg user information"))
+        Ok(tournaments)
     }
 
     pub async fn fetch_tournament(